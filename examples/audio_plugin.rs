@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    winit::{UpdateMode, WinitSettings},
+};
+use counter_bevy::audio::{
+    AudioErrorEvent, AudioPlugin,
+    mpris::MediaStateChanged,
+    ui::{spawn_media_controls, spawn_volume_slider},
+    volume::VolumeChanged,
+};
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugins(AudioPlugin)
+        // Volume and player changes arrive from the bus without any window event
+        .insert_resource(WinitSettings {
+            focused_mode: UpdateMode::reactive(Duration::from_millis(100)),
+            unfocused_mode: UpdateMode::reactive_low_power(Duration::from_millis(500)),
+        })
+        .add_systems(Startup, setup)
+        .add_systems(Update, log_audio_events)
+        .run();
+}
+
+fn setup(mut commands: Commands, assets: Res<AssetServer>) {
+    commands.spawn(Camera2d);
+    let font: Handle<Font> = assets.load("fonts/FiraSans-Bold.ttf");
+    spawn_volume_slider(
+        &mut commands,
+        font.clone(),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(30.0),
+            left: Val::Px(30.0),
+            ..default()
+        },
+    );
+    spawn_media_controls(
+        &mut commands,
+        font,
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(90.0),
+            left: Val::Px(30.0),
+            ..default()
+        },
+    );
+}
+
+fn log_audio_events(
+    mut volume: EventReader<VolumeChanged>,
+    mut media: EventReader<MediaStateChanged>,
+    mut errors: EventReader<AudioErrorEvent>,
+) {
+    for VolumeChanged(state) in volume.read() {
+        info!(
+            "{}: {:.0}% (muted: {})",
+            state.sink,
+            state.volume * 100.0,
+            state.muted
+        );
+    }
+    for MediaStateChanged(state) in media.read() {
+        info!("{}: {:?} {:?}", state.identity, state.status, state.title);
+    }
+    for error in errors.read() {
+        error!("{:?} failed: {}", error.action, error.reason);
+    }
+}
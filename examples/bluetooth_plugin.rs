@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    winit::{UpdateMode, WinitSettings},
+};
+use counter_bevy::bluetooth::{
+    BluetoothErrorEvent, BluetoothPlugin, state::BluetoothDeviceEvent, ui::spawn_bluetooth_panel,
+};
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugins(BluetoothPlugin)
+        // Device changes arrive from the bus without any window event
+        .insert_resource(WinitSettings {
+            focused_mode: UpdateMode::reactive(Duration::from_millis(100)),
+            unfocused_mode: UpdateMode::reactive_low_power(Duration::from_millis(500)),
+        })
+        .add_systems(Startup, setup)
+        .add_systems(Update, (log_device_events, show_errors))
+        .run();
+}
+
+#[derive(Component)]
+struct ErrorText;
+
+fn setup(mut commands: Commands, assets: Res<AssetServer>) {
+    commands.spawn(Camera2d);
+    spawn_bluetooth_panel(
+        &mut commands,
+        assets.load("fonts/FiraSans-Bold.ttf"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(30.0),
+            left: Val::Px(30.0),
+            min_width: Val::Px(360.0),
+            ..default()
+        },
+    );
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font: assets.load("fonts/FiraSans-Bold.ttf"),
+            font_size: 20.0,
+            ..default()
+        },
+        TextColor(Color::srgb(0.95, 0.4, 0.4)),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(20.0),
+            left: Val::Px(30.0),
+            ..default()
+        },
+        ErrorText,
+    ));
+}
+
+fn log_device_events(mut events: EventReader<BluetoothDeviceEvent>) {
+    for event in events.read() {
+        info!("{} ({}): {:?}", event.name, event.address, event.change);
+    }
+}
+
+fn show_errors(
+    mut events: EventReader<BluetoothErrorEvent>,
+    mut query: Query<&mut Text, With<ErrorText>>,
+) {
+    for event in events.read() {
+        for mut text in query.iter_mut() {
+            text.0 = format!("{:?} failed: {}", event.action, event.reason);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    winit::{UpdateMode, WinitSettings},
+};
+use counter_bevy::brightness::{
+    BrightnessChanged, BrightnessErrorEvent, BrightnessPlugin, ui::spawn_brightness_slider,
+};
+
+/// Pass a directory laid out like `/sys/class/backlight` to read devices from
+/// it instead.
+fn main() {
+    let plugin = match std::env::args().nth(1) {
+        Some(root) => BrightnessPlugin::with_root(root),
+        None => BrightnessPlugin::default(),
+    };
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugins(plugin)
+        // Devices are read again every second, keep updating to show it
+        .insert_resource(WinitSettings {
+            focused_mode: UpdateMode::reactive(Duration::from_millis(100)),
+            unfocused_mode: UpdateMode::reactive_low_power(Duration::from_secs(1)),
+        })
+        .add_systems(Startup, setup)
+        .add_systems(Update, log_brightness_events)
+        .run();
+}
+
+fn setup(mut commands: Commands, assets: Res<AssetServer>) {
+    commands.spawn(Camera2d);
+    spawn_brightness_slider(
+        &mut commands,
+        assets.load("fonts/FiraSans-Bold.ttf"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(30.0),
+            left: Val::Px(30.0),
+            ..default()
+        },
+    );
+}
+
+fn log_brightness_events(
+    mut changes: EventReader<BrightnessChanged>,
+    mut errors: EventReader<BrightnessErrorEvent>,
+) {
+    for BrightnessChanged(state) in changes.read() {
+        for backlight in &state.backlights {
+            info!(
+                "{}: {}/{}",
+                backlight.name, backlight.brightness, backlight.max_brightness
+            );
+        }
+    }
+    for error in errors.read() {
+        error!("Setting {} failed: {}", error.backlight, error.reason);
+    }
+}
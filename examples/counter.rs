@@ -4,6 +4,7 @@ use bevy::{
     tasks::{AsyncComputeTaskPool, Task},
     winit::WinitSettings,
 };
+use counter_bevy::counter::{Counter, CounterOp};
 use mechanix_debus_client::network_manager::handler::{
     NetworkManagerHandler, NetworkManagerRequest,
 };
@@ -22,7 +23,7 @@ fn main() {
         // Only run the app when there is user input. This will significantly reduce CPU/GPU use.
         .insert_resource(WinitSettings::desktop_app())
         // .init_resource::<Counter>()
-        .insert_resource(Counter(10))
+        .insert_resource(CounterState(Counter::new(10)))
         .insert_resource(WifiStatus(true))
         .add_systems(Startup, (setup, setup_async_task))
         .add_systems(Update, button_system)
@@ -68,18 +69,13 @@ fn setup_async_task(mut commands: Commands) {
     
 }
 
-#[derive(Resource, Component)]
-struct Counter(i32);
+/// Bevy adapter around the headless counter rules.
+#[derive(Resource, Deref, DerefMut)]
+struct CounterState(Counter);
 
 #[derive(Resource, Component)]
 struct WifiStatus(bool);
 
-impl Default for Counter {
-    fn default() -> Self {
-        Self(0)
-    }
-}
-
 #[derive(Clone, Copy, Component)]
 struct CounterText;
 
@@ -91,8 +87,8 @@ enum ButtonAction {
 }
 
 const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
-fn setup(mut commands: Commands, counter: Res<Counter>, assets: Res<AssetServer>) {
-    println!("counter in setup: {}", counter.0);
+fn setup(mut commands: Commands, counter: Res<CounterState>, assets: Res<AssetServer>) {
+    println!("counter in setup: {}", counter.value());
     // ui camera
     commands.spawn(Camera2d);
     // Text with one section
@@ -213,7 +209,7 @@ fn create_counter_text(commands: &mut Commands, counter_value: &Counter, assets:
             BackgroundColor(NORMAL_BUTTON),
         ))
         .with_child((
-            Text::new(&counter_value.0.to_string()),
+            Text::new(counter_value.value().to_string()),
             TextFont {
                 font: assets.load("fonts/FiraSans-Bold.ttf"),
                 font_size: 33.0,
@@ -238,7 +234,7 @@ fn button_system(
         Query<&mut Text, With<CounterText>>,
         Query<&mut Text>,
     )>,
-    mut counter: ResMut<Counter>,
+    mut counter: ResMut<CounterState>,
     mut wifi_status: ResMut<WifiStatus>,
     nm_req_sender: ResMut<NetworkRequestSender>,
 ) {
@@ -250,10 +246,10 @@ fn button_system(
 
                 match actions {
                     Some(ButtonAction::Increment) => {
-                        counter.0 += 1;
+                        counter.apply(CounterOp::Increment);
                     }
                     Some(ButtonAction::Decrement) => {
-                        counter.0 -= 1;
+                        counter.apply(CounterOp::Decrement);
                     }
                     Some(ButtonAction::Wifi) => {
                         handle_wifi(nm_req_sender.tx.clone(), wifi_status.0);
@@ -275,7 +271,7 @@ fn button_system(
     }
     for entity in queries.p1().iter_mut() {
         let mut text = entity;
-        text.0 = counter.0.to_string();
+        text.0 = counter.value().to_string();
     }
 }
 
@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    winit::{UpdateMode, WinitSettings},
+};
+use counter_bevy::logind::{
+    InhibitWhileActive, InhibitorLock, LoginErrorEvent, LoginPlugin, PrepareForSleep,
+    SessionLockEvent, ui::spawn_login_buttons,
+};
+
+/// Pass `--sleep` to also keep the machine from suspending while the window
+/// has focus, `--delay-sleep` to hold suspending off until "Going to sleep"
+/// was logged.
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let sleep = args.iter().any(|arg| arg == "--sleep");
+    let delay_sleep = args.iter().any(|arg| arg == "--delay-sleep");
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugins(LoginPlugin)
+        .insert_resource(InhibitWhileActive {
+            sleep,
+            delay_sleep,
+            ..default()
+        })
+        // logind signals arrive from the bus without any window event
+        .insert_resource(WinitSettings {
+            focused_mode: UpdateMode::reactive(Duration::from_millis(100)),
+            unfocused_mode: UpdateMode::reactive_low_power(Duration::from_millis(500)),
+        })
+        .add_systems(Startup, setup)
+        .add_systems(Update, (log_login_events, log_inhibitor_lock))
+        .run();
+}
+
+fn setup(mut commands: Commands, assets: Res<AssetServer>) {
+    commands.spawn(Camera2d);
+    spawn_login_buttons(
+        &mut commands,
+        assets.load("fonts/FiraSans-Bold.ttf"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(30.0),
+            left: Val::Px(30.0),
+            ..default()
+        },
+    );
+}
+
+fn log_login_events(
+    mut sleep: EventReader<PrepareForSleep>,
+    mut locks: EventReader<SessionLockEvent>,
+    mut errors: EventReader<LoginErrorEvent>,
+) {
+    for PrepareForSleep(start) in sleep.read() {
+        info!("{}", if *start { "Going to sleep" } else { "Resumed" });
+    }
+    for event in locks.read() {
+        info!("Session lock requested: {event:?}");
+    }
+    for error in errors.read() {
+        error!("{:?} failed: {}", error.action, error.reason);
+    }
+}
+
+fn log_inhibitor_lock(lock: Res<InhibitorLock>) {
+    if lock.is_changed() {
+        info!(
+            "Inhibitor lock held: {:?}, delaying sleep: {}",
+            lock.held(),
+            lock.delaying_sleep()
+        );
+    }
+}
@@ -1,261 +1,86 @@
-use crate::ErrorType::ToggleWifiError;
-use bevy::tasks::{AsyncComputeTaskPool, IoTaskPool};
-use bevy::{prelude::*, winit::WinitSettings};
-use freedesktop_network_manager_client::interfaces::wireless::WifiState;
-use freedesktop_network_manager_client::service::NetworkManagerService;
-use std::sync::mpsc::{Receiver, channel};
-use std::sync::{LazyLock, Mutex, mpsc};
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    winit::{UpdateMode, WinitSettings},
+};
+use counter_bevy::network_manager::{
+    ErrorType, NetworkAction, NetworkActionEvent, NetworkErrorEvent, NetworkManagerServicePlugin,
+    WifiStatusText,
+    airplane::{AirplaneMode, AirplaneModeChanged},
+    state::NetworkState,
+    ui::{spawn_access_point_list, spawn_connections_panel, spawn_network_status_icon},
+};
+
+const TOAST_DURATION: Duration = Duration::from_secs(4);
 
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins)
         .add_plugins(NetworkManagerServicePlugin)
-        .insert_resource(WinitSettings::desktop_app())
+        // Results arrive from the bus without any window event, wake up
+        // regularly to show them and to expire toasts.
+        .insert_resource(WinitSettings {
+            focused_mode: UpdateMode::reactive(Duration::from_millis(100)),
+            unfocused_mode: UpdateMode::reactive_low_power(Duration::from_millis(500)),
+        })
         .add_systems(Startup, setup)
+        .add_systems(
+            Update,
+            (
+                do_network_action,
+                update_wifi_button_label,
+                update_airplane_button_label,
+                display_wifi_errors,
+                expire_toasts,
+            ),
+        )
         .run();
 }
 
-/// Holds the async-initialized service, or None if not ready yet.
-#[derive(Resource)]
-pub struct NetworkManagerServiceResource {
-    pub service: Option<NetworkManagerService>,
-}
-#[derive(Resource)]
-pub struct WifiStateReceiver(pub Mutex<Receiver<WifiState>>);
-
-#[derive(Resource, Default)]
-struct WifiEventChannelInitialized(bool);
-
-#[derive(Resource, Clone)]
-pub struct WifiStatus {
-    pub connected: bool,
-    pub last_error: Option<String>,
-}
-#[derive(Resource)]
-pub struct WifiStatusReceiver {
-    receiver: Mutex<Receiver<WifiStatus>>,
-}
-#[derive(Debug, Clone)]
-pub enum NetworkAction {
-    ToggleWifi(bool), // true = enable, false = disable
-    SwitchNetwork(String), // network name or id
-                      // Add more actions as needed
-}
-#[derive(Event)]
-pub struct NetworkActionEvent(pub NetworkAction);
-#[derive(Debug, Clone)]
-pub enum ErrorType {
-    ToggleWifiError(String),
-}
-#[derive(Event)]
-pub struct WifiErrorEvent(pub ErrorType);
-
-pub struct NetworkManagerServicePlugin;
-
-impl Plugin for NetworkManagerServicePlugin {
-    fn build(&self, app: &mut App) {
-        app.add_event::<WifiStateEvent>()
-            .insert_resource(NetworkManagerServiceResource { service: None })
-            .insert_resource(WifiEventChannelInitialized(false))
-            .insert_resource(WifiStatus {
-                connected: false,
-                last_error: None,
-            })
-            .add_event::<NetworkActionEvent>()
-            .add_event::<WifiErrorEvent>()
-            .add_systems(Startup, init_network_manager_service) // Async task so temp move service result to static
-            .add_systems(Update, poll_service_init) // Once a service is initialized, it will move service from static to resource
-            .add_systems(Update, setup_wifi_event_channel_async) // Async task so temp move result to static
-            .add_systems(
-                Update,
-                (
-                    handle_network_action_events,
-                    poll_wifi_error_events.after(handle_network_action_events),
-                ),
-            )
-            .add_systems(
-                Update,
-                (
-                    poll_wifi_event_channel, // Once a channel is initialized, it will move a result from static to resource
-                    wifi_event_bridge_system.after(poll_wifi_event_channel),
-                ),
-            )
-            .add_systems(Update, handle_wifi_state_events);
-    }
-}
-/// Checks if the `NetworkManagerService` is ready (i.e. not None).
-///
-/// This is used to gate the execution of systems that depend on the service
-/// being available.
-fn service_ready(resource: Res<NetworkManagerServiceResource>) -> bool {
-    resource.service.is_some()
-}
-
-static SERVICE_RESULT: LazyLock<Mutex<Option<NetworkManagerService>>> =
-    LazyLock::new(|| Mutex::new(None));
-
-/// This plugin provides a resource for the `NetworkManagerService` which is
-/// initialized asynchronously on startup. It also provides a system for enabling
-/// WiFi.
-///
-/// The `NetworkManagerService` is not available until the `init_network_manager_service`
-/// system has completed. This is checked with the `service_ready` function.
-fn init_network_manager_service() {
-    IoTaskPool::get()
-        .spawn(async {
-            match NetworkManagerService::new().await {
-                Ok(service) => {
-                    let mut lock = SERVICE_RESULT.lock().unwrap();
-                    *lock = Some(service);
-                    info!("NetworkManagerService initialized!");
-                }
-                Err(e) => {
-                    error!("Failed to initialize NetworkManagerService: {e}");
-                }
-            }
-        })
-        .detach();
-}
-
-// Startup system: create channel and spawn async/event producer
-static WIFI_RX_RESULT: LazyLock<Mutex<Option<Receiver<WifiState>>>> =
-    LazyLock::new(|| Mutex::new(None));
-
-fn setup_wifi_event_channel_async(
-    service_res: Res<NetworkManagerServiceResource>,
-    mut wifi_channel_flag: ResMut<WifiEventChannelInitialized>,
-) {
-    if wifi_channel_flag.0 {
-        // Already initialized, do nothing
-        return;
-    }
-    if let Some(service) = &service_res.service {
-        let service = service.clone();
-        bevy::tasks::IoTaskPool::get()
-            .spawn(async move {
-                let receiver = service.subscribe_device_events().await;
-                *WIFI_RX_RESULT.lock().unwrap() = Some(receiver);
-            })
-            .detach();
-        wifi_channel_flag.0 = true; // Mark as initialized
-    }
-}
-
-// Polling system to insert the resource when ready
-fn poll_wifi_event_channel(mut commands: Commands) {
-    let mut lock = WIFI_RX_RESULT.lock().unwrap();
-    if let Some(rx) = lock.take() {
-        commands.insert_resource(WifiStateReceiver(Mutex::new(rx)));
-    }
-}
-// Polling system to move service from static to resource
-fn poll_service_init(mut resource: ResMut<NetworkManagerServiceResource>) {
-    let mut lock = SERVICE_RESULT.lock().unwrap();
-    if let Some(service) = lock.take() {
-        resource.service = Some(service);
-    }
-}
-
-fn wifi_event_bridge_system(
-    wifi_rx: Option<Res<WifiStateReceiver>>,
-    mut writer: EventWriter<WifiStateEvent>,
-) {
-    if let Some(wifi_rx) = wifi_rx {
-        let rx = wifi_rx.0.lock().unwrap();
-        while let Ok(wifi_state) = rx.try_recv() {
-            writer.write(WifiStateEvent(wifi_state));
-        }
-    }
-}
-
-fn handle_wifi_state_events(
-    mut reader: EventReader<WifiStateEvent>,
-    mut query: Query<&mut Text, With<WifiStatusText>>,
-) {
-    for event in reader.read().into_iter() {
-        info!("event reader: wifi state: {:?}", event.0);
-        for mut text in query.iter_mut() {
-            text.0 = event.0.to_string().clone();
-        }
-        // Handle logic here
-    }
-}
-fn handle_network_action_events(
-    mut events: EventReader<NetworkActionEvent>,
-    mut service: ResMut<NetworkManagerServiceResource>,
-    mut commands: Commands,
-) {
-    let (wifi_status_event_sender, wifi_status_event_receiver) = mpsc::channel();
-    let pool = AsyncComputeTaskPool::get();
-    for event in events.read() {
-        let NetworkActionEvent(action) = event;
-        match action {
-            NetworkAction::ToggleWifi(enable) => {
-                if let Some(service) = &mut service.service {
-                    let service = service.clone();
-                    let enable = *enable;
-                    let wifi_status_event_sender = wifi_status_event_sender.clone();
-                    pool.spawn(async move {
-                        if let Err(err) = service.toggle_wifi(enable).await {
-                            error!("failed to toggle wifi: {err}");
-                            let wifi_status = WifiStatus {
-                                connected: !enable,
-                                last_error: Some("Failed to toggle wifi".to_string()),
-                            };
-                            //Note: EventWriter is not thread safe, so use a std::mpsc
-                            let _ = wifi_status_event_sender.send(wifi_status);
-                        }
-                    })
-                    .detach();
-                }
-            }
-            NetworkAction::SwitchNetwork(network_id) => {
-                // handle switch network
-            } // Add more as needed
-        }
-    }
-    commands.insert_resource(WifiStatusReceiver {
-        receiver: Mutex::new(wifi_status_event_receiver),
-    });
+#[derive(Component)]
+enum ButtonAction {
+    Wifi,
+    AirplaneMode,
 }
 
-// Polling system to insert write error into an event
-fn poll_wifi_error_events(
-    mut error_event_writer: EventWriter<WifiErrorEvent>,
-    event_receiver: ResMut<WifiStatusReceiver>,
-) {
-    let receiver = event_receiver.receiver.lock().unwrap();
-    while let Ok(state) = receiver.try_recv() {
-        if let Some(last_error) = state.last_error {
-            error_event_writer.write(WifiErrorEvent(ToggleWifiError(last_error)));
-        }
-    }
-}
-#[derive(Event, Debug, Clone)]
-pub struct WifiStateEvent(pub WifiState);
+#[derive(Component)]
+struct WifiButtonLabel;
 
-#[derive(Clone, Copy, Component)]
-struct WifiStatusText;
+#[derive(Component)]
+struct AirplaneButtonLabel;
 
 #[derive(Component)]
-enum ButtonAction {
-    Wifi,
-}
+struct Toast(Timer);
+
+#[derive(Resource)]
+struct ToastFont(Handle<Font>);
 
 const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
 fn setup(mut commands: Commands, assets: Res<AssetServer>) {
     // ui camera
     commands.spawn(Camera2d);
+    commands.insert_resource(ToastFont(assets.load("fonts/FiraSans-Bold.ttf")));
     // Text with one section
 
     create_counter_text(&mut commands, &assets);
+    spawn_network_status_icon(
+        &mut commands,
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(63.0),
+            left: Val::Px(30.0),
+            ..default()
+        },
+    );
 
     commands
         .spawn((
             Button,
             Node {
-                width: Val::Px(100.0),
+                min_width: Val::Px(100.0),
                 height: Val::Px(65.0),
+                padding: UiRect::horizontal(Val::Px(12.0)),
                 border: UiRect::all(Val::Px(5.0)),
                 // horizontally center child text
                 justify_content: JustifyContent::Center,
@@ -279,7 +104,61 @@ fn setup(mut commands: Commands, assets: Res<AssetServer>) {
                 ..default()
             },
             TextColor(Color::srgb(0.9, 0.9, 0.9)),
+            WifiButtonLabel,
+        ));
+
+    commands
+        .spawn((
+            Button,
+            Node {
+                min_width: Val::Px(100.0),
+                height: Val::Px(45.0),
+                padding: UiRect::horizontal(Val::Px(12.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                position_type: PositionType::Absolute,
+                top: Val::Px(150.0),
+                left: Val::Px(660.0),
+                ..default()
+            },
+            BorderRadius::MAX,
+            BackgroundColor(NORMAL_BUTTON),
+            ButtonAction::AirplaneMode,
+        ))
+        .with_child((
+            Text::new("Airplane mode on"),
+            TextFont {
+                font: assets.load("fonts/FiraSans-Bold.ttf"),
+                font_size: 22.0,
+                ..default()
+            },
+            TextColor(Color::srgb(0.9, 0.9, 0.9)),
+            AirplaneButtonLabel,
         ));
+
+    spawn_access_point_list(
+        &mut commands,
+        assets.load("fonts/FiraSans-Bold.ttf"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(45.0),
+            left: Val::Px(260.0),
+            min_width: Val::Px(360.0),
+            ..default()
+        },
+    );
+
+    spawn_connections_panel(
+        &mut commands,
+        assets.load("fonts/FiraSans-Bold.ttf"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(250.0),
+            left: Val::Px(30.0),
+            min_width: Val::Px(200.0),
+            ..default()
+        },
+    );
 }
 
 fn create_counter_text(commands: &mut Commands, assets: &AssetServer) {
@@ -287,8 +166,9 @@ fn create_counter_text(commands: &mut Commands, assets: &AssetServer) {
         .spawn((
             Button,
             Node {
-                width: Val::Px(100.0),
+                min_width: Val::Px(100.0),
                 height: Val::Px(65.0),
+                padding: UiRect::horizontal(Val::Px(12.0)),
                 // border: UiRect::all(Val::Px(5.0)),
                 // horizontally center child text
                 justify_content: JustifyContent::Center,
@@ -304,7 +184,8 @@ fn create_counter_text(commands: &mut Commands, assets: &AssetServer) {
             BackgroundColor(NORMAL_BUTTON),
         ))
         .with_child((
-            Text::new("Connected"),
+            // Filled in from NetworkState by the plugin
+            Text::new(""),
             TextFont {
                 font: assets.load("fonts/FiraSans-Bold.ttf"),
                 font_size: 33.0,
@@ -315,19 +196,117 @@ fn create_counter_text(commands: &mut Commands, assets: &AssetServer) {
         ));
 }
 
-
-fn display_wifi_errors(mut events: EventReader<WifiErrorEvent> /* UI context */) {
-    for WifiErrorEvent(error) in events.read() {
-        match error {
-            ErrorType::ToggleWifiError(msg) => {
-                // Show the error message in your UI
-                println!("WiFi Toggle Error: {msg}");
-                // Or update a UI resource/component accordingly
-            } // Add more error variants as your enum grows
+fn do_network_action(
+    query: Query<(&Interaction, &ButtonAction), Changed<Interaction>>,
+    state: Res<NetworkState>,
+    airplane_mode: Res<AirplaneMode>,
+    mut event_writer: EventWriter<NetworkActionEvent>,
+) {
+    for (interaction, action) in query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match action {
+            ButtonAction::Wifi => {
+                event_writer.write(NetworkActionEvent(NetworkAction::ToggleWifi(
+                    !state.wireless_enabled,
+                )));
+            }
+            ButtonAction::AirplaneMode => {
+                event_writer.write(NetworkActionEvent(NetworkAction::AirplaneMode(
+                    !airplane_mode.enabled,
+                )));
+            }
         }
     }
 }
 
-fn do_network_action(mut event_writer: EventWriter<NetworkActionEvent>) {
-    event_writer.write(NetworkActionEvent(NetworkAction::ToggleWifi(true)));
-}
\ No newline at end of file
+fn update_airplane_button_label(
+    airplane_mode: Res<AirplaneMode>,
+    mut events: EventReader<AirplaneModeChanged>,
+    mut query: Query<&mut Text, With<AirplaneButtonLabel>>,
+) {
+    for event in events.read() {
+        info!("Airplane mode {}: {:?}", event.enabled, event.radios);
+    }
+    // Also follows radios switched outside of the app
+    if !airplane_mode.is_changed() {
+        return;
+    }
+    for mut text in query.iter_mut() {
+        text.0 = if airplane_mode.enabled {
+            "Airplane mode off".to_string()
+        } else {
+            "Airplane mode on".to_string()
+        };
+    }
+}
+
+fn update_wifi_button_label(
+    state: Res<NetworkState>,
+    mut query: Query<&mut Text, With<WifiButtonLabel>>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    for mut text in query.iter_mut() {
+        text.0 = if state.wireless_enabled {
+            "Wifi off".to_string()
+        } else {
+            "Wifi on".to_string()
+        };
+    }
+}
+
+fn display_wifi_errors(
+    mut commands: Commands,
+    mut events: EventReader<NetworkErrorEvent>,
+    font: Res<ToastFont>,
+    toasts: Query<(), With<Toast>>,
+) {
+    // Stack new toasts below the ones still shown
+    let shown = toasts.iter().count();
+    for (slot, NetworkErrorEvent(error)) in (shown..).zip(events.read()) {
+        let message = match error {
+            ErrorType::ToggleWifiError(msg) => format!("Could not toggle Wi-Fi: {msg}"),
+            ErrorType::ScanError(msg) => format!("Could not scan: {msg}"),
+            ErrorType::SwitchNetworkError { ssid, reason } => {
+                format!("Could not connect to {ssid}: {reason}")
+            }
+            ErrorType::VpnError { name, reason } => format!("VPN {name} failed: {reason}"),
+            ErrorType::WwanError(msg) => format!("Could not toggle mobile broadband: {msg}"),
+            ErrorType::AirplaneModeError(msg) => format!("Could not switch airplane mode: {msg}"),
+        };
+        commands
+            .spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(20.0 + 50.0 * slot as f32),
+                    right: Val::Px(20.0),
+                    padding: UiRect::axes(Val::Px(14.0), Val::Px(8.0)),
+                    ..default()
+                },
+                BackgroundColor(Color::srgb(0.6, 0.15, 0.15)),
+                BorderRadius::all(Val::Px(6.0)),
+                GlobalZIndex(20),
+                Toast(Timer::new(TOAST_DURATION, TimerMode::Once)),
+            ))
+            .with_child((
+                Text::new(message),
+                TextFont {
+                    font: font.0.clone(),
+                    font_size: 20.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.95, 0.95, 0.95)),
+            ));
+    }
+}
+
+fn expire_toasts(mut commands: Commands, time: Res<Time>, mut toasts: Query<(Entity, &mut Toast)>) {
+    for (entity, mut toast) in toasts.iter_mut() {
+        if toast.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    winit::{UpdateMode, WinitSettings},
+};
+use counter_bevy::notifications::{
+    Expiry, Notification, NotificationActionInvoked, NotificationClosed, NotificationError,
+    NotificationSent, NotificationsPlugin, SendNotification, Urgency,
+    server::NotificationServerPlugin, ui::spawn_toast_area,
+};
+
+const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
+
+/// Pass `--server` to show the notifications in this window instead of the
+/// desktop's notification server, which must not be running then.
+fn main() {
+    let serve = std::env::args().skip(1).any(|arg| arg == "--server");
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins)
+        .add_plugins(NotificationsPlugin)
+        // Replies arrive from the bus without any window event
+        .insert_resource(WinitSettings {
+            focused_mode: UpdateMode::reactive(Duration::from_millis(100)),
+            unfocused_mode: UpdateMode::reactive_low_power(Duration::from_millis(500)),
+        })
+        .add_systems(Startup, setup)
+        .add_systems(Update, (send_notifications, log_replies));
+    if serve {
+        app.add_plugins(NotificationServerPlugin);
+    }
+    app.run();
+}
+
+#[derive(Component, Clone, Copy)]
+enum SendButton {
+    Normal,
+    Critical,
+}
+
+fn setup(mut commands: Commands, assets: Res<AssetServer>) {
+    commands.spawn(Camera2d);
+    let font = assets.load("fonts/FiraSans-Bold.ttf");
+    for (i, (label, button)) in [
+        ("Send", SendButton::Normal),
+        ("Send critical", SendButton::Critical),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        commands
+            .spawn((
+                Button,
+                Node {
+                    padding: UiRect::axes(Val::Px(14.0), Val::Px(8.0)),
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(30.0 + 60.0 * i as f32),
+                    left: Val::Px(30.0),
+                    ..default()
+                },
+                BorderRadius::MAX,
+                BackgroundColor(NORMAL_BUTTON),
+                button,
+            ))
+            .with_child((
+                Text::new(label),
+                TextFont {
+                    font: font.clone(),
+                    font_size: 24.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.9, 0.9, 0.9)),
+            ));
+    }
+    // Only filled when NotificationServerPlugin is added
+    spawn_toast_area(
+        &mut commands,
+        font,
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(20.0),
+            right: Val::Px(20.0),
+            ..default()
+        },
+    );
+}
+
+fn send_notifications(
+    query: Query<(&Interaction, &SendButton), Changed<Interaction>>,
+    mut writer: EventWriter<SendNotification>,
+) {
+    for (interaction, button) in query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let notification = match button {
+            SendButton::Normal => Notification::new("Download finished")
+                .with_body("counter_bevy.tar.gz")
+                .with_action("open", "Open")
+                .with_action("folder", "Show in folder")
+                .with_expiry(Expiry::After(Duration::from_secs(8))),
+            SendButton::Critical => Notification::new("Battery low")
+                .with_body("Plug in the charger")
+                .with_urgency(Urgency::Critical),
+        };
+        writer.write(SendNotification(Notification {
+            app_name: "notifications example".to_string(),
+            ..notification
+        }));
+    }
+}
+
+fn log_replies(
+    mut sent: EventReader<NotificationSent>,
+    mut invoked: EventReader<NotificationActionInvoked>,
+    mut closed: EventReader<NotificationClosed>,
+    mut errors: EventReader<NotificationError>,
+) {
+    for event in sent.read() {
+        info!("Notification {} sent: {}", event.id, event.summary);
+    }
+    for event in invoked.read() {
+        info!("Notification {}: action {}", event.id, event.action_key);
+    }
+    for event in closed.read() {
+        info!("Notification {} closed: {:?}", event.id, event.reason);
+    }
+    for NotificationError(error) in errors.read() {
+        error!("Notification failed: {error}");
+    }
+}
@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    winit::{UpdateMode, WinitSettings},
+};
+use counter_bevy::power::{PowerPlugin, PowerStateChanged, ui::spawn_battery_indicator};
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugins(PowerPlugin)
+        // UPower changes arrive from the bus without any window event
+        .insert_resource(WinitSettings {
+            focused_mode: UpdateMode::reactive(Duration::from_secs(1)),
+            unfocused_mode: UpdateMode::reactive_low_power(Duration::from_secs(5)),
+        })
+        .add_systems(Startup, setup)
+        .add_systems(Update, log_power_changes)
+        .run();
+}
+
+fn setup(mut commands: Commands, assets: Res<AssetServer>) {
+    commands.spawn(Camera2d);
+    spawn_battery_indicator(
+        &mut commands,
+        assets.load("fonts/FiraSans-Bold.ttf"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(20.0),
+            right: Val::Px(20.0),
+            ..default()
+        },
+    );
+}
+
+fn log_power_changes(mut events: EventReader<PowerStateChanged>) {
+    for PowerStateChanged(state) in events.read() {
+        info!("{state} (on battery: {})", state.on_battery);
+    }
+}
@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use counter_bevy::AddNotificationEvent;
+use counter_bevy::{AddNotificationEvent, color::BarColor};
 use tokio::time;
 use zbus::{
     connection,
@@ -62,10 +62,7 @@ pub async fn event_notification_stream(
     loop {
         interval.tick().await;
         // Generate a random color
-        let r: f32 = fastrand::f32();
-        let g: f32 = fastrand::f32();
-        let b: f32 = fastrand::f32();
-        let color_str = format!("RGB({}, {}, {})", r, g, b);
+        let color_str = BarColor::random().to_string();
         // Send signal if there's a change in status
         let ctxt = match SignalContext::new(conn, "/org/mechanix/services/Add") {
             Ok(ctxt) => ctxt,
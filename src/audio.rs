@@ -0,0 +1,268 @@
+pub mod mpris;
+pub mod proxies;
+pub mod ui;
+pub mod volume;
+
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, IoTaskPool, Task, block_on, futures_lite::future},
+};
+use mpris::{
+    MediaState, MediaStateChanged, MediaStateReceiver, follow_media_state, sync_media_state,
+};
+use tokio::sync::watch;
+use volume::{
+    VolumeChanged, VolumeState, VolumeStateReceiver, connect_pulse_audio, follow_volume,
+    sync_volume_state,
+};
+use zbus::Connection;
+
+use crate::result_channel::ResultChannel;
+
+/// Connections used by the plugin. `pulse_audio` is a peer-to-peer connection
+/// to PulseAudio's own D-Bus server, and is None when the server does not
+/// expose one, as with PipeWire.
+#[derive(Resource, Default)]
+pub struct AudioBus {
+    pub pulse_audio: Option<Connection>,
+    pub session: Option<Connection>,
+}
+
+#[derive(Resource)]
+struct BusInitTask(Task<(Option<Connection>, Option<Connection>)>);
+
+/// Latest volume asked for, picked up by the task writing it. Dragging the
+/// slider asks for one per frame; writing them one at a time and skipping
+/// those already replaced keeps an older one from landing last.
+#[derive(Resource)]
+struct VolumeWriter(watch::Sender<f32>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AudioAction {
+    /// 1.0 is 100%, averaged over the channels of the default sink, whose
+    /// balance is kept.
+    SetVolume(f32),
+    SetMuted(bool),
+    /// Media actions apply to the player in `MediaState`.
+    PlayPause,
+    Next,
+    Previous,
+}
+
+#[derive(Event)]
+pub struct AudioActionEvent(pub AudioAction);
+
+/// Sent when an `AudioAction` failed. Successful actions show up in
+/// `VolumeState` and `MediaState`.
+#[derive(Event, Debug, Clone)]
+pub struct AudioErrorEvent {
+    pub action: AudioAction,
+    pub reason: String,
+}
+
+/// Follows the default PulseAudio sink into `VolumeState` and the current
+/// MPRIS media player into `MediaState`, and runs `AudioActionEvent`s.
+pub struct AudioPlugin;
+
+impl Plugin for AudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AudioBus>()
+            .init_resource::<VolumeState>()
+            .init_resource::<MediaState>()
+            .init_resource::<ResultChannel<AudioErrorEvent>>()
+            .add_event::<AudioActionEvent>()
+            .add_event::<AudioErrorEvent>()
+            .add_event::<VolumeChanged>()
+            .add_event::<MediaStateChanged>()
+            .add_systems(Startup, init_bus_connections)
+            .add_systems(
+                Update,
+                (
+                    poll_bus_init,
+                    sync_volume_state.after(poll_bus_init),
+                    sync_media_state.after(poll_bus_init),
+                    handle_audio_action_events,
+                    poll_audio_results.after(handle_audio_action_events),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    ui::volume_slider_system,
+                    ui::media_button_system,
+                    ui::update_volume_slider.after(sync_volume_state),
+                    ui::update_media_controls.after(sync_media_state),
+                ),
+            );
+    }
+}
+
+fn init_bus_connections(mut commands: Commands) {
+    let task = IoTaskPool::get().spawn(async {
+        let session = match Connection::session().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                error!("Failed to connect to the session bus: {e}");
+                return (None, None);
+            }
+        };
+        let pulse_audio = match connect_pulse_audio(&session).await {
+            Ok(connection) => Some(connection),
+            Err(e) => {
+                error!("Failed to connect to PulseAudio's D-Bus server: {e}");
+                None
+            }
+        };
+        (pulse_audio, Some(session))
+    });
+    commands.insert_resource(BusInitTask(task));
+}
+
+fn poll_bus_init(
+    mut commands: Commands,
+    task: Option<ResMut<BusInitTask>>,
+    mut bus: ResMut<AudioBus>,
+    results: Res<ResultChannel<AudioErrorEvent>>,
+) {
+    let Some(mut task) = task else {
+        return;
+    };
+    let Some((pulse_audio, session)) = block_on(future::poll_once(&mut task.0)) else {
+        return;
+    };
+    if let Some(connection) = &pulse_audio {
+        let (tx, rx) = watch::channel(VolumeState::default());
+        let follower = connection.clone();
+        IoTaskPool::get()
+            .spawn(async move {
+                if let Err(e) = follow_volume(&follower, &tx).await {
+                    error!("Failed to follow the volume: {e}");
+                }
+            })
+            .detach();
+        commands.insert_resource(VolumeStateReceiver(rx));
+
+        let (tx, mut rx) = watch::channel(0.0);
+        let connection = connection.clone();
+        let sender = results.sender();
+        IoTaskPool::get()
+            .spawn(async move {
+                while rx.changed().await.is_ok() {
+                    let action = AudioAction::SetVolume(*rx.borrow_and_update());
+                    if let Err(err) = run_action(&connection, None, &action).await {
+                        error!("Audio action {action:?} failed: {err}");
+                        let _ = sender.send(AudioErrorEvent {
+                            action,
+                            reason: err.to_string(),
+                        });
+                    }
+                }
+            })
+            .detach();
+        commands.insert_resource(VolumeWriter(tx));
+    }
+    if let Some(connection) = &session {
+        let (tx, rx) = watch::channel(MediaState::default());
+        let connection = connection.clone();
+        IoTaskPool::get()
+            .spawn(async move {
+                if let Err(e) = follow_media_state(&connection, &tx).await {
+                    error!("Failed to follow the media players: {e}");
+                }
+            })
+            .detach();
+        commands.insert_resource(MediaStateReceiver(rx));
+    }
+    bus.pulse_audio = pulse_audio;
+    bus.session = session;
+    commands.remove_resource::<BusInitTask>();
+}
+
+fn handle_audio_action_events(
+    mut events: EventReader<AudioActionEvent>,
+    bus: Res<AudioBus>,
+    media: Res<MediaState>,
+    volume_writer: Option<Res<VolumeWriter>>,
+    results: Res<ResultChannel<AudioErrorEvent>>,
+    mut error_writer: EventWriter<AudioErrorEvent>,
+) {
+    let pool = AsyncComputeTaskPool::get();
+    for AudioActionEvent(action) in events.read() {
+        let fail = |reason: &str| AudioErrorEvent {
+            action: action.clone(),
+            reason: reason.to_string(),
+        };
+        let target = match action {
+            AudioAction::SetVolume(volume) => {
+                match &volume_writer {
+                    Some(writer) => {
+                        writer.0.send_replace(*volume);
+                    }
+                    None => {
+                        error_writer.write(fail("PulseAudio's D-Bus server is not available"));
+                    }
+                }
+                continue;
+            }
+            AudioAction::SetMuted(_) => match &bus.pulse_audio {
+                Some(connection) => (connection.clone(), None),
+                None => {
+                    error_writer.write(fail("PulseAudio's D-Bus server is not available"));
+                    continue;
+                }
+            },
+            AudioAction::PlayPause | AudioAction::Next | AudioAction::Previous => {
+                let Some(connection) = &bus.session else {
+                    error_writer.write(fail("the session bus is not available"));
+                    continue;
+                };
+                let Some(player) = &media.player else {
+                    error_writer.write(fail("no media player"));
+                    continue;
+                };
+                (connection.clone(), Some(player.clone()))
+            }
+        };
+        let action = action.clone();
+        let sender = results.sender();
+        pool.spawn(async move {
+            let (connection, player) = target;
+            if let Err(err) = run_action(&connection, player, &action).await {
+                error!("Audio action {action:?} failed: {err}");
+                let _ = sender.send(AudioErrorEvent {
+                    action,
+                    reason: err.to_string(),
+                });
+            }
+        })
+        .detach();
+    }
+}
+
+async fn run_action(
+    connection: &Connection,
+    player: Option<String>,
+    action: &AudioAction,
+) -> zbus::Result<()> {
+    let player = match player {
+        Some(name) => Some(mpris::player(connection, name).await?),
+        None => None,
+    };
+    match (action, player) {
+        (AudioAction::SetVolume(volume), _) => volume::set_volume(connection, *volume).await,
+        (AudioAction::SetMuted(muted), _) => volume::set_muted(connection, *muted).await,
+        (AudioAction::PlayPause, Some(player)) => player.play_pause().await,
+        (AudioAction::Next, Some(player)) => player.next().await,
+        (AudioAction::Previous, Some(player)) => player.previous().await,
+        (_, None) => Err(zbus::Error::Failure("no media player".to_string())),
+    }
+}
+
+fn poll_audio_results(
+    results: Res<ResultChannel<AudioErrorEvent>>,
+    mut error_writer: EventWriter<AudioErrorEvent>,
+) {
+    for error in results.drain() {
+        error_writer.write(error);
+    }
+}
@@ -0,0 +1,184 @@
+//! Playback state of media players, through MPRIS on the session bus.
+
+use bevy::{
+    prelude::*,
+    tasks::futures_lite::{StreamExt, future},
+};
+use tokio::sync::watch;
+use zbus::{Connection, MatchRule, MessageStream, fdo::DBusProxy, message, zvariant::Value};
+
+use super::proxies::{MPRIS_PATH, MPRIS_PREFIX, MediaPlayerProxy, PlayerProxy};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PlaybackStatus {
+    Playing,
+    Paused,
+    #[default]
+    Stopped,
+}
+
+impl From<&str> for PlaybackStatus {
+    fn from(value: &str) -> Self {
+        match value {
+            "Playing" => PlaybackStatus::Playing,
+            "Paused" => PlaybackStatus::Paused,
+            _ => PlaybackStatus::Stopped,
+        }
+    }
+}
+
+/// The player being followed: the first one playing, else the first one
+/// found. Updated by the plugin as players change.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Eq)]
+pub struct MediaState {
+    /// Bus name of the player, None without any.
+    pub player: Option<String>,
+    /// Name of the player application, such as `Spotify`.
+    pub identity: String,
+    pub status: PlaybackStatus,
+    pub title: Option<String>,
+    /// Artists joined with commas.
+    pub artist: Option<String>,
+    pub can_go_next: bool,
+    pub can_go_previous: bool,
+    pub can_pause: bool,
+}
+
+async fn player_names(connection: &Connection) -> zbus::Result<Vec<String>> {
+    let mut names: Vec<String> = DBusProxy::new(connection)
+        .await?
+        .list_names()
+        .await?
+        .into_iter()
+        .map(|name| name.to_string())
+        .filter(|name| name.starts_with(MPRIS_PREFIX))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+async fn read_player(connection: &Connection, name: String) -> zbus::Result<MediaState> {
+    let player = PlayerProxy::builder(connection)
+        .destination(name.clone())?
+        .build()
+        .await?;
+    let identity = MediaPlayerProxy::builder(connection)
+        .destination(name.clone())?
+        .build()
+        .await?
+        .identity()
+        .await
+        // Optional in practice, some players leave it out
+        .unwrap_or_else(|_| name.trim_start_matches(MPRIS_PREFIX).to_string());
+    let metadata = player.metadata().await.unwrap_or_default();
+    let title = match metadata.get("xesam:title").map(|value| &**value) {
+        Some(Value::Str(title)) => Some(title.to_string()),
+        _ => None,
+    };
+    let artist = metadata
+        .get("xesam:artist")
+        .and_then(|value| value.try_clone().ok())
+        .and_then(|value| Vec::<String>::try_from(value).ok())
+        .filter(|artists| !artists.is_empty())
+        .map(|artists| artists.join(", "));
+    Ok(MediaState {
+        player: Some(name),
+        identity,
+        status: PlaybackStatus::from(player.playback_status().await?.as_str()),
+        title,
+        artist,
+        can_go_next: player.can_go_next().await.unwrap_or(false),
+        can_go_previous: player.can_go_previous().await.unwrap_or(false),
+        can_pause: player.can_pause().await.unwrap_or(false),
+    })
+}
+
+pub async fn read_media_state(connection: &Connection) -> zbus::Result<MediaState> {
+    let mut first = None;
+    for name in player_names(connection).await? {
+        // Players may quit while being read
+        let Ok(state) = read_player(connection, name).await else {
+            continue;
+        };
+        if state.status == PlaybackStatus::Playing {
+            return Ok(state);
+        }
+        first.get_or_insert(state);
+    }
+    Ok(first.unwrap_or_default())
+}
+
+/// Sends the current state, then a new one whenever a player changes a
+/// property, appears or goes away. Returns once `tx` has no receivers.
+pub async fn follow_media_state(
+    connection: &Connection,
+    tx: &watch::Sender<MediaState>,
+) -> zbus::Result<()> {
+    let properties_rule = MatchRule::builder()
+        .msg_type(message::Type::Signal)
+        .interface("org.freedesktop.DBus.Properties")?
+        .member("PropertiesChanged")?
+        .path(MPRIS_PATH)?
+        .build();
+    let mut property_changes =
+        MessageStream::for_match_rule(properties_rule, connection, None).await?;
+    let owners_rule = MatchRule::builder()
+        .msg_type(message::Type::Signal)
+        .sender("org.freedesktop.DBus")?
+        .member("NameOwnerChanged")?
+        .arg0ns(MPRIS_PREFIX.trim_end_matches('.'))?
+        .build();
+    let mut owner_changes = MessageStream::for_match_rule(owners_rule, connection, None).await?;
+
+    loop {
+        match read_media_state(connection).await {
+            Ok(state) => {
+                tx.send_if_modified(|current| {
+                    let modified = *current != state;
+                    *current = state;
+                    modified
+                });
+            }
+            Err(e) => error!("Error reading the media players: {e}"),
+        }
+        if tx.is_closed() {
+            return Ok(());
+        }
+        let property_changed = async { property_changes.next().await.is_some() };
+        let owner_changed = async { owner_changes.next().await.is_some() };
+        if !future::or(property_changed, owner_changed).await {
+            return Ok(());
+        }
+    }
+}
+
+pub(crate) async fn player(
+    connection: &Connection,
+    name: String,
+) -> zbus::Result<PlayerProxy<'static>> {
+    PlayerProxy::builder(connection)
+        .destination(name)?
+        .build()
+        .await
+}
+
+/// Sent whenever `MediaState` changes.
+#[derive(Event, Debug, Clone)]
+pub struct MediaStateChanged(pub MediaState);
+
+#[derive(Resource)]
+pub(crate) struct MediaStateReceiver(pub(crate) watch::Receiver<MediaState>);
+
+pub(crate) fn sync_media_state(
+    receiver: Option<ResMut<MediaStateReceiver>>,
+    mut state: ResMut<MediaState>,
+    mut writer: EventWriter<MediaStateChanged>,
+) {
+    let Some(mut receiver) = receiver else {
+        return;
+    };
+    if receiver.0.has_changed().unwrap_or(false) {
+        *state = receiver.0.borrow_and_update().clone();
+        writer.write(MediaStateChanged(state.clone()));
+    }
+}
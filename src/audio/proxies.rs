@@ -0,0 +1,102 @@
+//! Subsets of the PulseAudio D-Bus protocol and of MPRIS used by the plugin.
+
+use std::collections::HashMap;
+
+use zbus::{
+    Result as ZbusResult, proxy,
+    zvariant::{ObjectPath, OwnedObjectPath, OwnedValue},
+};
+
+/// Volume of a channel at 100%.
+pub const PA_VOLUME_NORM: u32 = 0x10000;
+
+pub const MPRIS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+pub const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+
+/// Tells where the PulseAudio D-Bus server listens. Only there when
+/// `module-dbus-protocol` is loaded.
+#[proxy(
+    interface = "org.PulseAudio.ServerLookup1",
+    default_service = "org.PulseAudio1",
+    default_path = "/org/pulseaudio/server_lookup1"
+)]
+pub trait ServerLookup {
+    #[zbus(property)]
+    fn address(&self) -> ZbusResult<String>;
+}
+
+// The objects below live on the peer-to-peer connection to that server, which
+// ignores the destination.
+
+#[proxy(
+    interface = "org.PulseAudio.Core1",
+    default_service = "org.PulseAudio1",
+    default_path = "/org/pulseaudio/core1"
+)]
+pub trait Core {
+    /// Signals are only sent to clients that asked for them. An empty
+    /// `objects` listens on all objects.
+    fn listen_for_signal(&self, signal: &str, objects: &[ObjectPath<'_>]) -> ZbusResult<()>;
+
+    #[zbus(property)]
+    fn fallback_sink(&self) -> ZbusResult<OwnedObjectPath>;
+}
+
+#[proxy(
+    interface = "org.PulseAudio.Core1.Device",
+    default_service = "org.PulseAudio1"
+)]
+pub trait PulseDevice {
+    #[zbus(property)]
+    fn name(&self) -> ZbusResult<String>;
+
+    /// One value per channel, `PA_VOLUME_NORM` being 100%.
+    #[zbus(property)]
+    fn volume(&self) -> ZbusResult<Vec<u32>>;
+
+    #[zbus(property)]
+    fn set_volume(&self, volume: &[u32]) -> ZbusResult<()>;
+
+    #[zbus(property)]
+    fn mute(&self) -> ZbusResult<bool>;
+
+    #[zbus(property)]
+    fn set_mute(&self, mute: bool) -> ZbusResult<()>;
+}
+
+#[proxy(
+    interface = "org.mpris.MediaPlayer2",
+    default_path = "/org/mpris/MediaPlayer2"
+)]
+pub trait MediaPlayer {
+    #[zbus(property)]
+    fn identity(&self) -> ZbusResult<String>;
+}
+
+#[proxy(
+    interface = "org.mpris.MediaPlayer2.Player",
+    default_path = "/org/mpris/MediaPlayer2"
+)]
+pub trait Player {
+    fn play_pause(&self) -> ZbusResult<()>;
+
+    fn next(&self) -> ZbusResult<()>;
+
+    fn previous(&self) -> ZbusResult<()>;
+
+    /// `Playing`, `Paused` or `Stopped`.
+    #[zbus(property)]
+    fn playback_status(&self) -> ZbusResult<String>;
+
+    #[zbus(property)]
+    fn metadata(&self) -> ZbusResult<HashMap<String, OwnedValue>>;
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> ZbusResult<bool>;
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> ZbusResult<bool>;
+
+    #[zbus(property)]
+    fn can_pause(&self) -> ZbusResult<bool>;
+}
@@ -0,0 +1,304 @@
+//! Ready-made volume slider and media controls for the audio plugin.
+
+use bevy::{prelude::*, ui::RelativeCursorPosition};
+
+use super::{
+    AudioAction, AudioActionEvent,
+    mpris::{MediaState, PlaybackStatus},
+    volume::VolumeState,
+};
+
+const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
+const HOVERED_BUTTON: Color = Color::srgb(0.25, 0.25, 0.25);
+const TRACK: Color = Color::srgb(0.2, 0.2, 0.2);
+const FILL: Color = Color::srgb(0.3, 0.6, 0.95);
+const MUTED_FILL: Color = Color::srgb(0.45, 0.45, 0.45);
+const DIM_TEXT: Color = Color::srgb(0.6, 0.6, 0.6);
+
+/// Volume slider driven by [`VolumeState`].
+#[derive(Component)]
+pub struct VolumeSlider;
+
+/// Track of the slider, pressing or dragging along it sets the volume.
+#[derive(Component)]
+pub(crate) struct VolumeTrack;
+
+#[derive(Component)]
+pub(crate) struct VolumeFill;
+
+#[derive(Component)]
+pub(crate) struct VolumeText;
+
+#[derive(Component)]
+pub(crate) struct MuteButton;
+
+/// Media controls driven by [`MediaState`].
+#[derive(Component)]
+pub struct MediaControls;
+
+#[derive(Component)]
+pub(crate) struct MediaTitle;
+
+#[derive(Component)]
+pub(crate) struct MediaArtist;
+
+/// What pressing a button of the media controls does.
+#[derive(Component, Clone)]
+pub struct MediaButton(pub AudioAction);
+
+/// Spawns a mute button, a track filled up to the volume, from 0 to 100%, and
+/// the volume as text.
+pub fn spawn_volume_slider(commands: &mut Commands, font: Handle<Font>, node: Node) -> Entity {
+    let text_font = TextFont {
+        font,
+        font_size: 20.0,
+        ..default()
+    };
+    commands
+        .spawn((
+            Node {
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(10.0),
+                ..node
+            },
+            VolumeSlider,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(70.0),
+                        padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    BorderRadius::all(Val::Px(4.0)),
+                    BackgroundColor(NORMAL_BUTTON),
+                    MuteButton,
+                ))
+                .with_child((
+                    Text::new("Mute"),
+                    text_font.clone(),
+                    TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                ));
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(200.0),
+                        height: Val::Px(12.0),
+                        ..default()
+                    },
+                    BackgroundColor(TRACK),
+                    BorderRadius::MAX,
+                    RelativeCursorPosition::default(),
+                    VolumeTrack,
+                ))
+                .with_child((
+                    Node {
+                        width: Val::Percent(0.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    BackgroundColor(FILL),
+                    BorderRadius::MAX,
+                    VolumeFill,
+                ));
+            parent.spawn((
+                Text::new(""),
+                text_font,
+                TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                VolumeText,
+            ));
+        })
+        .id()
+}
+
+/// Spawns the title and artist of the current player over previous,
+/// play/pause and next buttons.
+pub fn spawn_media_controls(commands: &mut Commands, font: Handle<Font>, node: Node) -> Entity {
+    let text_font = |font_size| TextFont {
+        font: font.clone(),
+        font_size,
+        ..default()
+    };
+    commands
+        .spawn((
+            Node {
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                ..node
+            },
+            MediaControls,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("No media player"),
+                text_font(20.0),
+                TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                MediaTitle,
+            ));
+            parent.spawn((
+                Text::new(""),
+                text_font(16.0),
+                TextColor(DIM_TEXT),
+                MediaArtist,
+            ));
+            parent
+                .spawn(Node {
+                    column_gap: Val::Px(6.0),
+                    ..default()
+                })
+                .with_children(|buttons| {
+                    for (label, action) in [
+                        ("Prev", AudioAction::Previous),
+                        ("Play", AudioAction::PlayPause),
+                        ("Next", AudioAction::Next),
+                    ] {
+                        spawn_media_button(buttons, text_font(18.0), label, action);
+                    }
+                });
+        })
+        .id()
+}
+
+fn spawn_media_button(
+    parent: &mut ChildSpawnerCommands,
+    font: TextFont,
+    label: &str,
+    action: AudioAction,
+) {
+    parent
+        .spawn((
+            Button,
+            Node {
+                width: Val::Px(70.0),
+                padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            BorderRadius::all(Val::Px(4.0)),
+            BackgroundColor(NORMAL_BUTTON),
+            MediaButton(action),
+        ))
+        .with_child((
+            Text::new(label),
+            font,
+            TextColor(Color::srgb(0.9, 0.9, 0.9)),
+        ));
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn volume_slider_system(
+    mut mute_buttons: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<MuteButton>),
+    >,
+    tracks: Query<(&Interaction, &RelativeCursorPosition), With<VolumeTrack>>,
+    state: Res<VolumeState>,
+    // Last volume asked for, as the state only catches up after a round trip
+    mut requested: Local<Option<f32>>,
+    mut writer: EventWriter<AudioActionEvent>,
+) {
+    for (interaction, mut color) in mute_buttons.iter_mut() {
+        color.0 = match interaction {
+            Interaction::Hovered | Interaction::Pressed => HOVERED_BUTTON,
+            Interaction::None => NORMAL_BUTTON,
+        };
+        if *interaction == Interaction::Pressed {
+            writer.write(AudioActionEvent(AudioAction::SetMuted(!state.muted)));
+        }
+    }
+    if tracks
+        .iter()
+        .all(|(interaction, _)| *interaction != Interaction::Pressed)
+    {
+        *requested = None;
+    }
+    // Checked every frame rather than on change, to follow drags
+    for (interaction, cursor) in tracks.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some(position) = cursor.normalized else {
+            continue;
+        };
+        let volume = (position.x.clamp(0.0, 1.0) * 100.0).round() / 100.0;
+        let current = requested.unwrap_or(state.volume);
+        if (volume - current).abs() >= 0.01 {
+            *requested = Some(volume);
+            writer.write(AudioActionEvent(AudioAction::SetVolume(volume)));
+        }
+    }
+}
+
+pub(crate) fn media_button_system(
+    mut query: Query<(&Interaction, &MediaButton, &mut BackgroundColor), Changed<Interaction>>,
+    mut writer: EventWriter<AudioActionEvent>,
+) {
+    for (interaction, button, mut color) in query.iter_mut() {
+        color.0 = match interaction {
+            Interaction::Hovered | Interaction::Pressed => HOVERED_BUTTON,
+            Interaction::None => NORMAL_BUTTON,
+        };
+        if *interaction == Interaction::Pressed {
+            writer.write(AudioActionEvent(button.0.clone()));
+        }
+    }
+}
+
+pub(crate) fn update_volume_slider(
+    state: Res<VolumeState>,
+    mut fills: Query<(&mut Node, &mut BackgroundColor), With<VolumeFill>>,
+    mut texts: Query<&mut Text, With<VolumeText>>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    let volume = if state.available { state.volume } else { 0.0 };
+    for (mut node, mut background) in fills.iter_mut() {
+        node.width = Val::Percent(volume.clamp(0.0, 1.0) * 100.0);
+        background.0 = if state.muted { MUTED_FILL } else { FILL };
+    }
+    for mut text in texts.iter_mut() {
+        text.0 = match (state.available, state.muted) {
+            (false, _) => "No volume control".to_string(),
+            (true, true) => "Muted".to_string(),
+            (true, false) => format!("{:.0}%", volume * 100.0),
+        };
+    }
+}
+
+pub(crate) fn update_media_controls(
+    state: Res<MediaState>,
+    mut titles: Query<&mut Text, (With<MediaTitle>, Without<MediaArtist>)>,
+    mut artists: Query<&mut Text, (With<MediaArtist>, Without<MediaTitle>)>,
+    buttons: Query<(&MediaButton, &Children)>,
+    mut labels: Query<&mut Text, (Without<MediaTitle>, Without<MediaArtist>)>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    for mut title in titles.iter_mut() {
+        title.0 = match (&state.player, &state.title) {
+            (None, _) => "No media player".to_string(),
+            (Some(_), Some(title)) => title.clone(),
+            (Some(_), None) => state.identity.clone(),
+        };
+    }
+    for mut artist in artists.iter_mut() {
+        artist.0 = state.artist.clone().unwrap_or_default();
+    }
+    let playing = state.status == PlaybackStatus::Playing;
+    for (button, children) in buttons.iter() {
+        if button.0 != AudioAction::PlayPause {
+            continue;
+        }
+        for child in children.iter() {
+            if let Ok(mut label) = labels.get_mut(child) {
+                label.0 = if playing { "Pause" } else { "Play" }.to_string();
+            }
+        }
+    }
+}
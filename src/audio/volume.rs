@@ -0,0 +1,176 @@
+//! Volume and mute of the default sink, through PulseAudio's D-Bus protocol.
+
+use bevy::{prelude::*, tasks::futures_lite::StreamExt};
+use tokio::sync::watch;
+use zbus::{Connection, MessageStream, connection, message, proxy::CacheProperties};
+
+use super::proxies::{CoreProxy, PA_VOLUME_NORM, PulseDeviceProxy, ServerLookupProxy};
+
+const SIGNALS: [&str; 3] = [
+    "org.PulseAudio.Core1.Device.VolumeUpdated",
+    "org.PulseAudio.Core1.Device.MuteUpdated",
+    "org.PulseAudio.Core1.FallbackSinkUpdated",
+];
+
+/// What PulseAudio currently reports for the default sink, updated by the
+/// plugin as it changes.
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct VolumeState {
+    /// False until connected, or without a sink.
+    pub available: bool,
+    pub sink: String,
+    /// 1.0 is 100%, PulseAudio allows going above.
+    pub volume: f32,
+    pub muted: bool,
+}
+
+/// Opens the peer-to-peer connection to PulseAudio's D-Bus server, found
+/// through `session`.
+pub async fn connect_pulse_audio(session: &Connection) -> zbus::Result<Connection> {
+    let address = ServerLookupProxy::new(session).await?.address().await?;
+    connection::Builder::address(address.as_str())?
+        .p2p()
+        .build()
+        .await
+}
+
+// The server has no bus to subscribe to property changes on, so proxies read
+// properties on every call instead of caching them.
+async fn default_sink(connection: &Connection) -> zbus::Result<PulseDeviceProxy<'static>> {
+    let core = CoreProxy::builder(connection)
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+    PulseDeviceProxy::builder(connection)
+        .path(core.fallback_sink().await?)?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await
+}
+
+pub async fn read_volume(connection: &Connection) -> zbus::Result<VolumeState> {
+    let sink = default_sink(connection).await?;
+    let channels = sink.volume().await?;
+    let average =
+        channels.iter().map(|&v| u64::from(v)).sum::<u64>() / channels.len().max(1) as u64;
+    Ok(VolumeState {
+        available: true,
+        sink: sink.name().await?,
+        volume: average as f32 / PA_VOLUME_NORM as f32,
+        muted: sink.mute().await?,
+    })
+}
+
+/// Sets the default sink to `volume`, 1.0 being 100%, scaling every channel by
+/// the same ratio so their balance is kept.
+pub async fn set_volume(connection: &Connection, volume: f32) -> zbus::Result<()> {
+    let sink = default_sink(connection).await?;
+    let channels = sink.volume().await?;
+    sink.set_volume(&scale_channels(&channels, volume)).await
+}
+
+/// `channels` scaled so that their average, which `read_volume` reports, is
+/// `volume`. Silent channels have no balance to keep and all end up at
+/// `volume`.
+fn scale_channels(channels: &[u32], volume: f32) -> Vec<u32> {
+    let target = f64::from(volume.max(0.0)) * f64::from(PA_VOLUME_NORM);
+    let total: f64 = channels.iter().map(|&channel| f64::from(channel)).sum();
+    if total == 0.0 {
+        return vec![target.round() as u32; channels.len().max(1)];
+    }
+    let ratio = target * channels.len() as f64 / total;
+    channels
+        .iter()
+        .map(|&channel| (f64::from(channel) * ratio).round() as u32)
+        .collect()
+}
+
+pub async fn set_muted(connection: &Connection, muted: bool) -> zbus::Result<()> {
+    default_sink(connection).await?.set_mute(muted).await
+}
+
+/// Sends the current state, then a new one after every volume, mute or
+/// default sink change. Returns once `tx` has no receivers.
+pub async fn follow_volume(
+    connection: &Connection,
+    tx: &watch::Sender<VolumeState>,
+) -> zbus::Result<()> {
+    let core = CoreProxy::builder(connection)
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+    for signal in SIGNALS {
+        core.listen_for_signal(signal, &[]).await?;
+    }
+    let mut messages = MessageStream::from(connection);
+
+    loop {
+        let state = match read_volume(connection).await {
+            Ok(state) => state,
+            Err(e) => {
+                error!("Error reading the volume: {e}");
+                VolumeState::default()
+            }
+        };
+        tx.send_if_modified(|current| {
+            let modified = *current != state;
+            *current = state;
+            modified
+        });
+        if tx.is_closed() {
+            return Ok(());
+        }
+        // Replies to our own calls come through the same stream
+        loop {
+            match messages.next().await {
+                Some(Ok(msg)) if msg.message_type() == message::Type::Signal => break,
+                Some(_) => continue,
+                None => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Sent whenever `VolumeState` changes.
+#[derive(Event, Debug, Clone)]
+pub struct VolumeChanged(pub VolumeState);
+
+#[derive(Resource)]
+pub(crate) struct VolumeStateReceiver(pub(crate) watch::Receiver<VolumeState>);
+
+pub(crate) fn sync_volume_state(
+    receiver: Option<ResMut<VolumeStateReceiver>>,
+    mut state: ResMut<VolumeState>,
+    mut writer: EventWriter<VolumeChanged>,
+) {
+    let Some(mut receiver) = receiver else {
+        return;
+    };
+    if receiver.0.has_changed().unwrap_or(false) {
+        *state = receiver.0.borrow_and_update().clone();
+        writer.write(VolumeChanged(state.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaling_keeps_the_balance() {
+        let half = PA_VOLUME_NORM / 2;
+        // Left at 50%, right at 100%: an average of 75% brought to 150%
+        assert_eq!(
+            scale_channels(&[half, PA_VOLUME_NORM], 1.5),
+            vec![PA_VOLUME_NORM, 2 * PA_VOLUME_NORM]
+        );
+        assert_eq!(scale_channels(&[half, half], 1.0), vec![PA_VOLUME_NORM; 2]);
+        assert_eq!(scale_channels(&[half, PA_VOLUME_NORM], 0.0), vec![0, 0]);
+    }
+
+    #[test]
+    fn silent_channels_are_set_alike() {
+        assert_eq!(scale_channels(&[0, 0], 0.5), vec![PA_VOLUME_NORM / 2; 2]);
+        assert_eq!(scale_channels(&[], 1.0), vec![PA_VOLUME_NORM]);
+    }
+}
@@ -2,25 +2,21 @@
 //! interaction state.
 
 use bevy::{color::palettes::basic::*, prelude::*, winit::WinitSettings};
+use counter_bevy::counter::{Counter, CounterOp};
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins)
         // Only run the app when there is user input. This will significantly reduce CPU/GPU use.
         .insert_resource(WinitSettings::desktop_app())
-        .insert_resource(Counter(0))
+        .insert_resource(CounterState(Counter::new(0)))
         .add_systems(Startup, setup)
         .add_systems(Update, button_system)
         .run();
 }
 
-#[derive(Resource, Component)]
-struct Counter(i32);
-
-impl Default for Counter {
-    fn default() -> Self {
-        Self(0)
-    }
-}
+/// Bevy adapter around the headless counter rules.
+#[derive(Resource, Deref, DerefMut)]
+struct CounterState(Counter);
 
 #[derive(Clone, Copy, Component)]
 struct CounterText;
@@ -32,8 +28,8 @@ enum ButtonAction {
 }
 
 const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
-fn setup(mut commands: Commands, counter: Res<Counter>, assets: Res<AssetServer>) {
-    println!("counter in setup: {}", counter.0);
+fn setup(mut commands: Commands, counter: Res<CounterState>, assets: Res<AssetServer>) {
+    println!("counter in setup: {}", counter.value());
     // ui camera
     commands.spawn(Camera2d);
     // Text with one section
@@ -124,7 +120,7 @@ fn create_counter_text(commands: &mut Commands, counter_value: &Counter, assets:
             BackgroundColor(NORMAL_BUTTON),
         ))
         .with_child((
-            Text::new(&counter_value.0.to_string()),
+            Text::new(counter_value.value().to_string()),
             TextFont {
                 font: assets.load("fonts/FiraSans-Bold.ttf"),
                 font_size: 33.0,
@@ -149,7 +145,7 @@ fn button_system(
         Query<&mut Text, With<CounterText>>,
         Query<&mut Text>,
     )>,
-    mut counter: ResMut<Counter>,
+    mut counter: ResMut<CounterState>,
 ) {
     
     for (interaction, _, mut border_color, _, actions) in queries.p0().iter_mut() {
@@ -160,10 +156,10 @@ fn button_system(
 
                 match actions {
                     Some(ButtonAction::Increment) => {
-                        counter.0 += 1;
+                        counter.apply(CounterOp::Increment);
                     }
                     Some(ButtonAction::Decrement) => {
-                        counter.0 -= 1;
+                        counter.apply(CounterOp::Decrement);
                     }
                     _ => {
                         println!("no action");
@@ -181,6 +177,6 @@ fn button_system(
     }
     for entity in queries.p1().iter_mut() {
         let mut text = entity;
-        text.0 = counter.0.to_string();
+        text.0 = counter.value().to_string();
     }
 }
@@ -0,0 +1,58 @@
+//! Stand-in NetworkManager for running the plugin without real hardware:
+//!
+//! ```text
+//! cargo run --features nm-mock --bin nm_mock -- --scenario my.scenario &
+//! NM_BUS=session cargo run --example network_manager_plugin
+//! ```
+
+use bevy::log::{Level, tracing_subscriber};
+use counter_bevy::{
+    bus::Bus,
+    network_manager::mock::{DEFAULT_SCENARIO, SCENARIO_USAGE, Scenario, serve},
+};
+
+fn main() {
+    let (bus, scenario) = match parse_args(std::env::args().skip(1)) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("usage: nm_mock [--bus session|system] [--scenario <file>]");
+            eprintln!("{}", SCENARIO_USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    // Without a Bevy app around, the mock's log lines need their own subscriber
+    tracing_subscriber::fmt().with_max_level(Level::INFO).init();
+
+    // The mock spawns its timers on async-std, so it runs there too
+    if let Err(e) = async_std::task::block_on(serve(bus, scenario)) {
+        eprintln!("Error serving NetworkManager: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<(Bus, Scenario), String> {
+    let mut bus = Bus::Session;
+    let mut scenario = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--bus" => bus = value()?.parse()?,
+            "--scenario" => {
+                let path = value()?;
+                let text = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("cannot read {}: {}", path, e))?;
+                scenario = Some(Scenario::parse(&text).map_err(|e| format!("{}: {}", path, e))?);
+            }
+            other => return Err(format!("unknown argument {}", other)),
+        }
+    }
+
+    let scenario = match scenario {
+        Some(scenario) => scenario,
+        None => Scenario::parse(DEFAULT_SCENARIO)?,
+    };
+    Ok((bus, scenario))
+}
@@ -1,6 +1,15 @@
 //! Demonstrates how the to use the size constraints to control the size of a UI node.
 
-use bevy::{color::palettes::css::*, prelude::*};
+use bevy::{
+    color::palettes::css::*,
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+};
+use counter_bevy::{
+    tictactoe::{Board, Outcome},
+    tictactoe_service::{GameEvent, publish_game_events},
+};
+use tokio::sync::mpsc;
 
 fn main() {
     App::new()
@@ -11,16 +20,34 @@ fn main() {
             }),
             ..default()
         }))
-        .add_systems(Startup, setup)
+        .init_resource::<GameBoard>()
+        .add_systems(Startup, (setup, setup_async_task))
         .add_systems(Update, button_click)
         .run();
 }
 
+// Task Resource
+#[derive(Resource)]
+struct AsyncTask(Task<()>);
+
+#[derive(Resource)]
+struct GameEventSender {
+    tx: mpsc::Sender<GameEvent>,
+}
+
+/// Bevy adapter around the headless game rules.
+#[derive(Resource, Default, Deref, DerefMut)]
+struct GameBoard(Board);
+
 #[derive(Component)]
 struct Position {
     row: usize,
     col: usize,
 }
+
+#[derive(Component)]
+struct CellText;
+
 fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands.spawn(Camera2d);
 
@@ -64,7 +91,7 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                     BackgroundColor(GRAY.into()),
                 ))
                 .with_children(|parent| {
-                    for _row in 0..3 {
+                    for col in 0..3 {
                         parent
                             .spawn((
                                 Node {
@@ -76,22 +103,31 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                                 BackgroundColor(GRAY.into()),
                             ))
                             .with_children(|column| {
-                                for col in 0..3 {
-                                    column.spawn((
-                                        Button,
-                                        Node {
-                                            width: Val::Px(100.0),
-                                            height: Val::Px(100.0),
-                                            margin: UiRect {
-                                                left: Val::Px(5.0),
-                                                right: Val::Px(5.0),
-                                                top: Val::Px(5.0),
-                                                bottom: Val::Px(5.0),
+                                for row in 0..3 {
+                                    column
+                                        .spawn((
+                                            Button,
+                                            Node {
+                                                width: Val::Px(100.0),
+                                                height: Val::Px(100.0),
+                                                margin: UiRect {
+                                                    left: Val::Px(5.0),
+                                                    right: Val::Px(5.0),
+                                                    top: Val::Px(5.0),
+                                                    bottom: Val::Px(5.0),
+                                                },
+                                                justify_content: JustifyContent::Center,
+                                                align_items: AlignItems::Center,
+                                                ..Default::default()
                                             },
-                                            ..Default::default()
-                                        },
-                                        BackgroundColor(YELLOW.into()),
-                                    ));
+                                            BackgroundColor(YELLOW.into()),
+                                            Position { row, col },
+                                        ))
+                                        .with_child((
+                                            Text::new(""),
+                                            text_font.clone(),
+                                            CellText,
+                                        ));
                                 }
                             });
                     }
@@ -99,20 +135,70 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         });
 }
 
+fn setup_async_task(mut commands: Commands) {
+    let pool = AsyncComputeTaskPool::get();
+    let (tx, rx) = mpsc::channel(10);
+    commands.insert_resource(GameEventSender { tx });
+
+    let task = pool.spawn(async move {
+        if let Err(e) = publish_game_events(rx).await {
+            println!("Error publishing game events: {}", e);
+        }
+    });
+
+    commands.insert_resource(AsyncTask(task));
+}
+
 fn button_click(
-    mut interaction_query: Query<(&Interaction, &Position), (Changed<Interaction>, With<Button>)>,
+    interaction_query: Query<(&Interaction, &Position, &Children), (Changed<Interaction>, With<Button>)>,
+    mut text_query: Query<&mut Text, With<CellText>>,
+    mut board: ResMut<GameBoard>,
+    event_sender: Res<GameEventSender>,
 ) {
-    for (interaction, position) in &mut interaction_query {
-        match *interaction {
-            Interaction::Hovered => {
-                println!("Hovered button at ({}, {})", position.row, position.col);
-            }
-            Interaction::Pressed => {
-                println!("Pressed button at ({}, {})", position.row, position.col);
+    for (interaction, position, children) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        println!("Pressed button at ({}, {})", position.row, position.col);
+
+        let player = board.turn();
+        let outcome = match board.play(position.row, position.col) {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                println!("Move rejected: {}", e);
+                continue;
             }
-            Interaction::None => {
-                println!("None button at ({}, {})", position.row, position.col);
+        };
+        for child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(child) {
+                text.0 = player.to_string();
             }
         }
+        send_event(
+            &event_sender,
+            GameEvent::MovePlayed {
+                row: position.row as u8,
+                col: position.col as u8,
+                player: player.to_string(),
+            },
+        );
+
+        match outcome {
+            Outcome::Won(winner) => send_event(
+                &event_sender,
+                GameEvent::GameWon {
+                    player: winner.to_string(),
+                },
+            ),
+            Outcome::Drawn => send_event(&event_sender, GameEvent::GameDrawn),
+            Outcome::InProgress => {}
+        }
+    }
+}
+
+fn send_event(sender: &GameEventSender, event: GameEvent) {
+    // Never block the UI on the bus; observers are best effort.
+    if let Err(e) = sender.tx.try_send(event) {
+        println!("Error sending game event: {}", e);
     }
 }
@@ -0,0 +1,65 @@
+//! ZeroMQ color server for the `container` binary.
+
+use std::time::Duration;
+
+use counter_bevy::{
+    zmq_client::DEFAULT_TOPIC,
+    zmq_server::{ServerMode, ZmqServerConfig, run_zmq_color_server},
+};
+
+fn main() {
+    let config = match parse_args(std::env::args().skip(1)) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!(
+                "usage: zmq_server [--endpoint <addr>] [--mode rep|pub] [--topic <topic>] [--interval-ms <ms>] [--count <n>]"
+            );
+            std::process::exit(2);
+        }
+    };
+
+    let context = zmq::Context::new();
+    if let Err(e) = run_zmq_color_server(&context, &config) {
+        eprintln!("Error serving colors on {}: {}", config.endpoint, e);
+        std::process::exit(1);
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<ZmqServerConfig, String> {
+    let mut config = ZmqServerConfig::default();
+    let mut mode = "rep".to_string();
+    let mut topic = DEFAULT_TOPIC.to_string();
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--endpoint" => config.endpoint = value()?,
+            "--mode" => mode = value()?,
+            "--topic" => topic = value()?,
+            "--interval-ms" => {
+                let value = value()?;
+                config.interval = value
+                    .parse::<u64>()
+                    .map(Duration::from_millis)
+                    .map_err(|e| format!("invalid milliseconds {:?}: {}", value, e))?;
+            }
+            "--count" => {
+                let value = value()?;
+                config.max_messages = Some(
+                    value
+                        .parse()
+                        .map_err(|e| format!("invalid count {:?}: {}", value, e))?,
+                );
+            }
+            other => return Err(format!("unknown argument {}", other)),
+        }
+    }
+
+    config.mode = match mode.as_str() {
+        "rep" => ServerMode::Rep,
+        "pub" => ServerMode::Pub { topic },
+        other => return Err(format!("unknown mode {}", other)),
+    };
+    Ok(config)
+}
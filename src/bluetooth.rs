@@ -0,0 +1,190 @@
+pub mod proxies;
+pub mod state;
+pub mod ui;
+
+use bevy::{
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, IoTaskPool},
+};
+use proxies::{AdapterProxy, DeviceProxy};
+use state::{
+    BluetoothDeviceEvent, BluetoothPowerChanged, BluetoothState, BluetoothStateReceiver,
+    follow_bluetooth_state, sync_bluetooth_state,
+};
+use tokio::sync::watch;
+use zbus::{Connection, zvariant::OwnedObjectPath};
+
+use crate::bus::{SystemBus, SystemBusPlugin};
+use crate::result_channel::ResultChannel;
+
+/// Devices are named by address, as found in `BluetoothState::devices`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BluetoothAction {
+    SetPowered(bool),
+    StartDiscovery,
+    StopDiscovery,
+    /// Pairs and trusts the device. Devices asking for a PIN or passkey need
+    /// an agent, such as the desktop's, to be registered with BlueZ.
+    Pair(String),
+    Connect(String),
+    Disconnect(String),
+    /// Forgets a paired device.
+    Remove(String),
+}
+
+#[derive(Event)]
+pub struct BluetoothActionEvent(pub BluetoothAction);
+
+/// Sent when a `BluetoothAction` failed. Successful actions show up in
+/// `BluetoothState` and its events.
+#[derive(Event, Debug, Clone)]
+pub struct BluetoothErrorEvent {
+    pub action: BluetoothAction,
+    pub reason: String,
+}
+
+/// Follows the first Bluetooth adapter known to BlueZ into `BluetoothState`
+/// and runs `BluetoothActionEvent`s.
+pub struct BluetoothPlugin;
+
+impl Plugin for BluetoothPlugin {
+    fn build(&self, app: &mut App) {
+        SystemBusPlugin::add_once(app);
+        app.init_resource::<BluetoothState>()
+            .init_resource::<ResultChannel<BluetoothErrorEvent>>()
+            .add_event::<BluetoothActionEvent>()
+            .add_event::<BluetoothErrorEvent>()
+            .add_event::<BluetoothDeviceEvent>()
+            .add_event::<BluetoothPowerChanged>()
+            .add_systems(
+                Update,
+                (
+                    start_following,
+                    sync_bluetooth_state.after(start_following),
+                    handle_bluetooth_action_events,
+                    poll_bluetooth_results.after(handle_bluetooth_action_events),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    ui::bluetooth_button_system,
+                    ui::update_bluetooth_panel.after(sync_bluetooth_state),
+                ),
+            );
+    }
+}
+
+/// Starts following BlueZ once the shared system bus is connected.
+fn start_following(
+    mut commands: Commands,
+    bus: Res<SystemBus>,
+    receiver: Option<Res<BluetoothStateReceiver>>,
+) {
+    if receiver.is_some() {
+        return;
+    }
+    let Some(connection) = bus.connection.clone() else {
+        return;
+    };
+    let (tx, rx) = watch::channel(BluetoothState::default());
+    IoTaskPool::get()
+        .spawn(async move {
+            if let Err(e) = follow_bluetooth_state(&connection, &tx).await {
+                error!("Failed to follow the Bluetooth state: {e}");
+            }
+        })
+        .detach();
+    commands.insert_resource(BluetoothStateReceiver(rx));
+}
+
+fn handle_bluetooth_action_events(
+    mut events: EventReader<BluetoothActionEvent>,
+    bus: Res<SystemBus>,
+    state: Res<BluetoothState>,
+    results: Res<ResultChannel<BluetoothErrorEvent>>,
+    mut error_writer: EventWriter<BluetoothErrorEvent>,
+) {
+    let pool = AsyncComputeTaskPool::get();
+    for BluetoothActionEvent(action) in events.read() {
+        let fail = |reason: &str| BluetoothErrorEvent {
+            action: action.clone(),
+            reason: reason.to_string(),
+        };
+        let Some(connection) = bus.connection.clone() else {
+            error_writer.write(fail("the system bus is not available"));
+            continue;
+        };
+        let Some(adapter) = &state.adapter else {
+            error_writer.write(fail("no Bluetooth adapter"));
+            continue;
+        };
+        let device = match action {
+            BluetoothAction::Pair(address)
+            | BluetoothAction::Connect(address)
+            | BluetoothAction::Disconnect(address)
+            | BluetoothAction::Remove(address) => match state.device(address) {
+                Some(device) => Some(device.path.clone()),
+                None => {
+                    error_writer.write(fail("unknown device"));
+                    continue;
+                }
+            },
+            _ => None,
+        };
+        let adapter = adapter.path.clone();
+        let action = action.clone();
+        let sender = results.sender();
+        pool.spawn(async move {
+            if let Err(err) = run_action(&connection, adapter, device, &action).await {
+                error!("Bluetooth action {action:?} failed: {err}");
+                let _ = sender.send(BluetoothErrorEvent {
+                    action,
+                    reason: err.to_string(),
+                });
+            }
+        })
+        .detach();
+    }
+}
+
+async fn run_action(
+    connection: &Connection,
+    adapter: OwnedObjectPath,
+    device: Option<OwnedObjectPath>,
+    action: &BluetoothAction,
+) -> zbus::Result<()> {
+    let adapter = AdapterProxy::builder(connection)
+        .path(adapter)?
+        .build()
+        .await?;
+    let device = match device {
+        Some(path) => Some(DeviceProxy::builder(connection).path(path)?.build().await?),
+        None => None,
+    };
+    match (action, device) {
+        (BluetoothAction::SetPowered(powered), _) => adapter.set_powered(*powered).await,
+        (BluetoothAction::StartDiscovery, _) => adapter.start_discovery().await,
+        (BluetoothAction::StopDiscovery, _) => adapter.stop_discovery().await,
+        (BluetoothAction::Pair(_), Some(device)) => {
+            device.pair().await?;
+            // Lets the device reconnect on its own later
+            device.set_trusted(true).await
+        }
+        (BluetoothAction::Connect(_), Some(device)) => device.connect().await,
+        (BluetoothAction::Disconnect(_), Some(device)) => device.disconnect().await,
+        (BluetoothAction::Remove(_), Some(device)) => {
+            adapter.remove_device(device.inner().path()).await
+        }
+        (_, None) => Err(zbus::Error::Failure("unknown device".to_string())),
+    }
+}
+
+fn poll_bluetooth_results(
+    results: Res<ResultChannel<BluetoothErrorEvent>>,
+    mut error_writer: EventWriter<BluetoothErrorEvent>,
+) {
+    for error in results.drain() {
+        error_writer.write(error);
+    }
+}
@@ -0,0 +1,35 @@
+//! Subset of the `org.bluez` D-Bus API used by the plugin.
+
+use zbus::{Result as ZbusResult, proxy, zvariant::ObjectPath};
+
+pub const BLUEZ_SERVICE: &str = "org.bluez";
+pub const ADAPTER_INTERFACE: &str = "org.bluez.Adapter1";
+pub const DEVICE_INTERFACE: &str = "org.bluez.Device1";
+pub const BATTERY_INTERFACE: &str = "org.bluez.Battery1";
+
+#[proxy(interface = "org.bluez.Adapter1", default_service = "org.bluez")]
+pub trait Adapter {
+    fn start_discovery(&self) -> ZbusResult<()>;
+
+    fn stop_discovery(&self) -> ZbusResult<()>;
+
+    fn remove_device(&self, device: &ObjectPath<'_>) -> ZbusResult<()>;
+
+    #[zbus(property)]
+    fn powered(&self) -> ZbusResult<bool>;
+
+    #[zbus(property)]
+    fn set_powered(&self, powered: bool) -> ZbusResult<()>;
+}
+
+#[proxy(interface = "org.bluez.Device1", default_service = "org.bluez")]
+pub trait Device {
+    fn pair(&self) -> ZbusResult<()>;
+
+    fn connect(&self) -> ZbusResult<()>;
+
+    fn disconnect(&self) -> ZbusResult<()>;
+
+    #[zbus(property)]
+    fn set_trusted(&self, trusted: bool) -> ZbusResult<()>;
+}
@@ -0,0 +1,430 @@
+//! Adapter and devices read from BlueZ and kept up to date.
+
+use std::{collections::HashMap, time::Duration};
+
+use bevy::prelude::*;
+use tokio::sync::watch;
+use zbus::{
+    Connection, MatchRule, MessageStream,
+    fdo::ObjectManagerProxy,
+    message,
+    zvariant::{OwnedObjectPath, OwnedValue, Value},
+};
+
+use super::proxies::{ADAPTER_INTERFACE, BATTERY_INTERFACE, BLUEZ_SERVICE, DEVICE_INTERFACE};
+use crate::debounce::next_burst;
+
+/// Signals arriving closer together than this cause a single re-read.
+/// Discovery in particular sends a stream of RSSI updates.
+const CHANGE_DEBOUNCE: Duration = Duration::from_millis(250);
+/// Longest a re-read is put off by signals that keep coming, as they do
+/// while discovering.
+const CHANGE_MAX_DELAY: Duration = Duration::from_secs(1);
+
+type Properties = HashMap<String, OwnedValue>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BluetoothAdapter {
+    pub path: OwnedObjectPath,
+    pub name: String,
+    pub address: String,
+    pub powered: bool,
+    pub discovering: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BluetoothDevice {
+    pub path: OwnedObjectPath,
+    /// Identifies the device in `BluetoothAction`s.
+    pub address: String,
+    pub name: String,
+    /// Freedesktop icon name such as `audio-headset`, when the device has one.
+    pub icon: Option<String>,
+    pub paired: bool,
+    pub connected: bool,
+    /// Signal strength in dBm, only known while discovering.
+    pub rssi: Option<i16>,
+    /// Battery level in percent, for devices reporting one.
+    pub battery: Option<u8>,
+}
+
+/// What BlueZ currently reports for the first adapter, updated by the plugin
+/// as it changes.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Eq)]
+pub struct BluetoothState {
+    /// None without an adapter or without BlueZ.
+    pub adapter: Option<BluetoothAdapter>,
+    /// Devices known to the adapter, paired ones first, then by name.
+    pub devices: Vec<BluetoothDevice>,
+}
+
+impl BluetoothState {
+    pub fn device(&self, address: &str) -> Option<&BluetoothDevice> {
+        self.devices.iter().find(|device| device.address == address)
+    }
+
+    /// Equal apart from the devices' RSSI, which keeps changing while
+    /// discovering.
+    pub fn eq_ignoring_rssi(&self, other: &BluetoothState) -> bool {
+        self.adapter == other.adapter
+            && self.devices.len() == other.devices.len()
+            && self.devices.iter().zip(&other.devices).all(|(a, b)| {
+                let a = BluetoothDevice {
+                    rssi: b.rssi,
+                    ..a.clone()
+                };
+                a == *b
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceChange {
+    Found,
+    Removed,
+    Paired,
+    Connected,
+    Disconnected,
+}
+
+/// Sent for every device that appeared, went away, or changed pairing or
+/// connection state.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct BluetoothDeviceEvent {
+    pub address: String,
+    pub name: String,
+    pub change: DeviceChange,
+}
+
+/// Sent when the adapter appears, goes away, or is powered on or off.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct BluetoothPowerChanged(pub Option<bool>);
+
+pub async fn read_bluetooth_state(connection: &Connection) -> zbus::Result<BluetoothState> {
+    let objects = ObjectManagerProxy::builder(connection)
+        .destination(BLUEZ_SERVICE)?
+        .path("/")?
+        .build()
+        .await?
+        .get_managed_objects()
+        .await?;
+
+    let mut adapters: Vec<BluetoothAdapter> = objects
+        .iter()
+        .filter_map(|(path, interfaces)| {
+            let properties = interfaces
+                .iter()
+                .find(|(name, _)| name.as_str() == ADAPTER_INTERFACE)?
+                .1;
+            Some(BluetoothAdapter {
+                path: path.clone(),
+                name: string_property(properties, "Alias").unwrap_or_default(),
+                address: string_property(properties, "Address").unwrap_or_default(),
+                powered: bool_property(properties, "Powered"),
+                discovering: bool_property(properties, "Discovering"),
+            })
+        })
+        .collect();
+    adapters.sort_by(|a, b| a.path.as_str().cmp(b.path.as_str()));
+    let Some(adapter) = adapters.into_iter().next() else {
+        return Ok(BluetoothState::default());
+    };
+
+    let mut devices: Vec<BluetoothDevice> = objects
+        .iter()
+        .filter_map(|(path, interfaces)| {
+            let interface = |wanted: &str| {
+                interfaces
+                    .iter()
+                    .find(|(name, _)| name.as_str() == wanted)
+                    .map(|(_, properties)| properties)
+            };
+            let properties = interface(DEVICE_INTERFACE)?;
+            let on_adapter = match properties.get("Adapter").map(|value| &**value) {
+                Some(Value::ObjectPath(path)) => path.as_str() == adapter.path.as_str(),
+                _ => false,
+            };
+            if !on_adapter {
+                return None;
+            }
+            let address = string_property(properties, "Address")?;
+            Some(BluetoothDevice {
+                path: path.clone(),
+                name: string_property(properties, "Alias").unwrap_or_else(|| address.clone()),
+                address,
+                icon: string_property(properties, "Icon"),
+                paired: bool_property(properties, "Paired"),
+                connected: bool_property(properties, "Connected"),
+                rssi: match properties.get("RSSI").map(|value| &**value) {
+                    Some(Value::I16(rssi)) => Some(*rssi),
+                    _ => None,
+                },
+                battery: interface(BATTERY_INTERFACE).and_then(|battery| {
+                    match battery.get("Percentage").map(|value| &**value) {
+                        Some(Value::U8(percentage)) => Some(*percentage),
+                        _ => None,
+                    }
+                }),
+            })
+        })
+        .collect();
+    devices.sort_by(|a, b| b.paired.cmp(&a.paired).then_with(|| a.name.cmp(&b.name)));
+
+    Ok(BluetoothState {
+        adapter: Some(adapter),
+        devices,
+    })
+}
+
+fn string_property(properties: &Properties, name: &str) -> Option<String> {
+    match properties.get(name).map(|value| &**value) {
+        Some(Value::Str(s)) => Some(s.to_string()),
+        _ => None,
+    }
+}
+
+fn bool_property(properties: &Properties, name: &str) -> bool {
+    matches!(
+        properties.get(name).map(|value| &**value),
+        Some(Value::Bool(true))
+    )
+}
+
+/// Sends the current state, then a new one after any BlueZ signal.
+/// Returns once `tx` has no receivers.
+pub async fn follow_bluetooth_state(
+    connection: &Connection,
+    tx: &watch::Sender<BluetoothState>,
+) -> zbus::Result<()> {
+    // Adapters and devices come and go through the object manager, their
+    // properties change on each object
+    let rule = MatchRule::builder()
+        .msg_type(message::Type::Signal)
+        .sender(BLUEZ_SERVICE)?
+        .build();
+    let mut changes = MessageStream::for_match_rule(rule, connection, None).await?;
+
+    loop {
+        match read_bluetooth_state(connection).await {
+            Ok(state) => {
+                tx.send_if_modified(|current| {
+                    let modified = *current != state;
+                    *current = state;
+                    modified
+                });
+            }
+            Err(e) => error!("Error reading the Bluetooth state: {e}"),
+        }
+        if tx.is_closed()
+            || next_burst(&mut changes, CHANGE_DEBOUNCE, CHANGE_MAX_DELAY)
+                .await
+                .is_none()
+        {
+            return Ok(());
+        }
+    }
+}
+
+#[derive(Resource)]
+pub(crate) struct BluetoothStateReceiver(pub(crate) watch::Receiver<BluetoothState>);
+
+pub(crate) fn sync_bluetooth_state(
+    receiver: Option<ResMut<BluetoothStateReceiver>>,
+    mut state: ResMut<BluetoothState>,
+    mut device_writer: EventWriter<BluetoothDeviceEvent>,
+    mut power_writer: EventWriter<BluetoothPowerChanged>,
+) {
+    let Some(mut receiver) = receiver else {
+        return;
+    };
+    if !receiver.0.has_changed().unwrap_or(false) {
+        return;
+    }
+    let new = receiver.0.borrow_and_update().clone();
+
+    let (power, devices) = diff_states(&state, &new);
+    if let Some(power) = power {
+        power_writer.write(power);
+    }
+    device_writer.write_batch(devices);
+    *state = new;
+}
+
+/// The events telling how `old` turned into `new`: the adapter's power, if it
+/// changed, and every device found, removed, paired, connected or
+/// disconnected.
+pub(crate) fn diff_states(
+    old: &BluetoothState,
+    new: &BluetoothState,
+) -> (Option<BluetoothPowerChanged>, Vec<BluetoothDeviceEvent>) {
+    let powered = |state: &BluetoothState| state.adapter.as_ref().map(|adapter| adapter.powered);
+    let power = (powered(old) != powered(new)).then(|| BluetoothPowerChanged(powered(new)));
+
+    let mut devices = Vec::new();
+    let mut send = |device: &BluetoothDevice, change| {
+        devices.push(BluetoothDeviceEvent {
+            address: device.address.clone(),
+            name: device.name.clone(),
+            change,
+        });
+    };
+    for device in new.devices.iter() {
+        let Some(previous) = old.device(&device.address) else {
+            send(device, DeviceChange::Found);
+            continue;
+        };
+        if device.paired && !previous.paired {
+            send(device, DeviceChange::Paired);
+        }
+        if device.connected != previous.connected {
+            let change = if device.connected {
+                DeviceChange::Connected
+            } else {
+                DeviceChange::Disconnected
+            };
+            send(device, change);
+        }
+    }
+    for device in old.devices.iter() {
+        if new.device(&device.address).is_none() {
+            send(device, DeviceChange::Removed);
+        }
+    }
+    (power, devices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(address: &str, rssi: Option<i16>) -> BluetoothDevice {
+        BluetoothDevice {
+            path: OwnedObjectPath::try_from(format!("/org/bluez/hci0/dev_{}", address)).unwrap(),
+            address: address.to_string(),
+            name: address.to_string(),
+            icon: None,
+            paired: false,
+            connected: false,
+            rssi,
+            battery: None,
+        }
+    }
+
+    #[test]
+    fn rssi_alone_is_not_a_change() {
+        let state = BluetoothState {
+            adapter: None,
+            devices: vec![device("A", Some(-40)), device("B", None)],
+        };
+        let moved = BluetoothState {
+            devices: vec![device("A", Some(-70)), device("B", Some(-90))],
+            ..state.clone()
+        };
+        assert!(state.eq_ignoring_rssi(&moved));
+        assert_ne!(state, moved);
+
+        let mut paired = moved.clone();
+        paired.devices[1].paired = true;
+        assert!(!state.eq_ignoring_rssi(&paired));
+        let fewer = BluetoothState {
+            devices: vec![device("A", Some(-40))],
+            ..state.clone()
+        };
+        assert!(!state.eq_ignoring_rssi(&fewer));
+    }
+
+    fn adapter(powered: bool) -> BluetoothAdapter {
+        BluetoothAdapter {
+            path: OwnedObjectPath::try_from("/org/bluez/hci0").unwrap(),
+            name: "hci0".to_string(),
+            address: "00:11:22:33:44:55".to_string(),
+            powered,
+            discovering: false,
+        }
+    }
+
+    fn event(address: &str, change: DeviceChange) -> BluetoothDeviceEvent {
+        BluetoothDeviceEvent {
+            address: address.to_string(),
+            name: address.to_string(),
+            change,
+        }
+    }
+
+    #[test]
+    fn diff_reports_found_and_removed_devices() {
+        let old = BluetoothState {
+            adapter: Some(adapter(true)),
+            devices: vec![device("A", None), device("B", None)],
+        };
+        let new = BluetoothState {
+            devices: vec![device("B", None), device("C", None)],
+            ..old.clone()
+        };
+        assert_eq!(
+            diff_states(&old, &new),
+            (
+                None,
+                vec![
+                    event("C", DeviceChange::Found),
+                    event("A", DeviceChange::Removed),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn diff_reports_pairing_and_connection_changes() {
+        let old = BluetoothState {
+            adapter: Some(adapter(true)),
+            devices: vec![device("A", None), device("B", None)],
+        };
+        let mut new = old.clone();
+        new.devices[0].paired = true;
+        new.devices[0].connected = true;
+        assert_eq!(
+            diff_states(&old, &new).1,
+            vec![
+                event("A", DeviceChange::Paired),
+                event("A", DeviceChange::Connected),
+            ]
+        );
+        // Unpairing goes unreported, the device is removed along with it
+        assert_eq!(
+            diff_states(&new, &old).1,
+            vec![event("A", DeviceChange::Disconnected)]
+        );
+        // Signal strength alone is no event
+        let mut moved = old.clone();
+        moved.devices[1].rssi = Some(-60);
+        assert_eq!(diff_states(&old, &moved), (None, vec![]));
+    }
+
+    #[test]
+    fn diff_reports_power_changes() {
+        let without = BluetoothState::default();
+        let off = BluetoothState {
+            adapter: Some(adapter(false)),
+            devices: vec![],
+        };
+        let on = BluetoothState {
+            adapter: Some(adapter(true)),
+            devices: vec![],
+        };
+        assert_eq!(
+            diff_states(&without, &off).0,
+            Some(BluetoothPowerChanged(Some(false)))
+        );
+        assert_eq!(
+            diff_states(&off, &on).0,
+            Some(BluetoothPowerChanged(Some(true)))
+        );
+        assert_eq!(
+            diff_states(&on, &without).0,
+            Some(BluetoothPowerChanged(None))
+        );
+        let mut discovering = on.clone();
+        discovering.adapter.as_mut().unwrap().discovering = true;
+        assert_eq!(diff_states(&on, &discovering).0, None);
+    }
+}
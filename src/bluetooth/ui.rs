@@ -0,0 +1,162 @@
+//! Ready-made Bevy UI for the Bluetooth plugin.
+
+use bevy::prelude::*;
+
+use super::{BluetoothAction, BluetoothActionEvent, state::BluetoothState};
+
+const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
+const HOVERED_BUTTON: Color = Color::srgb(0.25, 0.25, 0.25);
+const DIM_TEXT: Color = Color::srgb(0.6, 0.6, 0.6);
+
+/// Column the Bluetooth controls are spawned into. Holds the font used for them.
+#[derive(Component)]
+pub struct BluetoothPanel {
+    font: Handle<Font>,
+}
+
+/// What pressing a button of the Bluetooth panel does.
+#[derive(Component, Clone)]
+pub struct BluetoothButton(pub BluetoothAction);
+
+/// Spawns a panel with power and scan buttons and the devices of the adapter.
+/// Pressing a device pairs, connects or disconnects it.
+pub fn spawn_bluetooth_panel(commands: &mut Commands, font: Handle<Font>, node: Node) -> Entity {
+    commands
+        .spawn((
+            Node {
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                ..node
+            },
+            BackgroundColor(Color::srgb(0.08, 0.08, 0.08)),
+            BluetoothPanel { font },
+        ))
+        .id()
+}
+
+pub(crate) fn bluetooth_button_system(
+    mut query: Query<(&Interaction, &BluetoothButton, &mut BackgroundColor), Changed<Interaction>>,
+    mut writer: EventWriter<BluetoothActionEvent>,
+) {
+    for (interaction, button, mut color) in query.iter_mut() {
+        color.0 = match interaction {
+            Interaction::Hovered | Interaction::Pressed => HOVERED_BUTTON,
+            Interaction::None => NORMAL_BUTTON,
+        };
+        if *interaction == Interaction::Pressed {
+            writer.write(BluetoothActionEvent(button.0.clone()));
+        }
+    }
+}
+
+pub(crate) fn update_bluetooth_panel(
+    mut commands: Commands,
+    state: Res<BluetoothState>,
+    panels: Query<(Entity, &BluetoothPanel)>,
+    // State the panels were last built from
+    mut shown: Local<Option<BluetoothState>>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    // The panel doesn't show the RSSI, no need to rebuild it for every scan result
+    if shown
+        .as_ref()
+        .is_some_and(|shown| shown.eq_ignoring_rssi(&state))
+    {
+        return;
+    }
+    *shown = Some(state.clone());
+    for (entity, panel) in panels.iter() {
+        let text_font = TextFont {
+            font: panel.font.clone(),
+            font_size: 18.0,
+            ..default()
+        };
+        commands
+            .entity(entity)
+            .despawn_related::<Children>()
+            .with_children(|parent| {
+                let Some(adapter) = &state.adapter else {
+                    parent.spawn((
+                        Text::new("No Bluetooth adapter"),
+                        text_font.clone(),
+                        TextColor(DIM_TEXT),
+                    ));
+                    return;
+                };
+                if !adapter.powered {
+                    spawn_button(
+                        parent,
+                        &text_font,
+                        format!("Bluetooth ({}): off", adapter.name),
+                        BluetoothAction::SetPowered(true),
+                    );
+                    return;
+                }
+                spawn_button(
+                    parent,
+                    &text_font,
+                    format!("Bluetooth ({}): on", adapter.name),
+                    BluetoothAction::SetPowered(false),
+                );
+                if adapter.discovering {
+                    spawn_button(
+                        parent,
+                        &text_font,
+                        "Stop scanning".to_string(),
+                        BluetoothAction::StopDiscovery,
+                    );
+                } else {
+                    spawn_button(
+                        parent,
+                        &text_font,
+                        "Scan".to_string(),
+                        BluetoothAction::StartDiscovery,
+                    );
+                }
+
+                for device in state.devices.iter() {
+                    let (status, action) = if device.connected {
+                        (
+                            "connected",
+                            BluetoothAction::Disconnect(device.address.clone()),
+                        )
+                    } else if device.paired {
+                        ("paired", BluetoothAction::Connect(device.address.clone()))
+                    } else {
+                        ("pair", BluetoothAction::Pair(device.address.clone()))
+                    };
+                    let mut label = format!("{}   {}", device.name, status);
+                    if let Some(battery) = device.battery {
+                        label.push_str(&format!("   {}%", battery));
+                    }
+                    spawn_button(parent, &text_font, label, action);
+                }
+            });
+    }
+}
+
+fn spawn_button(
+    parent: &mut ChildSpawnerCommands,
+    font: &TextFont,
+    label: String,
+    action: BluetoothAction,
+) {
+    parent
+        .spawn((
+            Button,
+            Node {
+                padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+                ..default()
+            },
+            BackgroundColor(NORMAL_BUTTON),
+            BluetoothButton(action),
+        ))
+        .with_child((
+            Text::new(label),
+            font.clone(),
+            TextColor(Color::srgb(0.9, 0.9, 0.9)),
+        ));
+}
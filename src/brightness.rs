@@ -0,0 +1,613 @@
+pub mod ui;
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc::Sender,
+    time::Duration,
+};
+
+use async_std::channel::{self, Receiver};
+use bevy::{
+    prelude::*,
+    tasks::{IoTaskPool, Task, block_on, futures_lite::future},
+};
+use zbus::Connection;
+
+use crate::{
+    bus::{SystemBus, SystemBusPlugin},
+    logind::proxies::SessionProxy,
+    result_channel::ResultChannel,
+};
+
+pub const BACKLIGHT_ROOT: &str = "/sys/class/backlight";
+
+/// How often the devices are read again, to follow changes made by brightness
+/// keys or other programs.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A device under the backlight root, such as `intel_backlight`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backlight {
+    pub name: String,
+    pub brightness: u32,
+    pub max_brightness: u32,
+}
+
+impl Backlight {
+    /// Brightness from 0.0 to 1.0.
+    pub fn fraction(&self) -> f32 {
+        if self.max_brightness == 0 {
+            return 0.0;
+        }
+        self.brightness as f32 / self.max_brightness as f32
+    }
+
+    /// Brightness value for `fraction`, from 0.0 to 1.0, of the maximum.
+    pub fn value_at(&self, fraction: f32) -> u32 {
+        (fraction.clamp(0.0, 1.0) * self.max_brightness as f32).round() as u32
+    }
+}
+
+fn read_value(path: &Path) -> io::Result<u32> {
+    fs::read_to_string(path)?.trim().parse().map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), e),
+        )
+    })
+}
+
+pub fn read_backlight(root: &Path, name: &str) -> io::Result<Backlight> {
+    let dir = root.join(name);
+    Ok(Backlight {
+        name: name.to_string(),
+        brightness: read_value(&dir.join("brightness"))?,
+        max_brightness: read_value(&dir.join("max_brightness"))?,
+    })
+}
+
+/// Reads every device under `root`, sorted by name. A missing root means no
+/// devices, as on desktops with external monitors only.
+pub fn read_backlights(root: &Path) -> io::Result<Vec<Backlight>> {
+    let entries = match fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut backlights = Vec::new();
+    for entry in entries {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        match read_backlight(root, &name) {
+            Ok(backlight) => backlights.push(backlight),
+            Err(e) => warn!("Skipping backlight {name}: {e}"),
+        }
+    }
+    backlights.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(backlights)
+}
+
+/// Backlight devices as last read, updated by the plugin as they change.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Eq)]
+pub struct BrightnessState {
+    pub backlights: Vec<Backlight>,
+}
+
+impl BrightnessState {
+    pub fn backlight(&self, name: &str) -> Option<&Backlight> {
+        self.backlights
+            .iter()
+            .find(|backlight| backlight.name == name)
+    }
+}
+
+/// Sent whenever `BrightnessState` changes.
+#[derive(Event, Debug, Clone)]
+pub struct BrightnessChanged(pub BrightnessState);
+
+/// Sets the brightness of the named device, from 0 to its `max_brightness`.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct SetBrightness {
+    pub backlight: String,
+    pub brightness: u32,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct BrightnessErrorEvent {
+    pub backlight: String,
+    pub reason: String,
+}
+
+/// Directory the devices are read from.
+#[derive(Resource, Debug, Clone)]
+pub struct BacklightRoot(pub PathBuf);
+
+#[derive(Resource)]
+struct RefreshTimer(Timer);
+
+/// Reading of the devices, done on the IO pool as sysfs reads can block.
+#[derive(Resource)]
+struct ReadTask(Task<io::Result<Vec<Backlight>>>);
+
+/// What the task setting brightness sends back.
+enum BrightnessResult {
+    Set { backlight: String, brightness: u32 },
+    Failed { backlight: String, reason: String },
+}
+
+/// How `SetBrightness` reaches the devices.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BrightnessBackend {
+    /// logind's `Session.SetBrightness` on the shared system bus, which needs
+    /// no write access to sysfs. Devices must be the system's, as logind
+    /// only knows those.
+    #[default]
+    Logind,
+    /// Writes the `brightness` files under the plugin's root.
+    Files,
+}
+
+/// `BrightnessBackend` with what it needs to write.
+enum BacklightWriter {
+    Logind(Connection),
+    Files(PathBuf),
+}
+
+impl BacklightWriter {
+    async fn write(&self, backlight: &str, brightness: u32) -> io::Result<()> {
+        match self {
+            BacklightWriter::Logind(connection) => SessionProxy::new(connection)
+                .await
+                .map_err(io::Error::other)?
+                .set_brightness("backlight", backlight, brightness)
+                .await
+                .map_err(io::Error::other),
+            BacklightWriter::Files(root) => {
+                let path = root.join(backlight).join("brightness");
+                async_std::fs::write(path, brightness.to_string()).await
+            }
+        }
+    }
+}
+
+/// Requests waiting for the task writing them, one at a time. Dragging the
+/// slider sends one per frame, and writing them concurrently could leave an
+/// older one last.
+#[derive(Resource)]
+struct WriteQueue(channel::Sender<(String, u32)>);
+
+/// Writes what arrives on `requests` until the plugin is gone. Of the
+/// requests that queued up during a write, only the latest per device is
+/// written.
+async fn write_brightness(
+    writer: BacklightWriter,
+    requests: Receiver<(String, u32)>,
+    sender: Sender<BrightnessResult>,
+) {
+    while let Ok(request) = requests.recv().await {
+        let mut latest = vec![request];
+        while let Ok((backlight, brightness)) = requests.try_recv() {
+            latest.retain(|(name, _)| *name != backlight);
+            latest.push((backlight, brightness));
+        }
+        for (backlight, brightness) in latest {
+            let result = writer.write(&backlight, brightness).await;
+            let _ = sender.send(match result {
+                Ok(()) => BrightnessResult::Set {
+                    backlight,
+                    brightness,
+                },
+                Err(e) => {
+                    error!("Setting the brightness of {backlight} failed: {e}");
+                    BrightnessResult::Failed {
+                        backlight,
+                        reason: e.to_string(),
+                    }
+                }
+            });
+        }
+    }
+}
+
+/// Reads the backlight devices under `root` into `BrightnessState` and runs
+/// `SetBrightness` through `backend`.
+pub struct BrightnessPlugin {
+    pub root: PathBuf,
+    pub backend: BrightnessBackend,
+}
+
+impl Default for BrightnessPlugin {
+    fn default() -> Self {
+        Self {
+            root: PathBuf::from(BACKLIGHT_ROOT),
+            backend: BrightnessBackend::Logind,
+        }
+    }
+}
+
+impl BrightnessPlugin {
+    /// Reads and writes the files of a directory laid out like
+    /// `/sys/class/backlight`, such as a fake one for testing.
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            backend: BrightnessBackend::Files,
+        }
+    }
+}
+
+impl Plugin for BrightnessPlugin {
+    fn build(&self, app: &mut App) {
+        if self.backend == BrightnessBackend::Logind {
+            SystemBusPlugin::add_once(app);
+        }
+        app.insert_resource(BacklightRoot(self.root.clone()))
+            .insert_resource(self.backend)
+            .insert_resource(RefreshTimer(Timer::new(
+                REFRESH_INTERVAL,
+                TimerMode::Repeating,
+            )))
+            .init_resource::<BrightnessState>()
+            .init_resource::<ResultChannel<BrightnessResult>>()
+            .add_event::<BrightnessChanged>()
+            .add_event::<SetBrightness>()
+            .add_event::<BrightnessErrorEvent>()
+            .add_systems(Startup, read_brightness_state)
+            .add_systems(
+                Update,
+                (
+                    start_writer,
+                    refresh_brightness_state,
+                    poll_brightness_read.after(refresh_brightness_state),
+                    handle_set_brightness_events.after(start_writer),
+                    poll_brightness_results.after(handle_set_brightness_events),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    ui::brightness_slider_system,
+                    ui::update_brightness_slider
+                        .after(poll_brightness_read)
+                        .after(poll_brightness_results),
+                ),
+            );
+    }
+}
+
+/// Starts the task writing `SetBrightness` requests, for logind once the
+/// shared system bus is connected.
+fn start_writer(
+    mut commands: Commands,
+    backend: Res<BrightnessBackend>,
+    root: Res<BacklightRoot>,
+    bus: Option<Res<SystemBus>>,
+    queue: Option<Res<WriteQueue>>,
+    results: Res<ResultChannel<BrightnessResult>>,
+) {
+    if queue.is_some() {
+        return;
+    }
+    let writer = match *backend {
+        BrightnessBackend::Logind => {
+            let Some(connection) = bus.and_then(|bus| bus.connection.clone()) else {
+                return;
+            };
+            BacklightWriter::Logind(connection)
+        }
+        BrightnessBackend::Files => BacklightWriter::Files(root.0.clone()),
+    };
+    let (tx, rx) = channel::unbounded();
+    IoTaskPool::get()
+        .spawn(write_brightness(writer, rx, results.sender()))
+        .detach();
+    commands.insert_resource(WriteQueue(tx));
+}
+
+fn update_state(
+    state: &mut ResMut<BrightnessState>,
+    writer: &mut EventWriter<BrightnessChanged>,
+    backlights: Vec<Backlight>,
+) {
+    if state.backlights != backlights {
+        state.backlights = backlights;
+        writer.write(BrightnessChanged((**state).clone()));
+    }
+}
+
+fn read_brightness_state(mut commands: Commands, root: Res<BacklightRoot>) {
+    let root = root.0.clone();
+    let task = IoTaskPool::get().spawn(async move { read_backlights(&root) });
+    commands.insert_resource(ReadTask(task));
+}
+
+fn refresh_brightness_state(
+    commands: Commands,
+    time: Res<Time>,
+    mut timer: ResMut<RefreshTimer>,
+    root: Res<BacklightRoot>,
+    task: Option<Res<ReadTask>>,
+) {
+    // A read still running after a whole interval is left to finish
+    if timer.0.tick(time.delta()).just_finished() && task.is_none() {
+        read_brightness_state(commands, root);
+    }
+}
+
+fn poll_brightness_read(
+    mut commands: Commands,
+    task: Option<ResMut<ReadTask>>,
+    root: Res<BacklightRoot>,
+    mut state: ResMut<BrightnessState>,
+    mut writer: EventWriter<BrightnessChanged>,
+) {
+    let Some(mut task) = task else {
+        return;
+    };
+    let Some(result) = block_on(future::poll_once(&mut task.0)) else {
+        return;
+    };
+    commands.remove_resource::<ReadTask>();
+    match result {
+        Ok(backlights) => update_state(&mut state, &mut writer, backlights),
+        Err(e) => error!("Failed to read {}: {e}", root.0.display()),
+    }
+}
+
+fn handle_set_brightness_events(
+    mut events: EventReader<SetBrightness>,
+    queue: Option<Res<WriteQueue>>,
+    state: Res<BrightnessState>,
+    mut error_writer: EventWriter<BrightnessErrorEvent>,
+) {
+    for SetBrightness {
+        backlight,
+        brightness,
+    } in events.read()
+    {
+        let fail = |reason: &str| BrightnessErrorEvent {
+            backlight: backlight.clone(),
+            reason: reason.to_string(),
+        };
+        let Some(queue) = &queue else {
+            error_writer.write(fail("the system bus is not available"));
+            continue;
+        };
+        let Some(device) = state.backlight(backlight) else {
+            error_writer.write(fail("unknown backlight"));
+            continue;
+        };
+        let brightness = (*brightness).min(device.max_brightness);
+        let _ = queue.0.try_send((backlight.clone(), brightness));
+    }
+}
+
+fn poll_brightness_results(
+    results: Res<ResultChannel<BrightnessResult>>,
+    mut state: ResMut<BrightnessState>,
+    mut writer: EventWriter<BrightnessChanged>,
+    mut error_writer: EventWriter<BrightnessErrorEvent>,
+) {
+    for result in results.drain() {
+        match result {
+            // Shown right away rather than at the next refresh
+            BrightnessResult::Set {
+                backlight,
+                brightness,
+            } => {
+                let mut backlights = state.backlights.clone();
+                if let Some(device) = backlights.iter_mut().find(|b| b.name == backlight) {
+                    device.brightness = brightness;
+                }
+                update_state(&mut state, &mut writer, backlights);
+            }
+            BrightnessResult::Failed { backlight, reason } => {
+                error_writer.write(BrightnessErrorEvent { backlight, reason });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        os::unix::net::UnixStream,
+        sync::{Arc, Mutex},
+    };
+
+    use zbus::{Guid, connection, interface};
+
+    use super::*;
+    use crate::testing::update_until;
+
+    /// A fake backlight root under the system's temporary directory, removed
+    /// when dropped.
+    struct FakeRoot(PathBuf);
+
+    impl FakeRoot {
+        fn new(test: &str) -> Self {
+            let root = std::env::temp_dir().join(format!(
+                "counter_bevy_backlight_{}_{}",
+                test,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+            Self(root)
+        }
+
+        fn add(&self, name: &str, brightness: &str, max_brightness: &str) {
+            let dir = self.0.join(name);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("brightness"), brightness).unwrap();
+            fs::write(dir.join("max_brightness"), max_brightness).unwrap();
+        }
+    }
+
+    impl Drop for FakeRoot {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn reads_devices_sorted_by_name() {
+        let root = FakeRoot::new("sorted");
+        root.add("intel_backlight", "4800\n", "19200\n");
+        root.add("acpi_video0", "7\n", "15\n");
+        let backlights = read_backlights(&root.0).unwrap();
+        assert_eq!(
+            backlights,
+            vec![
+                Backlight {
+                    name: "acpi_video0".to_string(),
+                    brightness: 7,
+                    max_brightness: 15,
+                },
+                Backlight {
+                    name: "intel_backlight".to_string(),
+                    brightness: 4800,
+                    max_brightness: 19200,
+                },
+            ]
+        );
+        assert_eq!(backlights[1].fraction(), 0.25);
+    }
+
+    #[test]
+    fn skips_unreadable_devices() {
+        let root = FakeRoot::new("unreadable");
+        root.add("intel_backlight", "100", "200");
+        root.add("broken", "dim", "200");
+        fs::create_dir_all(root.0.join("empty")).unwrap();
+        let names: Vec<String> = read_backlights(&root.0)
+            .unwrap()
+            .into_iter()
+            .map(|backlight| backlight.name)
+            .collect();
+        assert_eq!(names, vec!["intel_backlight"]);
+    }
+
+    #[test]
+    fn missing_root_has_no_devices() {
+        let root = FakeRoot::new("missing");
+        fs::remove_dir_all(&root.0).unwrap();
+        assert!(read_backlights(&root.0).unwrap().is_empty());
+    }
+
+    fn brightness(world: &World) -> Option<u32> {
+        world
+            .resource::<BrightnessState>()
+            .backlight("intel_backlight")
+            .map(|backlight| backlight.brightness)
+    }
+
+    #[test]
+    fn set_brightness_writes_the_fake_root_and_updates_the_state() {
+        let root = FakeRoot::new("set");
+        root.add("intel_backlight", "100\n", "200\n");
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, BrightnessPlugin::with_root(&root.0)));
+        assert!(update_until(&mut app, |world| {
+            world.contains_resource::<WriteQueue>() && brightness(world) == Some(100)
+        }));
+
+        for brightness in [120, 150] {
+            app.world_mut().send_event(SetBrightness {
+                backlight: "intel_backlight".to_string(),
+                brightness,
+            });
+        }
+        assert!(update_until(&mut app, |world| brightness(world) == Some(150)));
+        let read = read_backlight(&root.0, "intel_backlight").unwrap();
+        assert_eq!(read.brightness, 150);
+
+        // Clamped to the maximum
+        app.world_mut().send_event(SetBrightness {
+            backlight: "intel_backlight".to_string(),
+            brightness: 500,
+        });
+        assert!(update_until(&mut app, |world| brightness(world) == Some(200)));
+        let read = read_backlight(&root.0, "intel_backlight").unwrap();
+        assert_eq!(read.brightness, 200);
+    }
+
+    #[test]
+    fn value_at_clamps_and_rounds() {
+        let backlight = Backlight {
+            name: "intel_backlight".to_string(),
+            brightness: 0,
+            max_brightness: 15,
+        };
+        assert_eq!(backlight.value_at(0.5), 8);
+        assert_eq!(backlight.value_at(-1.0), 0);
+        assert_eq!(backlight.value_at(2.0), 15);
+    }
+
+    /// Stands in for logind's session object, recording `SetBrightness` calls.
+    struct MockSession(Arc<Mutex<Vec<(String, String, u32)>>>);
+
+    #[interface(name = "org.freedesktop.login1.Session")]
+    impl MockSession {
+        fn set_brightness(&self, subsystem: &str, name: &str, brightness: u32) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((subsystem.to_string(), name.to_string(), brightness));
+        }
+    }
+
+    #[test]
+    fn set_brightness_goes_through_the_logind_session() {
+        let root = FakeRoot::new("logind");
+        root.add("intel_backlight", "100\n", "200\n");
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        // Peer-to-peer, so the test needs no bus daemon
+        let (server, client) = UnixStream::pair().unwrap();
+        let session = MockSession(calls.clone());
+        let server = async {
+            connection::Builder::unix_stream(server)
+                .server(Guid::generate())?
+                .p2p()
+                .serve_at("/org/freedesktop/login1/session/auto", session)?
+                .build()
+                .await
+        };
+        let client = connection::Builder::unix_stream(client).p2p().build();
+        // Both ends are built together, as each waits for the other
+        let (server, connection): (zbus::Result<Connection>, _) =
+            block_on(future::zip(server, client));
+        let _server = server.unwrap();
+        let connection = connection.unwrap();
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(SystemBus {
+                connection: Some(connection),
+            })
+            .add_plugins(BrightnessPlugin {
+                root: root.0.clone(),
+                backend: BrightnessBackend::Logind,
+            });
+        assert!(update_until(&mut app, |world| {
+            world.contains_resource::<WriteQueue>() && brightness(world) == Some(100)
+        }));
+
+        app.world_mut().send_event(SetBrightness {
+            backlight: "intel_backlight".to_string(),
+            brightness: 500,
+        });
+        assert!(update_until(&mut app, |world| brightness(world) == Some(200)));
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![(
+                "backlight".to_string(),
+                "intel_backlight".to_string(),
+                200
+            )]
+        );
+        // logind writes the system's devices, not the fake root
+        let read = read_backlight(&root.0, "intel_backlight").unwrap();
+        assert_eq!(read.brightness, 100);
+    }
+}
@@ -0,0 +1,165 @@
+//! Ready-made brightness slider for the brightness plugin.
+
+use bevy::{prelude::*, ui::RelativeCursorPosition};
+
+use super::{Backlight, BrightnessState, SetBrightness};
+
+const TRACK: Color = Color::srgb(0.2, 0.2, 0.2);
+const FILL: Color = Color::srgb(0.95, 0.8, 0.3);
+
+/// Lowest share of the maximum the slider sets, as many panels turn fully
+/// off at 0 and leave no way to see the slider again.
+const MIN_FRACTION: f32 = 0.02;
+
+/// Brightness slider driven by [`BrightnessState`], controlling its first
+/// device.
+#[derive(Component)]
+pub struct BrightnessSlider;
+
+/// Track of the slider, pressing or dragging along it sets the brightness.
+#[derive(Component)]
+pub(crate) struct BrightnessTrack;
+
+#[derive(Component)]
+pub(crate) struct BrightnessFill;
+
+#[derive(Component)]
+pub(crate) struct BrightnessText;
+
+/// Spawns a track filled up to the brightness, and the brightness as text.
+pub fn spawn_brightness_slider(commands: &mut Commands, font: Handle<Font>, node: Node) -> Entity {
+    commands
+        .spawn((
+            Node {
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(10.0),
+                ..node
+            },
+            BrightnessSlider,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        width: Val::Px(200.0),
+                        height: Val::Px(12.0),
+                        ..default()
+                    },
+                    BackgroundColor(TRACK),
+                    BorderRadius::MAX,
+                    RelativeCursorPosition::default(),
+                    BrightnessTrack,
+                ))
+                .with_child((
+                    Node {
+                        width: Val::Percent(0.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    BackgroundColor(FILL),
+                    BorderRadius::MAX,
+                    BrightnessFill,
+                ));
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font,
+                    font_size: 20.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                BrightnessText,
+            ));
+        })
+        .id()
+}
+
+pub(crate) fn brightness_slider_system(
+    tracks: Query<(&Interaction, &RelativeCursorPosition), With<BrightnessTrack>>,
+    state: Res<BrightnessState>,
+    // Last value asked for, as the state only catches up after a round trip
+    mut requested: Local<Option<u32>>,
+    mut writer: EventWriter<SetBrightness>,
+) {
+    let Some(backlight) = state.backlights.first() else {
+        return;
+    };
+    if tracks
+        .iter()
+        .all(|(interaction, _)| *interaction != Interaction::Pressed)
+    {
+        *requested = None;
+    }
+    // Checked every frame rather than on change, to follow drags
+    for (interaction, cursor) in tracks.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some(position) = cursor.normalized else {
+            continue;
+        };
+        let brightness = slider_value(backlight, position.x);
+        if brightness != requested.unwrap_or(backlight.brightness) {
+            *requested = Some(brightness);
+            writer.write(SetBrightness {
+                backlight: backlight.name.clone(),
+                brightness,
+            });
+        }
+    }
+}
+
+/// Brightness for the slider at `fraction`, never below `MIN_FRACTION` of the
+/// maximum or 1.
+fn slider_value(backlight: &Backlight, fraction: f32) -> u32 {
+    let lowest = backlight.value_at(MIN_FRACTION).max(1);
+    backlight
+        .value_at(fraction)
+        .max(lowest)
+        .min(backlight.max_brightness)
+}
+
+pub(crate) fn update_brightness_slider(
+    state: Res<BrightnessState>,
+    mut fills: Query<&mut Node, With<BrightnessFill>>,
+    mut texts: Query<&mut Text, With<BrightnessText>>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    let backlight = state.backlights.first();
+    let fraction = backlight.map_or(0.0, |backlight| backlight.fraction());
+    for mut node in fills.iter_mut() {
+        node.width = Val::Percent(fraction * 100.0);
+    }
+    for mut text in texts.iter_mut() {
+        text.0 = match backlight {
+            Some(_) => format!("{:.0}%", fraction * 100.0),
+            None => "No backlight".to_string(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backlight(max_brightness: u32) -> Backlight {
+        Backlight {
+            name: "intel_backlight".to_string(),
+            brightness: 0,
+            max_brightness,
+        }
+    }
+
+    #[test]
+    fn slider_never_turns_the_backlight_off() {
+        assert_eq!(slider_value(&backlight(19200), 0.0), 384);
+        assert_eq!(slider_value(&backlight(19200), 0.5), 9600);
+        assert_eq!(slider_value(&backlight(19200), 1.0), 19200);
+        // Too few steps for a share of the maximum
+        assert_eq!(slider_value(&backlight(15), 0.0), 1);
+        assert_eq!(slider_value(&backlight(0), 0.5), 0);
+    }
+}
@@ -0,0 +1,108 @@
+//! Choice between the session and the system bus, shared by the plugins and
+//! tools that can run on either, and the system bus connection the plugins
+//! share.
+
+use std::str::FromStr;
+
+use bevy::prelude::*;
+use bevy::tasks::{IoTaskPool, Task, block_on, futures_lite::future};
+use zbus::{Connection, connection};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bus {
+    Session,
+    System,
+}
+
+impl Bus {
+    pub async fn connect(self) -> zbus::Result<Connection> {
+        match self {
+            Bus::Session => Connection::session().await,
+            Bus::System => Connection::system().await,
+        }
+    }
+
+    /// For connections that need more setup, such as serving objects.
+    pub fn builder(self) -> zbus::Result<connection::Builder<'static>> {
+        match self {
+            Bus::Session => connection::Builder::session(),
+            Bus::System => connection::Builder::system(),
+        }
+    }
+}
+
+impl FromStr for Bus {
+    type Err = String;
+
+    /// Parses `session` or `system`, as given on the command line.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "session" => Ok(Bus::Session),
+            "system" => Ok(Bus::System),
+            other => Err(format!("unknown bus {}", other)),
+        }
+    }
+}
+
+/// Connection to the system bus, shared by every plugin talking to a system
+/// service. None until connected, or when connecting failed.
+#[derive(Resource, Default, Clone)]
+pub struct SystemBus {
+    pub connection: Option<Connection>,
+}
+
+#[derive(Resource)]
+struct SystemBusTask(Task<Option<Connection>>);
+
+/// Connects [`SystemBus`] on startup. Plugins add it when it isn't added
+/// already, so the connection is opened once however many of them run.
+pub struct SystemBusPlugin;
+
+impl Plugin for SystemBusPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SystemBus>()
+            .add_systems(Startup, connect_system_bus)
+            .add_systems(PreUpdate, poll_system_bus);
+    }
+}
+
+impl SystemBusPlugin {
+    /// Adds the plugin to `app` unless another plugin did already.
+    pub fn add_once(app: &mut App) {
+        if !app.is_plugin_added::<SystemBusPlugin>() {
+            app.add_plugins(SystemBusPlugin);
+        }
+    }
+}
+
+/// A connection already in [`SystemBus`] is kept, which lets tests hand in a
+/// peer-to-peer one.
+fn connect_system_bus(mut commands: Commands, bus: Res<SystemBus>) {
+    if bus.connection.is_some() {
+        return;
+    }
+    let task = IoTaskPool::get().spawn(async {
+        match Connection::system().await {
+            Ok(connection) => Some(connection),
+            Err(e) => {
+                error!("Failed to connect to the system bus: {e}");
+                None
+            }
+        }
+    });
+    commands.insert_resource(SystemBusTask(task));
+}
+
+fn poll_system_bus(
+    mut commands: Commands,
+    task: Option<ResMut<SystemBusTask>>,
+    mut bus: ResMut<SystemBus>,
+) {
+    let Some(mut task) = task else {
+        return;
+    };
+    if let Some(connection) = block_on(future::poll_once(&mut task.0)) {
+        bus.connection = connection;
+        commands.remove_resource::<SystemBusTask>();
+    }
+}
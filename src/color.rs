@@ -0,0 +1,99 @@
+use std::{fmt, sync::LazyLock};
+
+use bevy::color::Color;
+use regex::Regex;
+
+static RGB_COMPONENT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(\d+(?:\.\d+)?)").unwrap());
+
+// Structure to hold the color
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct BarColor {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+}
+
+impl BarColor {
+    /// A random color, as generated by the color servers.
+    pub fn random() -> Self {
+        Self {
+            r: fastrand::f32(),
+            g: fastrand::f32(),
+            b: fastrand::f32(),
+        }
+    }
+
+    /// Components scaled to `0..=255`, clamping out of range values.
+    pub fn to_rgb8(&self) -> [u8; 3] {
+        [self.r, self.g, self.b].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+    }
+
+    /// `#rrggbb`
+    pub fn to_hex(&self) -> String {
+        let [r, g, b] = self.to_rgb8();
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
+/// Formats as `RGB(r, g, b)`, the wire format understood by [`parse_rgb`].
+impl fmt::Display for BarColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RGB({}, {}, {})", self.r, self.g, self.b)
+    }
+}
+
+impl From<BarColor> for Color {
+    fn from(color: BarColor) -> Self {
+        Color::srgb(color.r, color.g, color.b)
+    }
+}
+
+/// Parses the `RGB(r, g, b)` strings sent by the color servers. Components are
+/// expected in the `0.0..=1.0` range.
+pub fn parse_rgb(s: &str) -> Result<BarColor, String> {
+    let captures: Vec<f32> = RGB_COMPONENT
+        .find_iter(s)
+        .filter_map(|m| m.as_str().parse::<f32>().ok())
+        .collect();
+
+    if captures.len() != 3 {
+        return Err("Invalid RGB format".to_string());
+    }
+
+    Ok(BarColor {
+        r: captures[0],
+        g: captures[1],
+        b: captures[2],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rgb_reads_three_components() {
+        assert_eq!(
+            parse_rgb("RGB(0.1, 0.5, 1)"),
+            Ok(BarColor {
+                r: 0.1,
+                g: 0.5,
+                b: 1.0,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_rgb_rejects_wrong_component_count() {
+        assert!(parse_rgb("RGB(0.1, 0.5)").is_err());
+        assert!(parse_rgb("RGB(0.1, 0.5, 0.2, 0.3)").is_err());
+        assert!(parse_rgb("hello").is_err());
+    }
+
+    #[test]
+    fn parse_rgb_round_trips_display() {
+        let color = BarColor::random();
+        assert!([color.r, color.g, color.b].iter().all(|c| (0.0..=1.0).contains(c)));
+        assert_eq!(parse_rgb(&color.to_string()), Ok(color));
+    }
+}
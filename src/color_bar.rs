@@ -0,0 +1,907 @@
+//! Color bars fed by [`ColorSource`]s, one bar per source.
+
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{
+    color::{Mix, Oklaba, Srgba, palettes::css::*},
+    prelude::*,
+};
+use tokio::sync::watch;
+
+use crate::{
+    color_bridge::{ColorBridgeStats, ColorReceiver, DeliveryPolicy, ReceivedColor, color_bridge},
+    color_source::{ColorSource, SOURCE_USAGE, parse_source_args},
+    level::{DbusLevelSource, LEVEL_USAGE, LevelMeter, LevelReceiver, parse_level_args},
+};
+
+pub const USAGE: &str = "[--transition-ms <ms>] [--easing linear|ease-in|ease-out|ease-in-out] \
+[--color-space srgb|oklab] [--delivery latest|drain|queue] [--frame-budget <n>] [--history <n>] \
+[--bar <source or level flags>...]";
+
+const DEFAULT_HISTORY_LEN: usize = 12;
+
+/// Capacity of the queue for the draining policies.
+const QUEUE_CAPACITY: usize = 10;
+
+enum BarSource {
+    Color(Box<dyn ColorSource>),
+    Level(DbusLevelSource, LevelMeter),
+}
+
+pub struct ColorBarPlugin {
+    sources: Mutex<Vec<BarSource>>,
+    transition: ColorTransition,
+    delivery: DeliveryPolicy,
+    history_len: usize,
+}
+
+impl ColorBarPlugin {
+    pub fn new(source: Box<dyn ColorSource>) -> Self {
+        Self::empty().with_bar(source)
+    }
+
+    fn empty() -> Self {
+        Self {
+            sources: Mutex::new(Vec::new()),
+            transition: ColorTransition::default(),
+            delivery: DeliveryPolicy::default(),
+            history_len: DEFAULT_HISTORY_LEN,
+        }
+    }
+
+    /// Adds another bar, updated independently from `source`.
+    pub fn with_bar(self, source: Box<dyn ColorSource>) -> Self {
+        self.sources.lock().unwrap().push(BarSource::Color(source));
+        self
+    }
+
+    /// Adds a level meter whose fill tracks the numeric value of `source`.
+    pub fn with_level_bar(self, source: DbusLevelSource, meter: LevelMeter) -> Self {
+        self.sources
+            .lock()
+            .unwrap()
+            .push(BarSource::Level(source, meter));
+        self
+    }
+
+    pub fn with_transition(mut self, transition: ColorTransition) -> Self {
+        self.transition = transition;
+        self
+    }
+
+    pub fn with_delivery(mut self, delivery: DeliveryPolicy) -> Self {
+        self.delivery = delivery;
+        self
+    }
+
+    /// Number of past colors shown in each bar's history strip.
+    pub fn with_history_len(mut self, history_len: usize) -> Self {
+        self.history_len = history_len;
+        self
+    }
+
+    /// Builds the plugin from command line arguments: the flags in [`USAGE`]
+    /// plus everything accepted by [`parse_source_args`]. Each `--bar` starts
+    /// the source flags of an additional bar, and a bar with `--level` takes
+    /// [`LEVEL_USAGE`] flags instead.
+    pub fn from_args(
+        args: impl Iterator<Item = String>,
+        default_source: &str,
+    ) -> Result<Self, String> {
+        let mut transition = ColorTransition::default();
+        let mut delivery = "latest".to_string();
+        let mut frame_budget = 1;
+        let mut history_len = DEFAULT_HISTORY_LEN;
+        let mut bar_args = vec![Vec::new()];
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+            match arg.as_str() {
+                "--transition-ms" => {
+                    let value = value()?;
+                    transition.duration = value
+                        .parse::<u64>()
+                        .map(Duration::from_millis)
+                        .map_err(|e| format!("invalid milliseconds {:?}: {}", value, e))?;
+                }
+                "--easing" => {
+                    transition.easing = match value()?.as_str() {
+                        "linear" => Easing::Linear,
+                        "ease-in" => Easing::EaseIn,
+                        "ease-out" => Easing::EaseOut,
+                        "ease-in-out" => Easing::EaseInOut,
+                        other => return Err(format!("unknown easing {}", other)),
+                    }
+                }
+                "--color-space" => {
+                    transition.space = match value()?.as_str() {
+                        "srgb" => InterpolationSpace::Srgb,
+                        "oklab" => InterpolationSpace::Oklab,
+                        other => return Err(format!("unknown color space {}", other)),
+                    }
+                }
+                "--delivery" => delivery = value()?,
+                "--frame-budget" => {
+                    let value = value()?;
+                    frame_budget = value
+                        .parse()
+                        .map_err(|e| format!("invalid frame budget {:?}: {}", value, e))?;
+                }
+                "--history" => {
+                    let value = value()?;
+                    history_len = value
+                        .parse()
+                        .map_err(|e| format!("invalid history length {:?}: {}", value, e))?;
+                }
+                "--bar" => bar_args.push(Vec::new()),
+                _ => bar_args.last_mut().unwrap().push(arg.clone()),
+            }
+        }
+        let delivery = match delivery.as_str() {
+            "latest" => DeliveryPolicy::LatestWins,
+            "drain" => DeliveryPolicy::DrainAll,
+            "queue" => DeliveryPolicy::Queue {
+                per_frame: frame_budget,
+            },
+            other => return Err(format!("unknown delivery policy {}", other)),
+        };
+
+        let mut plugin = Self::empty();
+        for args in bar_args {
+            plugin = if args.iter().any(|arg| arg == "--level") {
+                let (source, meter) = parse_level_args(args.into_iter())?;
+                plugin.with_level_bar(source, meter)
+            } else {
+                plugin.with_bar(parse_source_args(args.into_iter(), default_source)?)
+            };
+        }
+        Ok(plugin
+            .with_transition(transition)
+            .with_delivery(delivery)
+            .with_history_len(history_len))
+    }
+
+    pub fn usage() -> String {
+        format!("{} {}\n  level bar: {}", SOURCE_USAGE, USAGE, LEVEL_USAGE)
+    }
+}
+
+impl Plugin for ColorBarPlugin {
+    fn build(&self, app: &mut App) {
+        let sources = std::mem::take(&mut *self.sources.lock().unwrap());
+        app.insert_resource(PendingColorSources(Mutex::new(sources)))
+            .insert_resource(self.transition.clone())
+            .insert_resource(self.delivery)
+            .insert_resource(HistoryLength(self.history_len))
+            .add_systems(Startup, setup_bars)
+            .add_systems(
+                Update,
+                (
+                    color_change_system,
+                    animate_bar_color,
+                    update_history_ui,
+                    update_stats_text,
+                    level_change_system,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps linear progress `t` in `0.0..=1.0` onto the eased curve.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterpolationSpace {
+    Srgb,
+    /// Perceptually uniform, avoids the muddy midpoints of sRGB blends.
+    Oklab,
+}
+
+impl InterpolationSpace {
+    pub fn mix(self, from: Color, to: Color, t: f32) -> Color {
+        match self {
+            InterpolationSpace::Srgb => Srgba::from(from).mix(&Srgba::from(to), t).into(),
+            InterpolationSpace::Oklab => Oklaba::from(from).mix(&Oklaba::from(to), t).into(),
+        }
+    }
+}
+
+/// How the bar fades towards a newly received color. A zero duration snaps.
+#[derive(Resource, Debug, Clone)]
+pub struct ColorTransition {
+    pub duration: Duration,
+    pub easing: Easing,
+    pub space: InterpolationSpace,
+}
+
+impl Default for ColorTransition {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_millis(400),
+            easing: Easing::EaseInOut,
+            space: InterpolationSpace::Oklab,
+        }
+    }
+}
+
+/// In-flight fade of a bar's background color.
+#[derive(Component, Debug, Clone)]
+pub struct ColorAnimation {
+    from: Color,
+    to: Color,
+    elapsed: Duration,
+}
+
+// The sources are only started on Startup, once the task pools exist.
+#[derive(Resource)]
+struct PendingColorSources(Mutex<Vec<BarSource>>);
+
+#[derive(Resource)]
+struct HistoryLength(usize);
+
+/// The most recent colors of a bar, newest first.
+#[derive(Component, Debug, Clone)]
+pub struct ColorHistory {
+    entries: VecDeque<ReceivedColor>,
+    capacity: usize,
+}
+
+impl ColorHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, received: ReceivedColor) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_back();
+        }
+        self.entries.push_front(received);
+    }
+
+    pub fn latest(&self) -> Option<&ReceivedColor> {
+        self.entries.front()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ReceivedColor> {
+        self.entries.iter()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+/// A bar painted with the colors of its own source. The source's receiver,
+/// history and stats live on the same entity.
+#[derive(Component)]
+pub struct Bar {
+    pub source_name: String,
+}
+
+#[derive(Component)]
+struct ColorReadout {
+    bar: Entity,
+}
+
+/// Inner child of a level bar, resized to the current value.
+#[derive(Component)]
+struct LevelFill;
+
+#[derive(Component)]
+struct LevelReadout {
+    bar: Entity,
+}
+
+#[derive(Component)]
+struct StatsText {
+    bar: Entity,
+}
+
+/// Slot in a history strip, 0 being the newest color.
+#[derive(Component)]
+struct HistorySwatch {
+    bar: Entity,
+    index: usize,
+}
+
+#[derive(Component)]
+struct HistoryTime {
+    bar: Entity,
+    index: usize,
+}
+
+fn setup_bars(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    pending: Res<PendingColorSources>,
+    delivery: Res<DeliveryPolicy>,
+    history_len: Res<HistoryLength>,
+) {
+    // ui camera
+    commands.spawn(Camera2d);
+
+    let text_font = (
+        TextFont {
+            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+            font_size: 24.0,
+            ..Default::default()
+        },
+        TextColor(Color::srgb(0.9, 0.9, 0.9)),
+    );
+    let small_font = (
+        TextFont {
+            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+            font_size: 14.0,
+            ..Default::default()
+        },
+        TextColor(Color::srgb(0.7, 0.7, 0.7)),
+    );
+
+    let sources = std::mem::take(&mut *pending.0.lock().unwrap());
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(Color::BLACK),
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(Node {
+                    width: Val::Percent(80.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(30.),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for source in sources {
+                        let mut row = parent.spawn(Node {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            align_self: AlignSelf::Stretch,
+                            ..default()
+                        });
+                        match source {
+                            BarSource::Color(source) => {
+                                let source_name = source.name();
+                                println!("Starting color source: {} ({:?})", source_name, *delivery);
+                                let (tx, rx) = color_bridge(*delivery, QUEUE_CAPACITY);
+                                source.spawn(tx);
+                                row.with_children(|parent| {
+                                    spawn_bar_row(
+                                        parent,
+                                        (
+                                            Bar {
+                                                source_name: source_name.clone(),
+                                            },
+                                            rx,
+                                            ColorHistory::new(history_len.0),
+                                            ColorBridgeStats::default(),
+                                        ),
+                                        &source_name,
+                                        &text_font,
+                                        &small_font,
+                                    );
+                                });
+                            }
+                            BarSource::Level(source, meter) => {
+                                let source_name = source.name();
+                                println!("Starting level source: {}", source_name);
+                                let (tx, rx) = watch::channel(None);
+                                source.spawn(tx);
+                                row.with_children(|parent| {
+                                    spawn_level_row(
+                                        parent,
+                                        (
+                                            Bar {
+                                                source_name: source_name.clone(),
+                                            },
+                                            LevelReceiver { rx },
+                                            meter,
+                                        ),
+                                        &source_name,
+                                        &text_font,
+                                    );
+                                });
+                            }
+                        }
+                    }
+                });
+        });
+}
+
+fn spawn_bar_row(
+    parent: &mut ChildSpawnerCommands,
+    bar_components: (Bar, ColorReceiver, ColorHistory, ColorBridgeStats),
+    source_name: &str,
+    text_font: &(TextFont, TextColor),
+    small_font: &(TextFont, TextColor),
+) {
+    let history_len = bar_components.2.capacity();
+    parent.spawn((
+        Text::new(format!("Colors from {}", source_name)),
+        text_font.clone(),
+        Node {
+            margin: UiRect::bottom(Val::Px(10.)),
+            ..Default::default()
+        },
+    ));
+
+    let mut bar = Entity::PLACEHOLDER;
+    parent
+        .spawn((
+            Node {
+                align_self: AlignSelf::Stretch,
+                padding: UiRect::all(Val::Px(10.)),
+                ..default()
+            },
+            BackgroundColor(YELLOW.into()),
+        ))
+        .with_children(|parent| {
+            bar = parent
+                .spawn((
+                    Node {
+                        align_items: AlignItems::Stretch,
+                        width: Val::Percent(100.),
+                        height: Val::Px(60.),
+                        padding: UiRect::all(Val::Px(4.)),
+                        ..default()
+                    },
+                    BackgroundColor(Color::WHITE),
+                    bar_components,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((Node::default(), BackgroundColor(RED.into())));
+                })
+                .id();
+        });
+
+    parent.spawn((
+        Text::new("Waiting for colors..."),
+        text_font.clone(),
+        Node {
+            margin: UiRect::top(Val::Px(10.)),
+            ..Default::default()
+        },
+        ColorReadout { bar },
+    ));
+
+    parent
+        .spawn(Node {
+            flex_direction: FlexDirection::Row,
+            column_gap: Val::Px(6.),
+            margin: UiRect::top(Val::Px(10.)),
+            ..default()
+        })
+        .with_children(|parent| {
+            for index in 0..history_len {
+                parent
+                    .spawn(Node {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn((
+                            Node {
+                                width: Val::Px(48.),
+                                height: Val::Px(24.),
+                                border: UiRect::all(Val::Px(1.)),
+                                ..default()
+                            },
+                            BackgroundColor(Color::NONE),
+                            BorderColor(Color::srgb(0.3, 0.3, 0.3)),
+                            HistorySwatch { bar, index },
+                        ));
+                        parent.spawn((Text::new(""), small_font.clone(), HistoryTime { bar, index }));
+                    });
+            }
+        });
+
+    parent.spawn((
+        Text::new(""),
+        small_font.clone(),
+        Node {
+            margin: UiRect::top(Val::Px(6.)),
+            ..Default::default()
+        },
+        StatsText { bar },
+    ));
+}
+
+fn spawn_level_row(
+    parent: &mut ChildSpawnerCommands,
+    bar_components: (Bar, LevelReceiver, LevelMeter),
+    source_name: &str,
+    text_font: &(TextFont, TextColor),
+) {
+    let fill_color = bar_components.2.default_color;
+    parent.spawn((
+        Text::new(source_name.to_string()),
+        text_font.clone(),
+        Node {
+            margin: UiRect::bottom(Val::Px(10.)),
+            ..Default::default()
+        },
+    ));
+
+    let mut bar = Entity::PLACEHOLDER;
+    parent
+        .spawn((
+            Node {
+                align_self: AlignSelf::Stretch,
+                padding: UiRect::all(Val::Px(10.)),
+                ..default()
+            },
+            BackgroundColor(YELLOW.into()),
+        ))
+        .with_children(|parent| {
+            bar = parent
+                .spawn((
+                    Node {
+                        align_items: AlignItems::Stretch,
+                        width: Val::Percent(100.),
+                        height: Val::Px(60.),
+                        padding: UiRect::all(Val::Px(4.)),
+                        ..default()
+                    },
+                    BackgroundColor(Color::WHITE),
+                    bar_components,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        Node {
+                            width: Val::Percent(0.),
+                            ..default()
+                        },
+                        BackgroundColor(fill_color),
+                        LevelFill,
+                    ));
+                })
+                .id();
+        });
+
+    parent.spawn((
+        Text::new("Waiting for a value..."),
+        text_font.clone(),
+        Node {
+            margin: UiRect::top(Val::Px(10.)),
+            ..Default::default()
+        },
+        LevelReadout { bar },
+    ));
+}
+
+fn color_change_system(
+    mut commands: Commands,
+    mut bars: Query<
+        (
+            Entity,
+            &BackgroundColor,
+            &mut ColorReceiver,
+            &mut ColorHistory,
+            &mut ColorBridgeStats,
+        ),
+        With<Bar>,
+    >,
+) {
+    for (entity, color, mut receiver, mut history, mut stats) in bars.iter_mut() {
+        let colors = receiver.receive();
+        for received in &colors {
+            history.push(*received);
+        }
+        if let Some(latest) = colors.last() {
+            // Start from whatever is on screen, even mid-fade
+            commands.entity(entity).insert(ColorAnimation {
+                from: color.0,
+                to: latest.color.into(),
+                elapsed: Duration::ZERO,
+            });
+        }
+
+        let current = receiver.stats();
+        if *stats != current {
+            *stats = current;
+        }
+    }
+}
+
+fn animate_bar_color(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut BackgroundColor, &mut ColorAnimation)>,
+    transition: Res<ColorTransition>,
+    time: Res<Time>,
+) {
+    for (entity, mut color, mut animation) in query.iter_mut() {
+        animation.elapsed += time.delta();
+        if animation.elapsed >= transition.duration {
+            color.0 = animation.to;
+            commands.entity(entity).remove::<ColorAnimation>();
+            continue;
+        }
+        let t = animation.elapsed.as_secs_f32() / transition.duration.as_secs_f32();
+        color.0 = transition
+            .space
+            .mix(animation.from, animation.to, transition.easing.apply(t));
+    }
+}
+
+fn update_history_ui(
+    histories: Query<&ColorHistory, Changed<ColorHistory>>,
+    mut swatches: Query<(&HistorySwatch, &mut BackgroundColor), Without<ColorHistory>>,
+    mut times: Query<(&HistoryTime, &mut Text), Without<ColorReadout>>,
+    mut readouts: Query<(&ColorReadout, &mut Text)>,
+) {
+    for (swatch, mut color) in swatches.iter_mut() {
+        if let Ok(history) = histories.get(swatch.bar) {
+            color.0 = history
+                .iter()
+                .nth(swatch.index)
+                .map_or(Color::NONE, |entry| entry.color.into());
+        }
+    }
+    for (time, mut text) in times.iter_mut() {
+        if let Ok(history) = histories.get(time.bar) {
+            text.0 = history
+                .iter()
+                .nth(time.index)
+                .map(|entry| format_time_of_day(entry.received_at))
+                .unwrap_or_default();
+        }
+    }
+    for (readout, mut text) in readouts.iter_mut() {
+        let Some(latest) = histories.get(readout.bar).ok().and_then(|h| h.latest()) else {
+            continue;
+        };
+        let [r, g, b] = latest.color.to_rgb8();
+        text.0 = format!(
+            "{}  rgb({}, {}, {})  at {} UTC",
+            latest.color.to_hex(),
+            r,
+            g,
+            b,
+            format_time_of_day(latest.received_at)
+        );
+    }
+}
+
+fn update_stats_text(
+    stats: Query<&ColorBridgeStats, Changed<ColorBridgeStats>>,
+    mut query: Query<(&StatsText, &mut Text)>,
+) {
+    for (stats_text, mut text) in query.iter_mut() {
+        if let Ok(stats) = stats.get(stats_text.bar) {
+            text.0 = format!(
+                "received {}  shown {}  coalesced {}  dropped {}",
+                stats.received, stats.delivered, stats.coalesced, stats.dropped
+            );
+        }
+    }
+}
+
+fn level_change_system(
+    mut bars: Query<(Entity, &LevelMeter, &mut LevelReceiver, &Children)>,
+    mut fills: Query<(&mut Node, &mut BackgroundColor), With<LevelFill>>,
+    mut readouts: Query<(&LevelReadout, &mut Text)>,
+) {
+    for (entity, meter, mut receiver, children) in bars.iter_mut() {
+        if !receiver.rx.has_changed().unwrap_or(false) {
+            continue;
+        }
+        let Some(value) = *receiver.rx.borrow_and_update() else {
+            continue;
+        };
+        for child in children.iter() {
+            if let Ok((mut node, mut color)) = fills.get_mut(child) {
+                node.width = Val::Percent(meter.fraction(value) * 100.);
+                color.0 = meter.color(value);
+            }
+        }
+        for (readout, mut text) in readouts.iter_mut() {
+            if readout.bar == entity {
+                text.0 = format!("{:.1}  (range {} to {})", value, meter.min, meter.max);
+            }
+        }
+    }
+}
+
+// `HH:MM:SS.mmm`, UTC.
+fn format_time_of_day(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs() % 86_400;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::BarColor;
+
+    const EASINGS: [Easing; 4] = [
+        Easing::Linear,
+        Easing::EaseIn,
+        Easing::EaseOut,
+        Easing::EaseInOut,
+    ];
+
+    fn assert_close(a: Color, b: Color) {
+        let (a, b) = (a.to_srgba(), b.to_srgba());
+        for (x, y) in [(a.red, b.red), (a.green, b.green), (a.blue, b.blue)] {
+            assert!((x - y).abs() < 1e-3, "{a:?} != {b:?}");
+        }
+    }
+
+    fn from_args(args: &str) -> ColorBarPlugin {
+        ColorBarPlugin::from_args(args.split_whitespace().map(String::from), "stdin").unwrap()
+    }
+
+    fn source_names(plugin: &ColorBarPlugin) -> Vec<String> {
+        let sources = plugin.sources.lock().unwrap();
+        sources
+            .iter()
+            .map(|source| match source {
+                BarSource::Color(source) => source.name(),
+                BarSource::Level(source, _) => source.name(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn from_args_starts_a_bar_per_bar_flag() {
+        let plugin = from_args(
+            "--endpoint tcp://a:1 --source zmq --bar --bar --source file --file /tmp/color",
+        );
+        assert_eq!(
+            source_names(&plugin),
+            vec!["zmq tcp://a:1", "stdin", "file /tmp/color"]
+        );
+    }
+
+    #[test]
+    fn from_args_accepts_global_flags_after_a_bar() {
+        let plugin = from_args(
+            "--source zmq --bar --delivery queue --source file --file /tmp/color \
+             --frame-budget 3 --history 4",
+        );
+        assert_eq!(
+            source_names(&plugin),
+            vec!["zmq tcp://localhost:5555", "file /tmp/color"]
+        );
+        assert_eq!(plugin.delivery, DeliveryPolicy::Queue { per_frame: 3 });
+        assert_eq!(plugin.history_len, 4);
+    }
+
+    #[test]
+    fn from_args_mixes_level_and_color_bars() {
+        let plugin = from_args(
+            "--source zmq --bar --level --service org.example --path /p --property Level \
+             --bar --source file --file /tmp/color",
+        );
+        assert_eq!(
+            source_names(&plugin),
+            vec![
+                "zmq tcp://localhost:5555",
+                "dbus org.example org.example.Level",
+                "file /tmp/color",
+            ]
+        );
+    }
+
+    #[test]
+    fn from_args_reports_errors_of_any_bar() {
+        let args = ["--bar", "--source", "carrier-pigeon"].map(String::from);
+        assert!(ColorBarPlugin::from_args(args.into_iter(), "stdin").is_err());
+    }
+
+    fn received(r: f32, secs: u64) -> ReceivedColor {
+        ReceivedColor {
+            color: BarColor { r, g: 0.0, b: 0.0 },
+            received_at: UNIX_EPOCH + Duration::from_secs(secs),
+        }
+    }
+
+    #[test]
+    fn history_keeps_newest_first_up_to_capacity() {
+        let mut history = ColorHistory::new(3);
+        for (i, r) in [0.1, 0.2, 0.3, 0.4].into_iter().enumerate() {
+            history.push(received(r, i as u64));
+        }
+        let kept: Vec<f32> = history.iter().map(|entry| entry.color.r).collect();
+        assert_eq!(kept, vec![0.4, 0.3, 0.2]);
+        assert_eq!(history.latest(), Some(&received(0.4, 3)));
+        assert_eq!(history.capacity(), 3);
+    }
+
+    #[test]
+    fn history_without_capacity_keeps_nothing() {
+        let mut history = ColorHistory::new(0);
+        history.push(received(0.1, 0));
+        assert_eq!(history.latest(), None);
+        assert_eq!(history.iter().count(), 0);
+    }
+
+    #[test]
+    fn easings_keep_endpoints_and_clamp() {
+        for easing in EASINGS {
+            assert_eq!(easing.apply(0.0), 0.0, "{easing:?}");
+            assert_eq!(easing.apply(1.0), 1.0, "{easing:?}");
+            assert_eq!(easing.apply(-0.5), 0.0, "{easing:?}");
+            assert_eq!(easing.apply(1.5), 1.0, "{easing:?}");
+        }
+    }
+
+    #[test]
+    fn easings_are_monotonic() {
+        for easing in EASINGS {
+            let mut previous = easing.apply(0.0);
+            for step in 1..=100 {
+                let value = easing.apply(step as f32 / 100.0);
+                assert!(value >= previous, "{easing:?} decreases at step {step}");
+                previous = value;
+            }
+        }
+    }
+
+    #[test]
+    fn mix_keeps_endpoints() {
+        let (from, to) = (Color::srgb(0.9, 0.1, 0.2), Color::srgb(0.1, 0.4, 0.8));
+        for space in [InterpolationSpace::Srgb, InterpolationSpace::Oklab] {
+            assert_close(space.mix(from, to, 0.0), from);
+            assert_close(space.mix(from, to, 1.0), to);
+        }
+    }
+
+    #[test]
+    fn mix_is_monotonic_in_lightness() {
+        let (from, to) = (Color::BLACK, Color::WHITE);
+        for space in [InterpolationSpace::Srgb, InterpolationSpace::Oklab] {
+            let mut previous = -1.0;
+            for step in 0..=20 {
+                let lightness = Oklaba::from(space.mix(from, to, step as f32 / 20.0)).lightness;
+                assert!(lightness > previous, "{space:?} at step {step}");
+                previous = lightness;
+            }
+        }
+    }
+}
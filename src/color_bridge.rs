@@ -0,0 +1,301 @@
+//! Channel between a [`ColorSource`](crate::color_source::ColorSource) and the
+//! Bevy world, with a configurable delivery policy.
+
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::SystemTime,
+};
+
+use bevy::prelude::*;
+use tokio::sync::{mpsc, watch};
+
+use crate::color::BarColor;
+
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeliveryPolicy {
+    /// Only the most recent color is kept. Colors the bar never saw are
+    /// counted as coalesced.
+    #[default]
+    LatestWins,
+    /// Every queued color is taken each frame.
+    DrainAll,
+    /// At most `per_frame` queued colors are taken each frame.
+    Queue { per_frame: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColorBridgeClosed;
+
+/// A color and the time its source handed it to the bridge.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReceivedColor {
+    pub color: BarColor,
+    pub received_at: SystemTime,
+}
+
+#[derive(Default)]
+struct BridgeCounters {
+    received: AtomicU64,
+    dropped: AtomicU64,
+    coalesced: AtomicU64,
+    // Latest-wins only: a value was sent and not read yet
+    pending: AtomicBool,
+}
+
+/// Snapshot of the bridge counters, refreshed every frame.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ColorBridgeStats {
+    /// Colors handed to the bridge by the source.
+    pub received: u64,
+    /// Colors handed to the bar.
+    pub delivered: u64,
+    /// Colors superseded by a newer one before the bar could show them.
+    pub coalesced: u64,
+    /// Colors discarded because the queue was full.
+    pub dropped: u64,
+}
+
+enum SenderInner {
+    Queue(mpsc::Sender<ReceivedColor>),
+    Latest(watch::Sender<Option<ReceivedColor>>),
+}
+
+/// Producer side. Sending never blocks: with a queue policy a full queue
+/// drops the color and counts it instead.
+#[derive(Clone)]
+pub struct ColorSender {
+    inner: Arc<SenderInner>,
+    counters: Arc<BridgeCounters>,
+}
+
+impl ColorSender {
+    /// Stamps `color` with the current time, so queued colors keep the time
+    /// they arrived rather than the frame they were read in.
+    pub fn send(&self, color: BarColor) -> Result<(), ColorBridgeClosed> {
+        self.counters.received.fetch_add(1, Ordering::Relaxed);
+        let color = ReceivedColor {
+            color,
+            received_at: SystemTime::now(),
+        };
+        match self.inner.as_ref() {
+            SenderInner::Queue(tx) => match tx.try_send(color) {
+                Ok(()) => Ok(()),
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+                    Ok(())
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => Err(ColorBridgeClosed),
+            },
+            SenderInner::Latest(tx) => {
+                if tx.is_closed() {
+                    return Err(ColorBridgeClosed);
+                }
+                // Flagged under the channel's write lock, so the receiver
+                // can't read the value without also seeing the flag
+                tx.send_modify(|value| {
+                    *value = Some(color);
+                    if self.counters.pending.swap(true, Ordering::AcqRel) {
+                        self.counters.coalesced.fetch_add(1, Ordering::Relaxed);
+                    }
+                });
+                Ok(())
+            }
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        match self.inner.as_ref() {
+            SenderInner::Queue(tx) => tx.is_closed(),
+            SenderInner::Latest(tx) => tx.is_closed(),
+        }
+    }
+}
+
+enum ReceiverInner {
+    Queue {
+        rx: mpsc::Receiver<ReceivedColor>,
+        per_frame: Option<usize>,
+    },
+    Latest(watch::Receiver<Option<ReceivedColor>>),
+}
+
+/// Consumer side, polled once per frame.
+#[derive(Component)]
+pub struct ColorReceiver {
+    inner: ReceiverInner,
+    counters: Arc<BridgeCounters>,
+    delivered: u64,
+    coalesced: u64,
+}
+
+impl ColorReceiver {
+    /// Takes the colors available this frame according to the policy, oldest
+    /// first. Only the last one ends up on the bar; the rest count as
+    /// coalesced.
+    pub fn receive(&mut self) -> Vec<ReceivedColor> {
+        let colors = match &mut self.inner {
+            ReceiverInner::Queue { rx, per_frame } => {
+                let mut colors = Vec::new();
+                while per_frame.is_none_or(|max| colors.len() < max) {
+                    match rx.try_recv() {
+                        Ok(color) => colors.push(color),
+                        Err(_) => break,
+                    }
+                }
+                colors
+            }
+            ReceiverInner::Latest(rx) => {
+                if !rx.has_changed().unwrap_or(false) {
+                    return Vec::new();
+                }
+                let value = rx.borrow_and_update();
+                self.counters.pending.swap(false, Ordering::AcqRel);
+                value.iter().copied().collect()
+            }
+        };
+        if !colors.is_empty() {
+            self.delivered += 1;
+            self.coalesced += colors.len() as u64 - 1;
+        }
+        colors
+    }
+
+    pub fn stats(&self) -> ColorBridgeStats {
+        ColorBridgeStats {
+            received: self.counters.received.load(Ordering::Relaxed),
+            delivered: self.delivered,
+            coalesced: self.coalesced + self.counters.coalesced.load(Ordering::Relaxed),
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Creates both ends of a bridge. `capacity` bounds the queue policies.
+pub fn color_bridge(policy: DeliveryPolicy, capacity: usize) -> (ColorSender, ColorReceiver) {
+    let counters = Arc::new(BridgeCounters::default());
+    let (sender, receiver) = match policy {
+        DeliveryPolicy::LatestWins => {
+            let (tx, rx) = watch::channel(None);
+            (SenderInner::Latest(tx), ReceiverInner::Latest(rx))
+        }
+        DeliveryPolicy::DrainAll | DeliveryPolicy::Queue { .. } => {
+            let (tx, rx) = mpsc::channel(capacity);
+            let per_frame = match policy {
+                DeliveryPolicy::Queue { per_frame } => Some(per_frame.max(1)),
+                _ => None,
+            };
+            (SenderInner::Queue(tx), ReceiverInner::Queue { rx, per_frame })
+        }
+    };
+    (
+        ColorSender {
+            inner: Arc::new(sender),
+            counters: counters.clone(),
+        },
+        ColorReceiver {
+            inner: receiver,
+            counters,
+            delivered: 0,
+            coalesced: 0,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUEUE_CAPACITY: usize = 10;
+
+    fn color(r: f32) -> BarColor {
+        BarColor { r, g: 0.0, b: 0.0 }
+    }
+
+    fn receive(rx: &mut ColorReceiver) -> Vec<BarColor> {
+        rx.receive().iter().map(|received| received.color).collect()
+    }
+
+    #[test]
+    fn latest_wins_keeps_the_last_color() {
+        let (tx, mut rx) = color_bridge(DeliveryPolicy::LatestWins, QUEUE_CAPACITY);
+        for r in [0.1, 0.2, 0.3] {
+            tx.send(color(r)).unwrap();
+        }
+        assert_eq!(receive(&mut rx), vec![color(0.3)]);
+        assert!(receive(&mut rx).is_empty());
+        tx.send(color(0.4)).unwrap();
+        assert_eq!(receive(&mut rx), vec![color(0.4)]);
+        assert_eq!(
+            rx.stats(),
+            ColorBridgeStats {
+                received: 4,
+                delivered: 2,
+                coalesced: 2,
+                dropped: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn drain_all_takes_everything_queued() {
+        let (tx, mut rx) = color_bridge(DeliveryPolicy::DrainAll, QUEUE_CAPACITY);
+        for r in [0.1, 0.2, 0.3] {
+            tx.send(color(r)).unwrap();
+        }
+        assert_eq!(receive(&mut rx), vec![color(0.1), color(0.2), color(0.3)]);
+        assert_eq!(
+            rx.stats(),
+            ColorBridgeStats {
+                received: 3,
+                delivered: 1,
+                coalesced: 2,
+                dropped: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn queue_limits_per_frame_and_drops_when_full() {
+        let (tx, mut rx) = color_bridge(DeliveryPolicy::Queue { per_frame: 2 }, 3);
+        for r in [0.1, 0.2, 0.3, 0.4] {
+            tx.send(color(r)).unwrap();
+        }
+        assert_eq!(receive(&mut rx), vec![color(0.1), color(0.2)]);
+        assert_eq!(receive(&mut rx), vec![color(0.3)]);
+        assert!(receive(&mut rx).is_empty());
+        assert_eq!(
+            rx.stats(),
+            ColorBridgeStats {
+                received: 4,
+                delivered: 2,
+                coalesced: 1,
+                dropped: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn colors_keep_the_time_they_were_sent() {
+        let (tx, mut rx) = color_bridge(DeliveryPolicy::DrainAll, QUEUE_CAPACITY);
+        tx.send(color(0.1)).unwrap();
+        let sent_between = SystemTime::now();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        tx.send(color(0.2)).unwrap();
+        let received = rx.receive();
+        assert!(received[0].received_at <= sent_between);
+        assert!(received[1].received_at > sent_between);
+    }
+
+    #[test]
+    fn sending_fails_once_the_receiver_is_gone() {
+        for policy in [DeliveryPolicy::LatestWins, DeliveryPolicy::DrainAll] {
+            let (tx, rx) = color_bridge(policy, QUEUE_CAPACITY);
+            drop(rx);
+            assert!(tx.is_closed());
+            assert_eq!(tx.send(color(0.1)), Err(ColorBridgeClosed));
+        }
+    }
+}
@@ -0,0 +1,381 @@
+//! Transports that can feed colors into the color bar.
+
+use std::{
+    fs,
+    io::{self, BufRead},
+    path::PathBuf,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use bevy::tasks::{IoTaskPool, futures_lite::StreamExt};
+use zbus::{Connection, Message, Proxy, proxy::SignalStream};
+
+use crate::{
+    AddNotificationEvent,
+    color::{BarColor, parse_rgb},
+    color_bridge::ColorSender,
+    zmq_client::{DEFAULT_TOPIC, ZmqClientConfig, ZmqMode, spawn_zmq_color_client},
+};
+
+pub trait ColorSource: Send + 'static {
+    /// Short description used in logs.
+    fn name(&self) -> String;
+
+    /// Starts producing colors into `tx` in the background. Sources stop on
+    /// their own once the receiving side is dropped.
+    fn spawn(self: Box<Self>, tx: ColorSender);
+}
+
+/// A D-Bus signal carrying a color, either as an `a{sv}` dict with a
+/// `color` entry (like `org.mechanix.services.Add`) or as a plain string.
+#[derive(Debug, Clone)]
+pub struct DbusColorSource {
+    pub service: String,
+    pub path: String,
+    pub interface: String,
+    pub signal: String,
+}
+
+impl Default for DbusColorSource {
+    fn default() -> Self {
+        Self {
+            service: "org.mechanix.services.Add".to_string(),
+            path: "/org/mechanix/services/Add".to_string(),
+            interface: "org.mechanix.services.Add".to_string(),
+            signal: "Notification".to_string(),
+        }
+    }
+}
+
+impl ColorSource for DbusColorSource {
+    fn name(&self) -> String {
+        format!(
+            "dbus {} {} {}.{}",
+            self.service, self.path, self.interface, self.signal
+        )
+    }
+
+    fn spawn(self: Box<Self>, tx: ColorSender) {
+        IoTaskPool::get()
+            .spawn(async move {
+                let mut stream = match subscribe(&self).await {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        println!("Error subscribing to {}: {}", self.name(), e);
+                        return;
+                    }
+                };
+                println!("Signal stream started: {}", self.name());
+                while let Some(msg) = stream.next().await {
+                    match color_from_message(&msg) {
+                        Ok(color) => {
+                            if tx.send(color).is_err() {
+                                return;
+                            }
+                        }
+                        Err(e) => println!("Ignoring {}: {}", self.signal, e),
+                    }
+                }
+            })
+            .detach();
+    }
+}
+
+async fn subscribe(source: &DbusColorSource) -> zbus::Result<SignalStream<'static>> {
+    let connection = Connection::session().await?;
+    let proxy = Proxy::new(
+        &connection,
+        source.service.clone(),
+        source.path.clone(),
+        source.interface.clone(),
+    )
+    .await?;
+    proxy.receive_signal(source.signal.clone()).await
+}
+
+fn color_from_message(msg: &Message) -> Result<BarColor, String> {
+    let body = msg.body();
+    if let Ok(event) = body.deserialize::<AddNotificationEvent>() {
+        return parse_rgb(&event.color);
+    }
+    let color_str: String = body.deserialize().map_err(|e| e.to_string())?;
+    parse_rgb(&color_str)
+}
+
+/// A ZeroMQ color server, see [`crate::zmq_server`].
+pub struct ZmqColorSource(pub ZmqClientConfig);
+
+impl ColorSource for ZmqColorSource {
+    fn name(&self) -> String {
+        format!("zmq {}", self.0.endpoint)
+    }
+
+    fn spawn(self: Box<Self>, tx: ColorSender) {
+        spawn_zmq_color_client(self.0, tx);
+    }
+}
+
+/// One `RGB(r, g, b)` per line on standard input.
+pub struct StdinColorSource;
+
+impl ColorSource for StdinColorSource {
+    fn name(&self) -> String {
+        "stdin".to_string()
+    }
+
+    fn spawn(self: Box<Self>, tx: ColorSender) {
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(e) => {
+                        println!("Error reading stdin: {}", e);
+                        return;
+                    }
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match parse_rgb(&line) {
+                    Ok(color) => {
+                        if tx.send(color).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => println!("Ignoring {:?}: {}", line, e),
+                }
+            }
+        });
+    }
+}
+
+/// Watches a file and sends its last non-empty line whenever it is modified.
+pub struct FileColorSource {
+    pub path: PathBuf,
+    pub poll_interval: Duration,
+}
+
+impl FileColorSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            poll_interval: Duration::from_millis(250),
+        }
+    }
+}
+
+impl ColorSource for FileColorSource {
+    fn name(&self) -> String {
+        format!("file {}", self.path.display())
+    }
+
+    fn spawn(self: Box<Self>, tx: ColorSender) {
+        thread::spawn(move || {
+            let mut last_modified: Option<SystemTime> = None;
+            while !tx.is_closed() {
+                let modified = fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+                if modified.is_some() && modified != last_modified {
+                    last_modified = modified;
+                    match fs::read_to_string(&self.path) {
+                        Ok(contents) => {
+                            let line = contents.lines().rev().find(|l| !l.trim().is_empty());
+                            if let Some(line) = line {
+                                match parse_rgb(line) {
+                                    Ok(color) => {
+                                        if tx.send(color).is_err() {
+                                            return;
+                                        }
+                                    }
+                                    Err(e) => println!("Ignoring {}: {}", self.path.display(), e),
+                                }
+                            }
+                        }
+                        Err(e) => println!("Error reading {}: {}", self.path.display(), e),
+                    }
+                }
+                thread::sleep(self.poll_interval);
+            }
+        });
+    }
+}
+
+pub const SOURCE_USAGE: &str = "[--source dbus|zmq|stdin|file] [--file <path>] \
+[--service <name>] [--path <path>] [--interface <name>] [--signal <member>] \
+[--endpoint <addr>] [--mode req|sub] [--topic <topic>] [--timeout-ms <ms>] [--interval-ms <ms>]";
+
+/// Builds a source from command line arguments, see [`SOURCE_USAGE`].
+/// `default_source` is used when `--source` is not given.
+pub fn parse_source_args(
+    mut args: impl Iterator<Item = String>,
+    default_source: &str,
+) -> Result<Box<dyn ColorSource>, String> {
+    let mut source = default_source.to_string();
+    let mut file = None;
+    let mut dbus = DbusColorSource::default();
+    let mut path = None;
+    let mut interface = None;
+    let mut zmq = ZmqClientConfig::default();
+    let mut mode = "req".to_string();
+    let mut topic = DEFAULT_TOPIC.to_string();
+    let mut timeout = Duration::from_secs(2);
+    let mut interval = Duration::from_secs(1);
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--source" => source = value()?,
+            "--file" => file = Some(PathBuf::from(value()?)),
+            "--service" => dbus.service = value()?,
+            "--path" => path = Some(value()?),
+            "--interface" => interface = Some(value()?),
+            "--signal" => dbus.signal = value()?,
+            "--endpoint" => zmq.endpoint = value()?,
+            "--mode" => mode = value()?,
+            "--topic" => topic = value()?,
+            "--timeout-ms" => timeout = parse_millis(&value()?)?,
+            "--interval-ms" => interval = parse_millis(&value()?)?,
+            other => return Err(format!("unknown argument {}", other)),
+        }
+    }
+
+    match source.as_str() {
+        "dbus" => {
+            // Most services use their bus name as the interface name and
+            // the bus name with dots turned into slashes as the object path
+            dbus.path = path.unwrap_or_else(|| format!("/{}", dbus.service.replace('.', "/")));
+            dbus.interface = interface.unwrap_or_else(|| dbus.service.clone());
+            Ok(Box::new(dbus))
+        }
+        "zmq" => {
+            zmq.mode = match mode.as_str() {
+                "req" => ZmqMode::Req { timeout, interval },
+                "sub" => ZmqMode::Sub {
+                    topic,
+                    idle_timeout: timeout,
+                },
+                other => return Err(format!("unknown mode {}", other)),
+            };
+            Ok(Box::new(ZmqColorSource(zmq)))
+        }
+        "stdin" => Ok(Box::new(StdinColorSource)),
+        "file" => {
+            let path = file.ok_or("--source file needs --file <path>")?;
+            Ok(Box::new(FileColorSource::new(path)))
+        }
+        other => Err(format!("unknown source {}", other)),
+    }
+}
+
+fn parse_millis(s: &str) -> Result<Duration, String> {
+    s.parse::<u64>()
+        .map(Duration::from_millis)
+        .map_err(|e| format!("invalid milliseconds {:?}: {}", s, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color_bridge::{DeliveryPolicy, color_bridge};
+
+    fn parse(args: &[&str], default_source: &str) -> Result<String, String> {
+        parse_source_args(args.iter().map(|arg| arg.to_string()), default_source)
+            .map(|source| source.name())
+    }
+
+    #[test]
+    fn parse_source_args_uses_default_unless_overridden() {
+        assert_eq!(
+            parse(&[], "dbus"),
+            Ok("dbus org.mechanix.services.Add /org/mechanix/services/Add \
+                org.mechanix.services.Add.Notification"
+                .to_string())
+        );
+        assert_eq!(parse(&["--source", "stdin"], "dbus"), Ok("stdin".to_string()));
+        assert_eq!(
+            parse(&["--file", "/tmp/color", "--source", "file"], "dbus"),
+            Ok("file /tmp/color".to_string())
+        );
+        assert_eq!(
+            parse(&["--endpoint", "tcp://host:1234", "--mode", "sub"], "zmq"),
+            Ok("zmq tcp://host:1234".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_source_args_derives_dbus_path_and_interface_from_service() {
+        assert_eq!(
+            parse(&["--service", "org.example.Colors"], "dbus"),
+            Ok("dbus org.example.Colors /org/example/Colors org.example.Colors.Notification".to_string())
+        );
+        assert_eq!(
+            parse(
+                &[
+                    "--service",
+                    "org.example.Colors",
+                    "--path",
+                    "/colors",
+                    "--interface",
+                    "org.example.Palette",
+                    "--signal",
+                    "Changed",
+                ],
+                "dbus"
+            ),
+            Ok("dbus org.example.Colors /colors org.example.Palette.Changed".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_source_args_rejects_invalid_arguments() {
+        assert_eq!(
+            parse(&["--bogus"], "dbus"),
+            Err("unknown argument --bogus".to_string())
+        );
+        assert_eq!(
+            parse(&["--endpoint"], "zmq"),
+            Err("missing value for --endpoint".to_string())
+        );
+        assert_eq!(
+            parse(&["--mode", "push"], "zmq"),
+            Err("unknown mode push".to_string())
+        );
+        assert_eq!(parse(&[], "carrier-pigeon"), Err("unknown source carrier-pigeon".to_string()));
+        assert!(parse(&["--source", "file"], "dbus").is_err());
+        assert!(parse(&["--timeout-ms", "soon"], "zmq").is_err());
+    }
+
+    #[test]
+    fn file_source_sends_last_line_on_change() {
+        let path = std::env::temp_dir().join(format!("color-source-{}", std::process::id()));
+        fs::write(&path, "RGB(1, 1, 1)\nRGB(0.1, 0.2, 0.3)\n\n").unwrap();
+
+        let (tx, mut rx) = color_bridge(DeliveryPolicy::DrainAll, 8);
+        Box::new(FileColorSource {
+            path: path.clone(),
+            poll_interval: Duration::from_millis(10),
+        })
+        .spawn(tx);
+        let mut next_color = || {
+            for _ in 0..200 {
+                if let Some(received) = rx.receive().last() {
+                    return Some(received.color);
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+            None
+        };
+        assert_eq!(next_color(), parse_rgb("RGB(0.1, 0.2, 0.3)").ok());
+
+        fs::write(&path, "RGB(0.4, 0.5, 0.6)").unwrap();
+        // Don't rely on the file system's timestamp resolution
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now() + Duration::from_secs(1)))
+            .unwrap();
+        assert_eq!(next_color(), parse_rgb("RGB(0.4, 0.5, 0.6)").ok());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
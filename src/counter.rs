@@ -0,0 +1,62 @@
+//! Counter rules shared by the counter binaries, independent of Bevy.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CounterOp {
+    Increment,
+    Decrement,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Counter {
+    value: i32,
+}
+
+impl Counter {
+    pub fn new(value: i32) -> Self {
+        Self { value }
+    }
+
+    pub fn value(&self) -> i32 {
+        self.value
+    }
+
+    /// Applies `op` and returns the new value. Saturates at the `i32` bounds
+    /// instead of overflowing.
+    pub fn apply(&mut self, op: CounterOp) -> i32 {
+        self.value = match op {
+            CounterOp::Increment => self.value.saturating_add(1),
+            CounterOp::Decrement => self.value.saturating_sub(1),
+        };
+        self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn increment_then_decrement_is_identity() {
+        for start in [-3, 0, 10, 12345] {
+            let mut counter = Counter::new(start);
+            counter.apply(CounterOp::Increment);
+            counter.apply(CounterOp::Decrement);
+            assert_eq!(counter.value(), start);
+        }
+    }
+
+    #[test]
+    fn apply_returns_new_value() {
+        let mut counter = Counter::default();
+        assert_eq!(counter.apply(CounterOp::Decrement), -1);
+        assert_eq!(counter.apply(CounterOp::Increment), 0);
+    }
+
+    #[test]
+    fn saturates_at_bounds() {
+        let mut counter = Counter::new(i32::MAX);
+        assert_eq!(counter.apply(CounterOp::Increment), i32::MAX);
+        let mut counter = Counter::new(i32::MIN);
+        assert_eq!(counter.apply(CounterOp::Decrement), i32::MIN);
+    }
+}
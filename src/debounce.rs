@@ -0,0 +1,95 @@
+//! Coalescing bursts of D-Bus signals into a single re-read, for the plugins
+//! that re-read their whole state on any change.
+
+use std::time::{Duration, Instant};
+
+use bevy::tasks::futures_lite::{Stream, StreamExt, future};
+
+/// Waits for the next item of `changes`, then for `quiet` without any, and
+/// returns everything that arrived. A stream that never goes quiet is cut off
+/// `max_wait` after its first item, so the state still gets re-read now and
+/// then. Returns `None` once `changes` ended.
+pub(crate) async fn next_burst<S: Stream + Unpin>(
+    changes: &mut S,
+    quiet: Duration,
+    max_wait: Duration,
+) -> Option<Vec<S::Item>> {
+    let mut burst = vec![changes.next().await?];
+    let deadline = Instant::now() + max_wait;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Some(burst);
+        }
+        let next = async { Some(changes.next().await) };
+        let timeout = async {
+            async_std::task::sleep(quiet.min(remaining)).await;
+            None
+        };
+        match future::or(next, timeout).await {
+            Some(Some(item)) => burst.push(item),
+            Some(None) => return None,
+            None => return Some(burst),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use async_std::{channel, task::block_on};
+
+    use super::*;
+
+    #[test]
+    fn collects_a_burst_until_quiet() {
+        let (tx, mut rx) = channel::unbounded();
+        for i in 0..3 {
+            tx.try_send(i).unwrap();
+        }
+        let burst = block_on(next_burst(
+            &mut rx,
+            Duration::from_millis(20),
+            Duration::from_secs(5),
+        ));
+        assert_eq!(burst, Some(vec![0, 1, 2]));
+    }
+
+    #[test]
+    fn steady_stream_is_cut_off_after_max_wait() {
+        let (tx, mut rx) = channel::unbounded();
+        let sender = thread::spawn(move || {
+            // Faster than `quiet`, for longer than `max_wait`
+            for i in 0..100 {
+                if tx.try_send(i).is_err() {
+                    return;
+                }
+                thread::sleep(Duration::from_millis(5));
+            }
+        });
+        let started = Instant::now();
+        let burst = block_on(next_burst(
+            &mut rx,
+            Duration::from_millis(50),
+            Duration::from_millis(100),
+        ))
+        .unwrap();
+        assert!(started.elapsed() < Duration::from_millis(400));
+        assert!(burst.len() < 100);
+        drop(rx);
+        sender.join().unwrap();
+    }
+
+    #[test]
+    fn ended_stream_returns_none() {
+        let (tx, mut rx) = channel::unbounded::<u32>();
+        drop(tx);
+        let burst = block_on(next_burst(
+            &mut rx,
+            Duration::from_millis(20),
+            Duration::from_secs(5),
+        ));
+        assert_eq!(burst, None);
+    }
+}
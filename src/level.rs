@@ -0,0 +1,355 @@
+//! Numeric D-Bus values (battery percentage, volume, ...) shown as level meters
+//! by the color bar plugin.
+
+use bevy::{
+    prelude::*,
+    tasks::{IoTaskPool, futures_lite::StreamExt},
+};
+use tokio::sync::watch;
+use zbus::{
+    Proxy,
+    fdo::PropertiesProxy,
+    names::InterfaceName,
+    zvariant::{Structure, Value},
+};
+
+pub use crate::bus::Bus;
+
+pub const LEVEL_USAGE: &str = "--level [--bus session|system] --service <name> --path <path> \
+[--interface <name>] (--property <name> | --signal <member>) [--min <n>] [--max <n>] \
+[--threshold <below>:<#rrggbb>]...";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LevelMember {
+    /// Read on start, then follow `PropertiesChanged`.
+    Property(String),
+    /// First numeric argument of every emission, whatever the arguments
+    /// before it.
+    Signal(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct DbusLevelSource {
+    pub bus: Bus,
+    pub service: String,
+    pub path: String,
+    pub interface: String,
+    pub member: LevelMember,
+}
+
+impl DbusLevelSource {
+    /// Battery percentage of the UPower display device.
+    pub fn upower_battery() -> Self {
+        Self {
+            bus: Bus::System,
+            service: "org.freedesktop.UPower".to_string(),
+            path: "/org/freedesktop/UPower/devices/DisplayDevice".to_string(),
+            interface: "org.freedesktop.UPower.Device".to_string(),
+            member: LevelMember::Property("Percentage".to_string()),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        let member = match &self.member {
+            LevelMember::Property(name) | LevelMember::Signal(name) => name,
+        };
+        format!("dbus {} {}.{}", self.service, self.interface, member)
+    }
+
+    /// Follows the value in the background. Stops once `tx` has no receivers.
+    pub fn spawn(self, tx: watch::Sender<Option<f64>>) {
+        IoTaskPool::get()
+            .spawn(async move {
+                if let Err(e) = self.run(&tx).await {
+                    println!("Error following {}: {}", self.name(), e);
+                }
+            })
+            .detach();
+    }
+
+    async fn run(&self, tx: &watch::Sender<Option<f64>>) -> zbus::Result<()> {
+        let connection = self.bus.connect().await?;
+        match &self.member {
+            LevelMember::Property(property) => {
+                let interface = InterfaceName::try_from(self.interface.as_str())?;
+                let proxy = PropertiesProxy::builder(&connection)
+                    .destination(self.service.as_str())?
+                    .path(self.path.as_str())?
+                    .build()
+                    .await?;
+                let mut changes = proxy.receive_properties_changed().await?;
+
+                let value = proxy.get(interface.clone(), property).await?;
+                if tx.send(value_to_f64(&value)).is_err() {
+                    return Ok(());
+                }
+                while let Some(signal) = changes.next().await {
+                    let args = signal.args()?;
+                    if args.interface_name != interface {
+                        continue;
+                    }
+                    let changed = args.changed_properties.get(property.as_str());
+                    let value = if let Some(value) = changed {
+                        value_to_f64(value)
+                    } else if args.invalidated_properties.contains(&property.as_str()) {
+                        let value = proxy.get(interface.clone(), property).await?;
+                        value_to_f64(&value)
+                    } else {
+                        continue;
+                    };
+                    if tx.send(value).is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+            LevelMember::Signal(signal) => {
+                let proxy = Proxy::new(
+                    &connection,
+                    self.service.as_str(),
+                    self.path.as_str(),
+                    self.interface.as_str(),
+                )
+                .await?;
+                let mut stream = proxy.receive_signal(signal.as_str()).await?;
+                while let Some(msg) = stream.next().await {
+                    let body = msg.body();
+                    let value = body
+                        .deserialize::<Structure<'_>>()
+                        .ok()
+                        .and_then(|args| first_number(args.fields()));
+                    if value.is_none() {
+                        println!("Ignoring {}: no numeric argument", signal);
+                        continue;
+                    }
+                    if tx.send(value).is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The first of `fields` that holds a number, such as the `u` of a signal
+/// with `(s, u)` arguments.
+fn first_number(fields: &[Value<'_>]) -> Option<f64> {
+    fields.iter().find_map(value_to_f64)
+}
+
+fn value_to_f64(value: &Value<'_>) -> Option<f64> {
+    match value {
+        Value::U8(v) => Some(f64::from(*v)),
+        Value::Bool(v) => Some(if *v { 1.0 } else { 0.0 }),
+        Value::I16(v) => Some(f64::from(*v)),
+        Value::U16(v) => Some(f64::from(*v)),
+        Value::I32(v) => Some(f64::from(*v)),
+        Value::U32(v) => Some(f64::from(*v)),
+        Value::I64(v) => Some(*v as f64),
+        Value::U64(v) => Some(*v as f64),
+        Value::F64(v) => Some(*v),
+        Value::Value(v) => value_to_f64(v),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Threshold {
+    /// Applies to values strictly below this one.
+    pub below: f64,
+    pub color: Color,
+}
+
+/// Maps a value onto the bar width and picks the fill color.
+#[derive(Component, Debug, Clone)]
+pub struct LevelMeter {
+    pub min: f64,
+    pub max: f64,
+    /// Sorted by `below`, the first match wins.
+    thresholds: Vec<Threshold>,
+    pub default_color: Color,
+}
+
+impl Default for LevelMeter {
+    fn default() -> Self {
+        Self {
+            min: 0.0,
+            max: 100.0,
+            thresholds: Vec::new(),
+            default_color: Color::srgb(0.2, 0.8, 0.3),
+        }
+    }
+}
+
+impl LevelMeter {
+    pub fn with_threshold(mut self, below: f64, color: Color) -> Self {
+        self.thresholds.push(Threshold { below, color });
+        self.thresholds.sort_by(|a, b| a.below.total_cmp(&b.below));
+        self
+    }
+
+    /// Position of `value` between `min` and `max`, clamped to `0.0..=1.0`.
+    pub fn fraction(&self, value: f64) -> f32 {
+        let range = self.max - self.min;
+        if range <= 0.0 {
+            return 0.0;
+        }
+        ((value - self.min) / range).clamp(0.0, 1.0) as f32
+    }
+
+    pub fn color(&self, value: f64) -> Color {
+        self.thresholds
+            .iter()
+            .find(|threshold| value < threshold.below)
+            .map_or(self.default_color, |threshold| threshold.color)
+    }
+}
+
+/// Latest value of a level bar's source, `None` until one arrived.
+#[derive(Component)]
+pub struct LevelReceiver {
+    pub rx: watch::Receiver<Option<f64>>,
+}
+
+/// Parses the flags in [`LEVEL_USAGE`] (`--level` itself is ignored).
+pub fn parse_level_args(
+    mut args: impl Iterator<Item = String>,
+) -> Result<(DbusLevelSource, LevelMeter), String> {
+    let mut bus = Bus::Session;
+    let mut service = None;
+    let mut path = None;
+    let mut interface = None;
+    let mut member = None;
+    let mut meter = LevelMeter::default();
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--level" => {}
+            "--bus" => bus = value()?.parse()?,
+            "--service" => service = Some(value()?),
+            "--path" => path = Some(value()?),
+            "--interface" => interface = Some(value()?),
+            "--property" => member = Some(LevelMember::Property(value()?)),
+            "--signal" => member = Some(LevelMember::Signal(value()?)),
+            "--min" => meter.min = parse_number(&value()?)?,
+            "--max" => meter.max = parse_number(&value()?)?,
+            "--threshold" => {
+                let value = value()?;
+                let (below, color) = value.split_once(':').ok_or(format!(
+                    "invalid threshold {:?}, expected <below>:<#rrggbb>",
+                    value
+                ))?;
+                let color = Srgba::hex(color)
+                    .map_err(|e| format!("invalid color {:?}: {}", color, e))?;
+                meter = meter.with_threshold(parse_number(below)?, color.into());
+            }
+            other => return Err(format!("unknown argument {}", other)),
+        }
+    }
+
+    let service = service.ok_or("--level needs --service <name>")?;
+    let source = DbusLevelSource {
+        bus,
+        interface: interface.unwrap_or_else(|| service.clone()),
+        path: path.ok_or("--level needs --path <path>")?,
+        member: member.ok_or("--level needs --property <name> or --signal <member>")?,
+        service,
+    };
+    Ok((source, meter))
+}
+
+fn parse_number(s: &str) -> Result<f64, String> {
+    s.parse().map_err(|e| format!("invalid number {:?}: {}", s, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> impl Iterator<Item = String> {
+        args.split_whitespace()
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn first_number_skips_leading_non_numbers() {
+        let fields = [Value::from("sink"), Value::U16(42), Value::F64(1.5)];
+        assert_eq!(first_number(&fields), Some(42.0));
+        assert_eq!(first_number(&[Value::I64(-3)]), Some(-3.0));
+        assert_eq!(first_number(&[Value::from("no number")]), None);
+    }
+
+    #[test]
+    fn parses_property_source_with_thresholds() {
+        let (source, meter) = parse_level_args(args(
+            "--level --bus system --service org.example --path /org/example \
+             --property Level --min 10 --max 20 --threshold 15:#ff0000",
+        ))
+        .unwrap();
+        assert_eq!(source.bus, Bus::System);
+        assert_eq!(source.service, "org.example");
+        assert_eq!(source.interface, "org.example");
+        assert_eq!(source.path, "/org/example");
+        assert_eq!(source.member, LevelMember::Property("Level".to_string()));
+        assert_eq!((meter.min, meter.max), (10.0, 20.0));
+        assert_eq!(meter.color(12.0), Color::from(Srgba::hex("#ff0000").unwrap()));
+        assert_eq!(meter.color(18.0), meter.default_color);
+    }
+
+    #[test]
+    fn parses_signal_source_on_custom_interface() {
+        let (source, _) = parse_level_args(args(
+            "--service org.example --path /p --interface org.example.Meter --signal Changed",
+        ))
+        .unwrap();
+        assert_eq!(source.bus, Bus::Session);
+        assert_eq!(source.interface, "org.example.Meter");
+        assert_eq!(source.member, LevelMember::Signal("Changed".to_string()));
+    }
+
+    #[test]
+    fn rejects_incomplete_or_invalid_arguments() {
+        assert!(parse_level_args(args("--path /p --property P")).is_err());
+        assert!(parse_level_args(args("--service s --property P")).is_err());
+        assert!(parse_level_args(args("--service s --path /p")).is_err());
+        assert!(parse_level_args(args("--service s --path /p --property P --bus usb")).is_err());
+        assert!(parse_level_args(args("--service s --path /p --property P --min x")).is_err());
+        assert!(parse_level_args(args("--service s --path /p --property P --threshold 5")).is_err());
+        assert!(parse_level_args(args("--service s --path /p --property P --max")).is_err());
+    }
+
+    #[test]
+    fn fraction_is_clamped_to_the_range() {
+        let meter = LevelMeter {
+            min: 10.0,
+            max: 20.0,
+            ..LevelMeter::default()
+        };
+        assert_eq!(meter.fraction(15.0), 0.5);
+        assert_eq!(meter.fraction(0.0), 0.0);
+        assert_eq!(meter.fraction(30.0), 1.0);
+        let empty = LevelMeter {
+            min: 5.0,
+            max: 5.0,
+            ..LevelMeter::default()
+        };
+        assert_eq!(empty.fraction(5.0), 0.0);
+    }
+
+    #[test]
+    fn color_picks_the_lowest_matching_threshold() {
+        let red = Color::srgb(1.0, 0.0, 0.0);
+        let yellow = Color::srgb(1.0, 1.0, 0.0);
+        // Added out of order on purpose
+        let meter = LevelMeter::default()
+            .with_threshold(50.0, yellow)
+            .with_threshold(20.0, red);
+        assert_eq!(meter.color(10.0), red);
+        assert_eq!(meter.color(20.0), yellow);
+        assert_eq!(meter.color(49.9), yellow);
+        assert_eq!(meter.color(50.0), meter.default_color);
+    }
+}
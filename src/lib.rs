@@ -1,14 +1,30 @@
 use zbus::zvariant::{DeserializeDict, SerializeDict, Type};
 pub mod add_proxy;
+pub mod audio;
+pub mod bluetooth;
+pub mod brightness;
+pub mod bus;
+pub mod color;
+pub mod color_bar;
+pub mod color_bridge;
+pub mod color_source;
+pub mod counter;
+mod debounce;
+pub mod level;
+pub mod logind;
+pub mod network_manager;
+pub mod notifications;
+pub mod power;
+mod result_channel;
+#[cfg(test)]
+mod testing;
+pub mod tictactoe;
+pub mod tictactoe_service;
+pub mod zmq_client;
+pub mod zmq_server;
 #[derive(DeserializeDict, SerializeDict, Type, Debug)]
 // `Type` treats `BluetoothNotificationEvent` is an alias for `a{sv}`.
 #[zvariant(signature = "a{sv}")]
 pub struct AddNotificationEvent {
     pub color: String,
 }
-
-pub mod bluetooth {
-    use crate::add_proxy;
-    pub use add_proxy::{AddService, NotificationStream};
-}
-
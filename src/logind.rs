@@ -0,0 +1,624 @@
+pub mod proxies;
+pub mod ui;
+
+use std::sync::mpsc::Sender;
+
+use bevy::{
+    prelude::*,
+    tasks::{
+        AsyncComputeTaskPool, IoTaskPool,
+        futures_lite::{StreamExt, future},
+    },
+};
+use proxies::{ManagerProxy, SessionProxy};
+use zbus::{Connection, zvariant::OwnedFd};
+
+use crate::bus::{SystemBus, SystemBusPlugin};
+use crate::result_channel::ResultChannel;
+
+/// Answer of logind's `CanReboot`, `CanPowerOff` and `CanSuspend`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Capability {
+    Yes,
+    /// Allowed once the user authenticated through polkit.
+    Challenge,
+    No,
+    /// Not supported by the machine, or not known yet.
+    #[default]
+    NotApplicable,
+}
+
+impl From<&str> for Capability {
+    fn from(value: &str) -> Self {
+        match value {
+            "yes" => Capability::Yes,
+            "challenge" => Capability::Challenge,
+            "no" => Capability::No,
+            _ => Capability::NotApplicable,
+        }
+    }
+}
+
+impl Capability {
+    pub fn allowed(self) -> bool {
+        matches!(self, Capability::Yes | Capability::Challenge)
+    }
+}
+
+/// What the user may do, read from logind once connected.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoginCapabilities {
+    pub reboot: Capability,
+    pub power_off: Capability,
+    pub suspend: Capability,
+}
+
+impl LoginCapabilities {
+    pub fn get(&self, action: LoginAction) -> Capability {
+        match action {
+            LoginAction::Reboot => self.reboot,
+            LoginAction::PowerOff => self.power_off,
+            LoginAction::Suspend => self.suspend,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginAction {
+    Reboot,
+    PowerOff,
+    Suspend,
+}
+
+#[derive(Event)]
+pub struct LoginActionEvent(pub LoginAction);
+
+/// Sent when a `LoginAction` failed or was not allowed, or with no action when
+/// an inhibitor lock could not be taken.
+#[derive(Event, Debug, Clone)]
+pub struct LoginErrorEvent {
+    pub action: Option<LoginAction>,
+    pub reason: String,
+}
+
+/// Sent with true before the machine suspends or hibernates, and with false
+/// after it resumed.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PrepareForSleep(pub bool);
+
+/// logind asking the app's session to lock or unlock its screen, such as
+/// after `loginctl lock-session`.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionLockEvent {
+    Lock,
+    Unlock,
+}
+
+/// Inhibitor locks the plugin holds while a window of the app has focus, and
+/// releases when none has, apart from the sleep delay lock. Change it to take
+/// or release locks.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct InhibitWhileActive {
+    /// Keeps the screen from blanking and the session from going idle.
+    pub idle: bool,
+    /// Keeps the machine from suspending, including on user request, but not
+    /// through `LoginAction::Suspend`.
+    pub sleep: bool,
+    /// Holds off suspending, focused or not, until the app had a frame to
+    /// handle `PrepareForSleep(true)`, and at most logind's
+    /// `InhibitDelayMaxSec`. Taken again on resume.
+    pub delay_sleep: bool,
+    /// Both shown by `systemd-inhibit --list`.
+    pub who: String,
+    pub why: String,
+}
+
+impl Default for InhibitWhileActive {
+    fn default() -> Self {
+        Self {
+            idle: true,
+            sleep: false,
+            delay_sleep: false,
+            who: env!("CARGO_PKG_NAME").to_string(),
+            why: "Application in use".to_string(),
+        }
+    }
+}
+
+impl InhibitWhileActive {
+    /// The `what` argument of logind's `Inhibit` for the block lock, None
+    /// without any. Sleep is left out while the app itself suspends.
+    fn what(&self, suspending: bool) -> Option<String> {
+        let what: Vec<&str> = [(self.idle, "idle"), (self.sleep && !suspending, "sleep")]
+            .into_iter()
+            .filter_map(|(on, what)| on.then_some(what))
+            .collect();
+        (!what.is_empty()).then(|| what.join(":"))
+    }
+}
+
+fn inhibits_sleep(what: &str) -> bool {
+    what.split(':').any(|what| what == "sleep")
+}
+
+/// The `mode` argument of logind's `Inhibit`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InhibitMode {
+    /// Holds off what it inhibits until released.
+    Block,
+    /// Lets the app get ready first, `PrepareForSleep` being sent meanwhile.
+    Delay,
+}
+
+impl InhibitMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            InhibitMode::Block => "block",
+            InhibitMode::Delay => "delay",
+        }
+    }
+}
+
+/// The lock of one mode, released when dropped.
+#[derive(Default)]
+struct Inhibitor {
+    held: Option<(String, OwnedFd)>,
+    /// Asked for and not answered yet.
+    pending: Option<String>,
+    /// Refused, not asked for again until the locks wanted change.
+    failed: Option<String>,
+}
+
+impl Inhibitor {
+    fn held(&self) -> Option<&str> {
+        self.held.as_ref().map(|(what, _)| what.as_str())
+    }
+
+    /// True when there is nothing to do for `wanted`: it is held, asked for
+    /// or was refused.
+    fn settled(&self, wanted: Option<&str>) -> bool {
+        self.held() == wanted
+            || (wanted.is_some()
+                && (self.pending.as_deref() == wanted || self.failed.as_deref() == wanted))
+    }
+
+    /// Releases the lock without `wanted`, else returns it as pending, to be
+    /// asked for. Only for locks not `settled`.
+    fn want(&mut self, wanted: Option<String>) -> Option<String> {
+        let Some(what) = wanted else {
+            self.held = None;
+            self.failed = None;
+            return None;
+        };
+        self.pending = Some(what.clone());
+        Some(what)
+    }
+}
+
+/// The inhibitor locks currently held.
+#[derive(Resource, Default)]
+pub struct InhibitorLock {
+    block: Inhibitor,
+    delay: Inhibitor,
+    /// A `LoginAction::Suspend` was asked for and the machine has not resumed
+    /// yet, so the block lock leaves sleep out.
+    suspending: bool,
+    /// Between `PrepareForSleep(true)` and `PrepareForSleep(false)`, without
+    /// the delay lock.
+    sleeping: bool,
+}
+
+impl InhibitorLock {
+    /// What the block lock inhibits, such as `idle:sleep`.
+    pub fn held(&self) -> Option<&str> {
+        self.block.held()
+    }
+
+    /// Whether the delay lock on sleep is held.
+    pub fn delaying_sleep(&self) -> bool {
+        self.delay.held.is_some()
+    }
+
+    fn inhibitor(&self, mode: InhibitMode) -> &Inhibitor {
+        match mode {
+            InhibitMode::Block => &self.block,
+            InhibitMode::Delay => &self.delay,
+        }
+    }
+
+    fn inhibitor_mut(&mut self, mode: InhibitMode) -> &mut Inhibitor {
+        match mode {
+            InhibitMode::Block => &mut self.block,
+            InhibitMode::Delay => &mut self.delay,
+        }
+    }
+}
+
+/// What the tasks talking to logind send back.
+enum LoginResult {
+    Capabilities(LoginCapabilities),
+    Inhibited {
+        mode: InhibitMode,
+        what: String,
+        fd: OwnedFd,
+    },
+    InhibitFailed {
+        mode: InhibitMode,
+        what: String,
+        reason: String,
+    },
+    ActionFailed {
+        action: LoginAction,
+        reason: String,
+    },
+    PrepareForSleep(bool),
+    SessionLock(SessionLockEvent),
+}
+
+/// Wraps `org.freedesktop.login1`: runs `LoginActionEvent`s, holds the locks
+/// of `InhibitWhileActive` and reports sleep and session lock signals.
+pub struct LoginPlugin;
+
+impl Plugin for LoginPlugin {
+    fn build(&self, app: &mut App) {
+        SystemBusPlugin::add_once(app);
+        app.init_resource::<LoginCapabilities>()
+            .init_resource::<InhibitWhileActive>()
+            .init_resource::<InhibitorLock>()
+            .init_resource::<ResultChannel<LoginResult>>()
+            .add_event::<LoginActionEvent>()
+            .add_event::<LoginErrorEvent>()
+            .add_event::<PrepareForSleep>()
+            .add_event::<SessionLockEvent>()
+            .add_systems(
+                Update,
+                (
+                    start_following,
+                    update_inhibitor_lock,
+                    handle_login_action_events,
+                    poll_login_results
+                        .after(update_inhibitor_lock)
+                        .after(handle_login_action_events),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    ui::login_button_system,
+                    ui::update_login_buttons.after(poll_login_results),
+                ),
+            );
+    }
+}
+
+/// Reads the capabilities and starts following logind's signals once the
+/// shared system bus is connected.
+fn start_following(
+    bus: Res<SystemBus>,
+    results: Res<ResultChannel<LoginResult>>,
+    mut started: Local<bool>,
+) {
+    if *started {
+        return;
+    }
+    let Some(connection) = bus.connection.clone() else {
+        return;
+    };
+    *started = true;
+    let sender = results.sender();
+    IoTaskPool::get()
+        .spawn(async move {
+            match read_capabilities(&connection).await {
+                Ok(capabilities) => {
+                    let _ = sender.send(LoginResult::Capabilities(capabilities));
+                }
+                Err(e) => error!("Failed to read logind capabilities: {e}"),
+            }
+            if let Err(e) = follow_login_signals(&connection, &sender).await {
+                error!("Failed to follow logind signals: {e}");
+            }
+        })
+        .detach();
+}
+
+pub async fn read_capabilities(connection: &Connection) -> zbus::Result<LoginCapabilities> {
+    let manager = ManagerProxy::new(connection).await?;
+    Ok(LoginCapabilities {
+        reboot: Capability::from(manager.can_reboot().await?.as_str()),
+        power_off: Capability::from(manager.can_power_off().await?.as_str()),
+        suspend: Capability::from(manager.can_suspend().await?.as_str()),
+    })
+}
+
+async fn own_session(
+    connection: &Connection,
+    manager: &ManagerProxy<'_>,
+) -> zbus::Result<SessionProxy<'static>> {
+    let id = SessionProxy::new(connection).await?.id().await?;
+    SessionProxy::builder(connection)
+        .path(manager.get_session(&id).await?)?
+        .build()
+        .await
+}
+
+/// Forwards `PrepareForSleep`, and `Lock` and `Unlock` of the app's session
+/// when it runs in one, until the plugin is gone.
+async fn follow_login_signals(
+    connection: &Connection,
+    sender: &Sender<LoginResult>,
+) -> zbus::Result<()> {
+    let manager = ManagerProxy::new(connection).await?;
+    let mut sleep = manager.receive_prepare_for_sleep().await?;
+    let (mut lock, mut unlock) = match own_session(connection, &manager).await {
+        Ok(session) => (
+            Some(session.receive_lock().await?),
+            Some(session.receive_unlock().await?),
+        ),
+        Err(e) => {
+            warn!("Not following session locks, no login session: {e}");
+            (None, None)
+        }
+    };
+    loop {
+        let next_sleep = async {
+            let signal = sleep.next().await?;
+            Some(
+                signal
+                    .args()
+                    .map(|args| LoginResult::PrepareForSleep(*args.start())),
+            )
+        };
+        let next_lock = async {
+            match &mut lock {
+                Some(lock) => lock.next().await?,
+                None => future::pending().await,
+            };
+            Some(Ok(LoginResult::SessionLock(SessionLockEvent::Lock)))
+        };
+        let next_unlock = async {
+            match &mut unlock {
+                Some(unlock) => unlock.next().await?,
+                None => future::pending().await,
+            };
+            Some(Ok(LoginResult::SessionLock(SessionLockEvent::Unlock)))
+        };
+        match future::or(next_sleep, future::or(next_lock, next_unlock)).await {
+            Some(Ok(result)) => {
+                if sender.send(result).is_err() {
+                    return Ok(());
+                }
+            }
+            Some(Err(e)) => debug!("Invalid logind signal: {e}"),
+            None => return Ok(()),
+        }
+    }
+}
+
+/// Takes the block locks of `InhibitWhileActive` when a window gains focus
+/// and releases them when none has it any more, and holds the delay lock
+/// except while asleep.
+fn update_inhibitor_lock(
+    windows: Query<&Window>,
+    policy: Res<InhibitWhileActive>,
+    mut lock: ResMut<InhibitorLock>,
+    bus: Res<SystemBus>,
+    results: Res<ResultChannel<LoginResult>>,
+) {
+    let Some(connection) = &bus.connection else {
+        return;
+    };
+    let active = windows.iter().any(|window| window.focused);
+    let block = if active {
+        policy.what(lock.suspending)
+    } else {
+        None
+    };
+    // Released the frame after PrepareForSleep(true) was sent
+    let delay = (policy.delay_sleep && !lock.sleeping).then(|| "sleep".to_string());
+    for (mode, wanted) in [(InhibitMode::Block, block), (InhibitMode::Delay, delay)] {
+        // Checked first to leave the resource unchanged when there is nothing to do
+        if lock.inhibitor(mode).settled(wanted.as_deref()) {
+            continue;
+        }
+        let Some(what) = lock.inhibitor_mut(mode).want(wanted) else {
+            continue;
+        };
+        let connection = connection.clone();
+        let who = policy.who.clone();
+        let why = policy.why.clone();
+        let sender = results.sender();
+        AsyncComputeTaskPool::get()
+            .spawn(async move {
+                let fd = match ManagerProxy::new(&connection).await {
+                    Ok(manager) => manager.inhibit(&what, &who, &why, mode.as_str()).await,
+                    Err(e) => Err(e),
+                };
+                let _ = sender.send(match fd {
+                    Ok(fd) => LoginResult::Inhibited { mode, what, fd },
+                    Err(e) => LoginResult::InhibitFailed {
+                        mode,
+                        what,
+                        reason: e.to_string(),
+                    },
+                });
+            })
+            .detach();
+    }
+}
+
+fn handle_login_action_events(
+    mut events: EventReader<LoginActionEvent>,
+    bus: Res<SystemBus>,
+    capabilities: Res<LoginCapabilities>,
+    mut lock: ResMut<InhibitorLock>,
+    results: Res<ResultChannel<LoginResult>>,
+    mut error_writer: EventWriter<LoginErrorEvent>,
+) {
+    let pool = AsyncComputeTaskPool::get();
+    for LoginActionEvent(action) in events.read() {
+        let action = *action;
+        let fail = |reason: &str| LoginErrorEvent {
+            action: Some(action),
+            reason: reason.to_string(),
+        };
+        let Some(connection) = bus.connection.clone() else {
+            error_writer.write(fail("the system bus is not available"));
+            continue;
+        };
+        if !capabilities.get(action).allowed() {
+            error_writer.write(fail("not allowed"));
+            continue;
+        }
+        if action == LoginAction::Suspend {
+            // Our own block lock on sleep would hold the request up, so it is
+            // released before asking
+            lock.suspending = true;
+            if lock.block.held().is_some_and(inhibits_sleep) {
+                lock.block.held = None;
+            }
+        }
+        let sender = results.sender();
+        pool.spawn(async move {
+            if let Err(err) = run_action(&connection, action).await {
+                error!("Login action {action:?} failed: {err}");
+                let _ = sender.send(LoginResult::ActionFailed {
+                    action,
+                    reason: err.to_string(),
+                });
+            }
+        })
+        .detach();
+    }
+}
+
+async fn run_action(connection: &Connection, action: LoginAction) -> zbus::Result<()> {
+    let manager = ManagerProxy::new(connection).await?;
+    match action {
+        LoginAction::Reboot => manager.reboot(true).await,
+        LoginAction::PowerOff => manager.power_off(true).await,
+        LoginAction::Suspend => manager.suspend(true).await,
+    }
+}
+
+fn poll_login_results(
+    results: Res<ResultChannel<LoginResult>>,
+    mut capabilities: ResMut<LoginCapabilities>,
+    mut lock: ResMut<InhibitorLock>,
+    mut sleep_writer: EventWriter<PrepareForSleep>,
+    mut session_writer: EventWriter<SessionLockEvent>,
+    mut error_writer: EventWriter<LoginErrorEvent>,
+) {
+    for result in results.drain() {
+        match result {
+            LoginResult::Capabilities(read) => *capabilities = read,
+            LoginResult::Inhibited { mode, what, fd } => {
+                let suspending = lock.suspending;
+                let inhibitor = lock.inhibitor_mut(mode);
+                if inhibitor.pending.as_ref() == Some(&what) {
+                    inhibitor.pending = None;
+                }
+                // Asked for before a Suspend, dropping it releases it
+                if mode == InhibitMode::Block && suspending && inhibits_sleep(&what) {
+                    continue;
+                }
+                // Replacing the previous lock releases it. One no longer
+                // wanted is released by the next update_inhibitor_lock.
+                inhibitor.held = Some((what, fd));
+            }
+            LoginResult::InhibitFailed { mode, what, reason } => {
+                let inhibitor = lock.inhibitor_mut(mode);
+                if inhibitor.pending.as_ref() == Some(&what) {
+                    inhibitor.pending = None;
+                }
+                inhibitor.failed = Some(what);
+                error_writer.write(LoginErrorEvent {
+                    action: None,
+                    reason,
+                });
+            }
+            LoginResult::ActionFailed { action, reason } => {
+                if action == LoginAction::Suspend {
+                    lock.suspending = false;
+                }
+                error_writer.write(LoginErrorEvent {
+                    action: Some(action),
+                    reason,
+                });
+            }
+            LoginResult::PrepareForSleep(start) => {
+                lock.sleeping = start;
+                if !start {
+                    lock.suspending = false;
+                }
+                sleep_writer.write(PrepareForSleep(start));
+            }
+            LoginResult::SessionLock(event) => {
+                session_writer.write(event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capability_from_logind_answers() {
+        assert_eq!(Capability::from("yes"), Capability::Yes);
+        assert_eq!(Capability::from("challenge"), Capability::Challenge);
+        assert_eq!(Capability::from("no"), Capability::No);
+        assert_eq!(Capability::from("na"), Capability::NotApplicable);
+        assert_eq!(Capability::from(""), Capability::NotApplicable);
+        assert!(Capability::Yes.allowed());
+        assert!(Capability::Challenge.allowed());
+        assert!(!Capability::No.allowed());
+        assert!(!Capability::NotApplicable.allowed());
+    }
+
+    fn policy(idle: bool, sleep: bool) -> InhibitWhileActive {
+        InhibitWhileActive {
+            idle,
+            sleep,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn what_joins_the_wanted_locks() {
+        assert_eq!(policy(true, false).what(false).as_deref(), Some("idle"));
+        assert_eq!(policy(false, true).what(false).as_deref(), Some("sleep"));
+        assert_eq!(
+            policy(true, true).what(false).as_deref(),
+            Some("idle:sleep")
+        );
+        assert_eq!(policy(false, false).what(false), None);
+    }
+
+    #[test]
+    fn what_leaves_sleep_out_while_suspending() {
+        assert_eq!(policy(true, true).what(true).as_deref(), Some("idle"));
+        assert_eq!(policy(false, true).what(true), None);
+        assert!(inhibits_sleep("idle:sleep"));
+        assert!(!inhibits_sleep("idle"));
+    }
+
+    #[test]
+    fn inhibitor_asks_once_for_each_lock() {
+        let mut inhibitor = Inhibitor::default();
+        assert!(inhibitor.settled(None));
+        assert!(!inhibitor.settled(Some("idle")));
+        assert_eq!(
+            inhibitor.want(Some("idle".to_string())).as_deref(),
+            Some("idle")
+        );
+        assert!(inhibitor.settled(Some("idle")));
+        assert!(!inhibitor.settled(Some("idle:sleep")));
+
+        inhibitor.pending = None;
+        inhibitor.failed = Some("idle".to_string());
+        assert!(inhibitor.settled(Some("idle")));
+        assert_eq!(inhibitor.want(None), None);
+        assert_eq!(inhibitor.failed, None);
+    }
+}
@@ -0,0 +1,62 @@
+//! Subset of the `org.freedesktop.login1` D-Bus API used by the plugin.
+
+use zbus::{
+    Result as ZbusResult, proxy,
+    zvariant::{OwnedFd, OwnedObjectPath},
+};
+
+#[proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+pub trait Manager {
+    /// `interactive` lets polkit ask the user for authentication.
+    fn reboot(&self, interactive: bool) -> ZbusResult<()>;
+
+    fn power_off(&self, interactive: bool) -> ZbusResult<()>;
+
+    fn suspend(&self, interactive: bool) -> ZbusResult<()>;
+
+    /// `yes`, `no`, `challenge` (allowed after authentication) or `na`.
+    fn can_reboot(&self) -> ZbusResult<String>;
+
+    fn can_power_off(&self) -> ZbusResult<String>;
+
+    fn can_suspend(&self) -> ZbusResult<String>;
+
+    /// `what` is a colon-separated list such as `idle:sleep`, `mode` is
+    /// `block` or `delay`. The lock is held until the returned fd is closed.
+    fn inhibit(&self, what: &str, who: &str, why: &str, mode: &str) -> ZbusResult<OwnedFd>;
+
+    fn get_session(&self, session_id: &str) -> ZbusResult<OwnedObjectPath>;
+
+    /// Sent with `start` true before suspending or hibernating, and false
+    /// after resuming.
+    #[zbus(signal)]
+    fn prepare_for_sleep(&self, start: bool) -> ZbusResult<()>;
+}
+
+/// The default path is the caller's own session. Signals are sent from the
+/// session's real path instead, found with `ManagerProxy::get_session`.
+#[proxy(
+    interface = "org.freedesktop.login1.Session",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1/session/auto"
+)]
+pub trait Session {
+    /// Sets a device of `subsystem`, `backlight` or `leds`, as named under
+    /// `/sys/class/<subsystem>`, without needing write access to sysfs.
+    fn set_brightness(&self, subsystem: &str, name: &str, brightness: u32) -> ZbusResult<()>;
+
+    #[zbus(property)]
+    fn id(&self) -> ZbusResult<String>;
+
+    /// Asks the session's screen locker to lock, as `loginctl lock-session`
+    /// does.
+    #[zbus(signal)]
+    fn lock(&self) -> ZbusResult<()>;
+
+    #[zbus(signal)]
+    fn unlock(&self) -> ZbusResult<()>;
+}
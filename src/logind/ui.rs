@@ -0,0 +1,89 @@
+//! Ready-made reboot, power off and suspend buttons for the logind plugin.
+
+use bevy::prelude::*;
+
+use super::{LoginAction, LoginActionEvent, LoginCapabilities};
+
+const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
+const HOVERED_BUTTON: Color = Color::srgb(0.25, 0.25, 0.25);
+const TEXT: Color = Color::srgb(0.9, 0.9, 0.9);
+const DIM_TEXT: Color = Color::srgb(0.45, 0.45, 0.45);
+
+/// What pressing a button of the power buttons does. Buttons for actions
+/// logind does not allow are dimmed and do nothing.
+#[derive(Component, Clone, Copy)]
+pub struct LoginButton(pub LoginAction);
+
+/// Spawns a row of suspend, reboot and power off buttons.
+pub fn spawn_login_buttons(commands: &mut Commands, font: Handle<Font>, node: Node) -> Entity {
+    let text_font = TextFont {
+        font,
+        font_size: 20.0,
+        ..default()
+    };
+    commands
+        .spawn(Node {
+            column_gap: Val::Px(8.0),
+            ..node
+        })
+        .with_children(|parent| {
+            for (label, action) in [
+                ("Suspend", LoginAction::Suspend),
+                ("Reboot", LoginAction::Reboot),
+                ("Power off", LoginAction::PowerOff),
+            ] {
+                parent
+                    .spawn((
+                        Button,
+                        Node {
+                            padding: UiRect::axes(Val::Px(14.0), Val::Px(8.0)),
+                            ..default()
+                        },
+                        BorderRadius::all(Val::Px(4.0)),
+                        BackgroundColor(NORMAL_BUTTON),
+                        LoginButton(action),
+                    ))
+                    .with_child((Text::new(label), text_font.clone(), TextColor(DIM_TEXT)));
+            }
+        })
+        .id()
+}
+
+pub(crate) fn login_button_system(
+    mut query: Query<(&Interaction, &LoginButton, &mut BackgroundColor), Changed<Interaction>>,
+    capabilities: Res<LoginCapabilities>,
+    mut writer: EventWriter<LoginActionEvent>,
+) {
+    for (interaction, button, mut color) in query.iter_mut() {
+        let allowed = capabilities.get(button.0).allowed();
+        color.0 = match interaction {
+            Interaction::Hovered | Interaction::Pressed if allowed => HOVERED_BUTTON,
+            _ => NORMAL_BUTTON,
+        };
+        if *interaction == Interaction::Pressed && allowed {
+            writer.write(LoginActionEvent(button.0));
+        }
+    }
+}
+
+pub(crate) fn update_login_buttons(
+    capabilities: Res<LoginCapabilities>,
+    buttons: Query<(Ref<LoginButton>, &Children)>,
+    mut texts: Query<&mut TextColor>,
+) {
+    for (button, children) in buttons.iter() {
+        if !capabilities.is_changed() && !button.is_added() {
+            continue;
+        }
+        let color = if capabilities.get(button.0).allowed() {
+            TEXT
+        } else {
+            DIM_TEXT
+        };
+        for child in children.iter() {
+            if let Ok(mut text_color) = texts.get_mut(child) {
+                text_color.0 = color;
+            }
+        }
+    }
+}
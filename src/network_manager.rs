@@ -0,0 +1,563 @@
+pub mod access_points;
+pub mod airplane;
+pub mod connections;
+pub mod mock;
+pub mod proxies;
+pub mod state;
+pub mod ui;
+
+use access_points::{
+    AccessPoint, ConnectionProgress, connect_to_network, connect_with_password, scan_access_points,
+};
+use airplane::{
+    AirplaneMode, AirplaneModeChanged, Radios, disable_airplane_mode, enable_airplane_mode,
+};
+use bevy::prelude::*;
+use connections::{
+    ConnectionsReceiver, ConnectionsState, VpnState, VpnStateChanged, WiredState,
+    WiredStateChanged, WwanState, WwanStateChanged, activate_vpn, deactivate_vpn,
+    follow_connections, set_wwan_enabled, sync_connections,
+};
+use bevy::tasks::{AsyncComputeTaskPool, IoTaskPool, Task, block_on, futures_lite::future};
+use proxies::NetworkManagerProxy;
+use state::{NetworkState, NetworkStateReceiver, follow_network_state, sync_network_state};
+use tokio::sync::watch;
+use zbus::Connection;
+
+use crate::bus::{Bus, SystemBus, SystemBusPlugin};
+use crate::result_channel::ResultChannel;
+
+/// Set to `session` to talk to the `nm_mock` binary instead of the real
+/// NetworkManager on the system bus.
+pub const BUS_ENV: &str = "NM_BUS";
+
+/// Bus connection all NetworkManager calls go through.
+#[derive(Resource, Default)]
+pub struct NetworkManagerBus {
+    pub connection: Option<Connection>,
+}
+
+#[derive(Resource)]
+struct BusInitTask(Task<Option<Connection>>);
+
+/// Outcome of a `NetworkAction`, sent back from the task running it.
+enum NetworkResult {
+    WifiToggled(bool),
+    ToggleFailed(String),
+    Scanned(Vec<AccessPoint>),
+    ScanFailed(String),
+    Progress {
+        ssid: String,
+        progress: ConnectionProgress,
+    },
+    VpnToggled {
+        name: String,
+        active: bool,
+    },
+    VpnFailed {
+        name: String,
+        reason: String,
+    },
+    WwanToggled(bool),
+    WwanFailed(String),
+    AirplaneModeSet {
+        enabled: bool,
+        /// Radios to restore later, only when turning airplane mode on.
+        saved: Option<Radios>,
+        radios: Radios,
+    },
+    AirplaneModeFailed {
+        reason: String,
+        /// Radios as they were before a switch that failed part way.
+        saved: Option<Radios>,
+    },
+}
+
+/// Networks found by the last scan, strongest first.
+#[derive(Resource, Default, Debug, Clone)]
+pub struct AccessPointList(pub Vec<AccessPoint>);
+
+#[derive(Debug, Clone)]
+pub enum NetworkAction {
+    ToggleWifi(bool),      // true = enable, false = disable
+    SwitchNetwork(String), // SSID
+    ConnectWithPassword { ssid: String, password: String },
+    ScanAccessPoints,
+    ActivateVpn(String),   // profile name
+    DeactivateVpn(String), // profile name
+    ToggleWwan(bool),
+    AirplaneMode(bool), // true = all radios off, false = restore them
+}
+#[derive(Event)]
+pub struct NetworkActionEvent(pub NetworkAction);
+#[derive(Debug, Clone)]
+pub enum ErrorType {
+    ToggleWifiError(String),
+    ScanError(String),
+    SwitchNetworkError { ssid: String, reason: String },
+    VpnError { name: String, reason: String },
+    WwanError(String),
+    AirplaneModeError(String),
+}
+/// Sent for every failed action, whichever radio or connection it was about.
+#[derive(Event)]
+pub struct NetworkErrorEvent(pub ErrorType);
+
+#[deprecated(note = "renamed to `NetworkErrorEvent`, it covers more than Wi-Fi")]
+pub type WifiErrorEvent = NetworkErrorEvent;
+
+/// Sent once a `ToggleWifi` action went through, with the requested state.
+#[derive(Event, Debug, Clone)]
+pub struct WifiToggled(pub bool);
+
+/// Sent once a VPN action went through, `active` tells which one it was.
+#[derive(Event, Debug, Clone)]
+pub struct VpnToggled {
+    pub name: String,
+    pub active: bool,
+}
+
+/// Sent once a `ToggleWwan` action went through, with the requested state.
+#[derive(Event, Debug, Clone)]
+pub struct WwanToggled(pub bool);
+
+/// Sent after every completed scan.
+#[derive(Event, Debug, Clone)]
+pub struct AccessPointsUpdated(pub Vec<AccessPoint>);
+
+/// Progress of a `SwitchNetwork` action.
+#[derive(Event, Debug, Clone)]
+pub struct ConnectionProgressEvent {
+    pub ssid: String,
+    pub progress: ConnectionProgress,
+}
+
+/// Marks a text that shows the current `NetworkState`.
+#[derive(Clone, Copy, Component)]
+pub struct WifiStatusText;
+
+/// This plugin connects to NetworkManager asynchronously on startup, keeps
+/// [`NetworkState`] up to date and runs the [`NetworkAction`]s it is sent.
+///
+/// Actions sent before the connection is up fail with a [`NetworkErrorEvent`].
+pub struct NetworkManagerServicePlugin;
+
+impl Plugin for NetworkManagerServicePlugin {
+    fn build(&self, app: &mut App) {
+        SystemBusPlugin::add_once(app);
+        app.init_resource::<NetworkState>()
+            .init_resource::<NetworkManagerBus>()
+            .init_resource::<ResultChannel<NetworkResult>>()
+            .init_resource::<AccessPointList>()
+            .init_resource::<WiredState>()
+            .init_resource::<VpnState>()
+            .init_resource::<WwanState>()
+            .init_resource::<AirplaneMode>()
+            .init_resource::<ui::PasswordDialog>()
+            .add_event::<NetworkActionEvent>()
+            .add_event::<NetworkErrorEvent>()
+            .add_event::<WifiToggled>()
+            .add_event::<AccessPointsUpdated>()
+            .add_event::<ConnectionProgressEvent>()
+            .add_event::<VpnToggled>()
+            .add_event::<WwanToggled>()
+            .add_event::<AirplaneModeChanged>()
+            .add_event::<WiredStateChanged>()
+            .add_event::<VpnStateChanged>()
+            .add_event::<WwanStateChanged>()
+            .add_systems(Startup, init_bus_connection) // Spawns the init task
+            .add_systems(Update, poll_bus_init) // Once the task is done, it moves the connection into the resource
+            .add_systems(
+                Update,
+                (
+                    handle_network_action_events,
+                    poll_network_results.after(handle_network_action_events),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    sync_network_state.after(poll_bus_init),
+                    ui::update_network_status_text.after(sync_network_state),
+                    ui::update_network_status_icon.after(sync_network_state),
+                    sync_connections.after(poll_bus_init),
+                    airplane::sync_airplane_mode
+                        .after(sync_network_state)
+                        .after(sync_connections)
+                        .after(poll_network_results),
+                    ui::update_connections_panel.after(sync_connections),
+                    ui::connections_button_system,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    ui::access_point_button_system,
+                    ui::update_access_point_list.after(poll_network_results),
+                    ui::update_connection_status_text.after(poll_network_results),
+                    ui::open_password_dialog.after(poll_network_results),
+                    ui::password_dialog_input,
+                    ui::password_dialog_buttons,
+                    ui::render_password_dialog
+                        .after(ui::open_password_dialog)
+                        .after(ui::password_dialog_input)
+                        .after(ui::password_dialog_buttons),
+                ),
+            );
+    }
+}
+
+/// A connection already in [`NetworkManagerBus`] is kept, which lets tests
+/// hand in a peer-to-peer one.
+fn init_bus_connection(mut commands: Commands, bus: Res<NetworkManagerBus>) {
+    let preset = bus.connection.clone();
+    let task = IoTaskPool::get().spawn(async move {
+        if preset.is_some() {
+            return preset;
+        }
+        let bus = match std::env::var(BUS_ENV) {
+            Ok(value) => value.parse::<Bus>().unwrap_or_else(|e| {
+                error!("Invalid {BUS_ENV}: {e}, using the system bus");
+                Bus::System
+            }),
+            Err(_) => Bus::System,
+        };
+        match bus.connect().await {
+            Ok(connection) => Some(connection),
+            Err(e) => {
+                error!("Failed to connect to the bus: {e}");
+                None
+            }
+        }
+    });
+    commands.insert_resource(BusInitTask(task));
+}
+
+fn poll_bus_init(
+    mut commands: Commands,
+    task: Option<ResMut<BusInitTask>>,
+    mut bus: ResMut<NetworkManagerBus>,
+) {
+    let Some(mut task) = task else {
+        return;
+    };
+    if let Some(connection) = block_on(future::poll_once(&mut task.0)) {
+        if let Some(connection) = &connection {
+            let (tx, rx) = watch::channel(NetworkState::default());
+            let follower = connection.clone();
+            IoTaskPool::get()
+                .spawn(async move {
+                    if let Err(e) = follow_network_state(&follower, &tx).await {
+                        error!("Failed to follow the network state: {e}");
+                    }
+                })
+                .detach();
+            commands.insert_resource(NetworkStateReceiver(rx));
+
+            let (tx, rx) = watch::channel(ConnectionsState::default());
+            let follower = connection.clone();
+            IoTaskPool::get()
+                .spawn(async move {
+                    if let Err(e) = follow_connections(&follower, &tx).await {
+                        error!("Failed to follow wired, VPN and WWAN connections: {e}");
+                    }
+                })
+                .detach();
+            commands.insert_resource(ConnectionsReceiver(rx));
+        }
+        bus.connection = connection;
+        commands.remove_resource::<BusInitTask>();
+    }
+}
+
+fn handle_network_action_events(
+    mut events: EventReader<NetworkActionEvent>,
+    bus: Res<NetworkManagerBus>,
+    results: Res<ResultChannel<NetworkResult>>,
+    access_points: Res<AccessPointList>,
+    mut airplane_mode: ResMut<AirplaneMode>,
+    system_bus: Res<SystemBus>,
+    mut error_writer: EventWriter<NetworkErrorEvent>,
+) {
+    let pool = AsyncComputeTaskPool::get();
+    for event in events.read() {
+        let NetworkActionEvent(action) = event;
+        match action {
+            NetworkAction::ToggleWifi(enable) => {
+                let Some(connection) = bus.connection.clone() else {
+                    error_writer.write(NetworkErrorEvent(ErrorType::ToggleWifiError(
+                        "NetworkManager is not available".to_string(),
+                    )));
+                    continue;
+                };
+                let enable = *enable;
+                let sender = results.sender();
+                pool.spawn(async move {
+                    let toggle = async {
+                        NetworkManagerProxy::new(&connection)
+                            .await?
+                            .set_wireless_enabled(enable)
+                            .await
+                    };
+                    let result = match toggle.await {
+                        Ok(_) => NetworkResult::WifiToggled(enable),
+                        Err(err) => {
+                            error!("failed to toggle wifi: {err}");
+                            NetworkResult::ToggleFailed(err.to_string())
+                        }
+                    };
+                    // EventWriter can't be used from the task, poll_network_results forwards it
+                    let _ = sender.send(result);
+                })
+                .detach();
+            }
+            NetworkAction::SwitchNetwork(ssid)
+            | NetworkAction::ConnectWithPassword { ssid, .. } => {
+                let Some(connection) = bus.connection.clone() else {
+                    error_writer.write(NetworkErrorEvent(ErrorType::SwitchNetworkError {
+                        ssid: ssid.clone(),
+                        reason: "NetworkManager is not available".to_string(),
+                    }));
+                    continue;
+                };
+                let ssid = ssid.clone();
+                let known = access_points.0.iter().find(|ap| ap.ssid == ssid).cloned();
+                let password = match action {
+                    NetworkAction::ConnectWithPassword { password, .. } => Some(password.clone()),
+                    _ => None,
+                };
+                let sender = results.sender();
+                pool.spawn(async move {
+                    let progress_sender = sender.clone();
+                    let progress_ssid = ssid.clone();
+                    let report = move |progress| {
+                        let _ = progress_sender.send(NetworkResult::Progress {
+                            ssid: progress_ssid.clone(),
+                            progress,
+                        });
+                    };
+                    let result = match password {
+                        Some(password) => {
+                            connect_with_password(&connection, &ssid, &password, known, report)
+                                .await
+                        }
+                        None => connect_to_network(&connection, &ssid, known, report).await,
+                    };
+                    if let Err(err) = result {
+                        error!("failed to connect to {ssid}: {err}");
+                        let _ = sender.send(NetworkResult::Progress {
+                            ssid,
+                            progress: ConnectionProgress::Failed(err.to_string()),
+                        });
+                    }
+                })
+                .detach();
+            }
+            NetworkAction::ScanAccessPoints => {
+                let Some(connection) = bus.connection.clone() else {
+                    error_writer.write(NetworkErrorEvent(ErrorType::ScanError(
+                        "NetworkManager is not available".to_string(),
+                    )));
+                    continue;
+                };
+                let sender = results.sender();
+                pool.spawn(async move {
+                    let result = match scan_access_points(&connection).await {
+                        Ok(access_points) => NetworkResult::Scanned(access_points),
+                        Err(err) => {
+                            error!("failed to scan access points: {err}");
+                            NetworkResult::ScanFailed(err.to_string())
+                        }
+                    };
+                    let _ = sender.send(result);
+                })
+                .detach();
+            }
+            NetworkAction::ActivateVpn(name) | NetworkAction::DeactivateVpn(name) => {
+                let active = matches!(action, NetworkAction::ActivateVpn(_));
+                let Some(connection) = bus.connection.clone() else {
+                    error_writer.write(NetworkErrorEvent(ErrorType::VpnError {
+                        name: name.clone(),
+                        reason: "NetworkManager is not available".to_string(),
+                    }));
+                    continue;
+                };
+                let name = name.clone();
+                let sender = results.sender();
+                pool.spawn(async move {
+                    let result = if active {
+                        activate_vpn(&connection, &name).await
+                    } else {
+                        deactivate_vpn(&connection, &name).await
+                    };
+                    let result = match result {
+                        Ok(()) => NetworkResult::VpnToggled { name, active },
+                        Err(err) => {
+                            error!("failed to switch VPN {name}: {err}");
+                            NetworkResult::VpnFailed {
+                                name,
+                                reason: err.to_string(),
+                            }
+                        }
+                    };
+                    let _ = sender.send(result);
+                })
+                .detach();
+            }
+            NetworkAction::ToggleWwan(enable) => {
+                let Some(connection) = bus.connection.clone() else {
+                    error_writer.write(NetworkErrorEvent(ErrorType::WwanError(
+                        "NetworkManager is not available".to_string(),
+                    )));
+                    continue;
+                };
+                let enable = *enable;
+                let sender = results.sender();
+                pool.spawn(async move {
+                    let result = match set_wwan_enabled(&connection, enable).await {
+                        Ok(()) => NetworkResult::WwanToggled(enable),
+                        Err(err) => {
+                            error!("failed to toggle mobile broadband: {err}");
+                            NetworkResult::WwanFailed(err.to_string())
+                        }
+                    };
+                    let _ = sender.send(result);
+                })
+                .detach();
+            }
+            NetworkAction::AirplaneMode(enable) => {
+                let Some(connection) = bus.connection.clone() else {
+                    error_writer.write(NetworkErrorEvent(ErrorType::AirplaneModeError(
+                        "NetworkManager is not available".to_string(),
+                    )));
+                    continue;
+                };
+                let enable = *enable;
+                if !airplane_mode.request(enable) {
+                    debug!("airplane mode is already on its way to {enable}");
+                    continue;
+                }
+                // Up to date, as no other switch is running
+                let saved = airplane_mode.saved.clone();
+                // BlueZ is on the system bus, whichever bus NetworkManager is on
+                let system = system_bus.connection.clone();
+                let sender = results.sender();
+                pool.spawn(async move {
+                    let system = system.as_ref();
+                    let result = if enable {
+                        match enable_airplane_mode(&connection, system).await {
+                            Ok((before, Ok(radios))) => Ok((Some(before), radios)),
+                            Ok((before, Err(err))) => Err((Some(before), err)),
+                            Err(err) => Err((None, err)),
+                        }
+                    } else {
+                        disable_airplane_mode(&connection, system, saved)
+                            .await
+                            .map(|radios| (None, radios))
+                            .map_err(|err| (None, err))
+                    };
+                    let result = match result {
+                        Ok((saved, radios)) => NetworkResult::AirplaneModeSet {
+                            enabled: enable,
+                            saved,
+                            radios,
+                        },
+                        Err((saved, err)) => {
+                            error!("failed to switch airplane mode: {err}");
+                            NetworkResult::AirplaneModeFailed {
+                                reason: err.to_string(),
+                                saved,
+                            }
+                        }
+                    };
+                    let _ = sender.send(result);
+                })
+                .detach();
+            }
+        }
+    }
+}
+
+/// Turns the outcomes sent by action tasks into events.
+#[allow(clippy::too_many_arguments)]
+fn poll_network_results(
+    results: Res<ResultChannel<NetworkResult>>,
+    mut access_points: ResMut<AccessPointList>,
+    mut airplane_mode: ResMut<AirplaneMode>,
+    mut toggled_writer: EventWriter<WifiToggled>,
+    mut updated_writer: EventWriter<AccessPointsUpdated>,
+    mut progress_writer: EventWriter<ConnectionProgressEvent>,
+    mut vpn_writer: EventWriter<VpnToggled>,
+    mut wwan_writer: EventWriter<WwanToggled>,
+    mut airplane_writer: EventWriter<AirplaneModeChanged>,
+    mut action_writer: EventWriter<NetworkActionEvent>,
+    mut error_writer: EventWriter<NetworkErrorEvent>,
+) {
+    for result in results.drain() {
+        match result {
+            NetworkResult::WifiToggled(enabled) => {
+                toggled_writer.write(WifiToggled(enabled));
+            }
+            NetworkResult::ToggleFailed(reason) => {
+                error_writer.write(NetworkErrorEvent(ErrorType::ToggleWifiError(reason)));
+            }
+            NetworkResult::VpnToggled { name, active } => {
+                vpn_writer.write(VpnToggled { name, active });
+            }
+            NetworkResult::VpnFailed { name, reason } => {
+                error_writer.write(NetworkErrorEvent(ErrorType::VpnError { name, reason }));
+            }
+            NetworkResult::WwanToggled(enabled) => {
+                wwan_writer.write(WwanToggled(enabled));
+            }
+            NetworkResult::WwanFailed(reason) => {
+                error_writer.write(NetworkErrorEvent(ErrorType::WwanError(reason)));
+            }
+            NetworkResult::AirplaneModeSet {
+                enabled,
+                saved,
+                radios,
+            } => {
+                airplane_mode.enabled = enabled;
+                airplane_mode.saved = saved;
+                airplane_writer.write(AirplaneModeChanged { enabled, radios });
+                if let Some(next) = airplane_mode.finish() {
+                    action_writer.write(NetworkActionEvent(NetworkAction::AirplaneMode(next)));
+                }
+            }
+            NetworkResult::AirplaneModeFailed { reason, saved } => {
+                // Some radios may be off already, turning airplane mode off
+                // again restores them
+                if let Some(saved) = saved {
+                    airplane_mode.enabled = true;
+                    airplane_mode.saved = Some(saved);
+                }
+                error_writer.write(NetworkErrorEvent(ErrorType::AirplaneModeError(reason)));
+                if let Some(next) = airplane_mode.finish() {
+                    action_writer.write(NetworkActionEvent(NetworkAction::AirplaneMode(next)));
+                }
+            }
+            NetworkResult::Scanned(list) => {
+                access_points.0 = list.clone();
+                updated_writer.write(AccessPointsUpdated(list));
+            }
+            NetworkResult::ScanFailed(reason) => {
+                error_writer.write(NetworkErrorEvent(ErrorType::ScanError(reason)));
+            }
+            NetworkResult::Progress { ssid, progress } => {
+                let reason = match &progress {
+                    ConnectionProgress::Failed(reason) => Some(reason.clone()),
+                    ConnectionProgress::WrongPassword(_) => Some("wrong password".to_string()),
+                    _ => None,
+                };
+                if let Some(reason) = reason {
+                    error_writer.write(NetworkErrorEvent(ErrorType::SwitchNetworkError {
+                        ssid: ssid.clone(),
+                        reason,
+                    }));
+                }
+                progress_writer.write(ConnectionProgressEvent { ssid, progress });
+            }
+        }
+    }
+}
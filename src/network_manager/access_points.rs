@@ -0,0 +1,599 @@
+//! Wi-Fi scanning and connecting through NetworkManager.
+
+use std::{cmp::Reverse, collections::HashMap, time::Duration};
+
+use bevy::{
+    log::{error, info},
+    tasks::futures_lite::{StreamExt, future},
+};
+use zbus::{
+    Connection,
+    zvariant::{ObjectPath, OwnedObjectPath, Value},
+};
+
+use super::proxies::{
+    AccessPointProxy, ActiveConnectionProxy, ConnectionSettings, DeviceProxy,
+    NM_802_11_AP_FLAGS_PRIVACY, NM_802_11_AP_SEC_KEY_MGMT_802_1X, NM_802_11_AP_SEC_KEY_MGMT_PSK,
+    NM_802_11_AP_SEC_KEY_MGMT_SAE, NM_ACTIVE_CONNECTION_STATE_ACTIVATED,
+    NM_ACTIVE_CONNECTION_STATE_DEACTIVATED, NM_ACTIVE_CONNECTION_STATE_REASON_LOGIN_FAILED,
+    NM_ACTIVE_CONNECTION_STATE_REASON_NO_SECRETS, NM_DEVICE_TYPE_WIFI, NetworkManagerProxy,
+    SettingsConnectionProxy, SettingsProxy, WirelessProxy,
+};
+
+/// How long to wait for a requested scan to finish before listing what is known.
+const SCAN_TIMEOUT: Duration = Duration::from_secs(5);
+const SCAN_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// How long an activation may take before it is given up, NetworkManager
+/// itself keeps trying for much longer on some failures.
+const ACTIVATION_TIMEOUT: Duration = Duration::from_secs(45);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Security {
+    Open,
+    Wep,
+    Wpa,
+    Wpa2,
+    Wpa3,
+    Enterprise,
+}
+
+impl Security {
+    fn from_flags(flags: u32, wpa_flags: u32, rsn_flags: u32) -> Self {
+        let key_mgmt = wpa_flags | rsn_flags;
+        if key_mgmt & NM_802_11_AP_SEC_KEY_MGMT_802_1X != 0 {
+            Security::Enterprise
+        } else if rsn_flags & NM_802_11_AP_SEC_KEY_MGMT_SAE != 0 {
+            Security::Wpa3
+        } else if rsn_flags & NM_802_11_AP_SEC_KEY_MGMT_PSK != 0 {
+            Security::Wpa2
+        } else if wpa_flags != 0 {
+            Security::Wpa
+        } else if flags & NM_802_11_AP_FLAGS_PRIVACY != 0 {
+            Security::Wep
+        } else {
+            Security::Open
+        }
+    }
+
+    pub fn is_secured(self) -> bool {
+        self != Security::Open
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Security::Open => "Open",
+            Security::Wep => "WEP",
+            Security::Wpa => "WPA",
+            Security::Wpa2 => "WPA2",
+            Security::Wpa3 => "WPA3",
+            Security::Enterprise => "802.1X",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessPoint {
+    pub ssid: String,
+    /// Signal strength in percent.
+    pub strength: u8,
+    pub security: Security,
+    pub path: OwnedObjectPath,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionProgress {
+    /// The network is secured and has no saved profile, connect again with
+    /// `NetworkAction::ConnectWithPassword`.
+    PasswordRequired(Security),
+    Connecting,
+    Connected,
+    WrongPassword(Security),
+    Failed(String),
+}
+
+/// Returns the first Wi-Fi device.
+pub(crate) async fn wifi_device(connection: &Connection) -> zbus::Result<OwnedObjectPath> {
+    let nm = NetworkManagerProxy::new(connection).await?;
+    for path in nm.get_devices().await? {
+        let device = DeviceProxy::builder(connection)
+            .path(path.clone())?
+            .build()
+            .await?;
+        if device.device_type().await? == NM_DEVICE_TYPE_WIFI {
+            return Ok(path);
+        }
+    }
+    Err(zbus::Error::Failure("no Wi-Fi device found".to_string()))
+}
+
+/// Requests a scan, waits for it to complete (or time out) and returns the
+/// visible networks, strongest first, one entry per SSID.
+pub async fn scan_access_points(connection: &Connection) -> zbus::Result<Vec<AccessPoint>> {
+    let device = wifi_device(connection).await?;
+    let wireless = WirelessProxy::builder(connection)
+        .path(device)?
+        .build()
+        .await?;
+
+    let last_scan = wireless.last_scan().await.unwrap_or_default();
+    // NetworkManager refuses scans requested too often, the cached list is
+    // still fine in that case.
+    if wireless.request_scan(HashMap::new()).await.is_ok() {
+        let mut waited = Duration::ZERO;
+        while waited < SCAN_TIMEOUT {
+            async_std::task::sleep(SCAN_POLL_INTERVAL).await;
+            waited += SCAN_POLL_INTERVAL;
+            if wireless.last_scan().await.unwrap_or_default() != last_scan {
+                break;
+            }
+        }
+    }
+
+    let mut access_points: Vec<AccessPoint> = Vec::new();
+    for path in wireless.get_all_access_points().await? {
+        let ap = match read_access_point(connection, path).await {
+            Ok(ap) => ap,
+            // Access points disappear while we read them
+            Err(_) => continue,
+        };
+        // Hidden networks have no SSID
+        if ap.ssid.is_empty() {
+            continue;
+        }
+        match access_points.iter_mut().find(|known| known.ssid == ap.ssid) {
+            Some(known) if known.strength < ap.strength => *known = ap,
+            Some(_) => {}
+            None => access_points.push(ap),
+        }
+    }
+    access_points.sort_by_key(|ap| Reverse(ap.strength));
+    Ok(access_points)
+}
+
+async fn read_access_point(
+    connection: &Connection,
+    path: OwnedObjectPath,
+) -> zbus::Result<AccessPoint> {
+    let proxy = AccessPointProxy::builder(connection)
+        .path(path.clone())?
+        .build()
+        .await?;
+    let ssid = String::from_utf8_lossy(&proxy.ssid().await?).into_owned();
+    Ok(AccessPoint {
+        ssid,
+        strength: proxy.strength().await?,
+        security: Security::from_flags(
+            proxy.flags().await?,
+            proxy.wpa_flags().await?,
+            proxy.rsn_flags().await?,
+        ),
+        path,
+    })
+}
+
+/// Saved connection profile for `ssid`, if any.
+pub(crate) async fn saved_connection(
+    connection: &Connection,
+    ssid: &str,
+) -> zbus::Result<Option<OwnedObjectPath>> {
+    let settings = SettingsProxy::new(connection).await?;
+    for path in settings.list_connections().await? {
+        let profile = SettingsConnectionProxy::builder(connection)
+            .path(path.clone())?
+            .build()
+            .await?;
+        let Ok(profile_settings) = profile.get_settings().await else {
+            continue;
+        };
+        if settings_ssid(&profile_settings).as_deref() == Some(ssid.as_bytes()) {
+            return Ok(Some(path));
+        }
+    }
+    Ok(None)
+}
+
+pub(crate) fn settings_ssid(settings: &ConnectionSettings) -> Option<Vec<u8>> {
+    let ssid = settings.get("802-11-wireless")?.get("ssid")?;
+    match &**ssid {
+        Value::Array(array) => Some(
+            array
+                .iter()
+                .filter_map(|byte| match byte {
+                    Value::U8(byte) => Some(*byte),
+                    _ => None,
+                })
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Connects to `ssid`, preferring a saved profile. Open networks without a
+/// profile get a new one, secured ones report `PasswordRequired`. Calls
+/// `progress` as the activation goes on.
+///
+/// `known` is the entry of the last scan for `ssid`, if any. It saves a
+/// rescan as long as NetworkManager still lists it.
+pub async fn connect_to_network(
+    connection: &Connection,
+    ssid: &str,
+    known: Option<AccessPoint>,
+    mut progress: impl FnMut(ConnectionProgress),
+) -> zbus::Result<()> {
+    let device = wifi_device(connection).await?;
+    let access_point = locate_access_point(connection, ssid, known).await?;
+    let nm = NetworkManagerProxy::new(connection).await?;
+    let specific_object = access_point_path(access_point.as_ref());
+
+    if let Some(profile) = saved_connection(connection, ssid).await? {
+        progress(ConnectionProgress::Connecting);
+        let active = nm
+            .activate_connection(&profile, &device, &specific_object)
+            .await?;
+        return match wait_for_activation(connection, active).await? {
+            Activation::Activated => {
+                progress(ConnectionProgress::Connected);
+                Ok(())
+            }
+            Activation::Failed { reason } => Err(activation_failed(reason)),
+            Activation::TimedOut => Err(activation_timed_out()),
+        };
+    }
+
+    let Some(access_point) = access_point else {
+        return Err(zbus::Error::Failure(format!("{} is not in range", ssid)));
+    };
+    if access_point.security.is_secured() {
+        progress(ConnectionProgress::PasswordRequired(access_point.security));
+        return Ok(());
+    }
+    progress(ConnectionProgress::Connecting);
+    add_and_activate(
+        connection,
+        &device,
+        &access_point,
+        wifi_settings(ssid),
+        progress,
+    )
+    .await
+}
+
+/// Creates a profile for the secured network `ssid` and activates it. A
+/// rejected password reports `WrongPassword` and the profile is removed again.
+/// `known` works as in [`connect_to_network`].
+pub async fn connect_with_password(
+    connection: &Connection,
+    ssid: &str,
+    password: &str,
+    known: Option<AccessPoint>,
+    mut progress: impl FnMut(ConnectionProgress),
+) -> zbus::Result<()> {
+    let device = wifi_device(connection).await?;
+    let Some(access_point) = locate_access_point(connection, ssid, known).await? else {
+        return Err(zbus::Error::Failure(format!("{} is not in range", ssid)));
+    };
+    if let Err(e) = validate_password(access_point.security, password) {
+        return Err(zbus::Error::Failure(e));
+    }
+    let settings = secured_wifi_settings(ssid, access_point.security, password)
+        .map_err(zbus::Error::Failure)?;
+
+    progress(ConnectionProgress::Connecting);
+    add_and_activate(connection, &device, &access_point, settings, progress).await
+}
+
+/// Checks a password against what `security` accepts, so the dialog can
+/// complain before anything is sent to NetworkManager.
+pub fn validate_password(security: Security, password: &str) -> Result<(), String> {
+    let is_hex = |s: &str| s.chars().all(|c| c.is_ascii_hexdigit());
+    match security {
+        Security::Open => Ok(()),
+        Security::Wep => match password.len() {
+            5 | 13 => Ok(()),
+            10 | 26 if is_hex(password) => Ok(()),
+            _ => Err("WEP keys are 5 or 13 characters, or 10 or 26 hex digits".to_string()),
+        },
+        Security::Wpa | Security::Wpa2 => match password.len() {
+            8..=63 => Ok(()),
+            64 if is_hex(password) => Ok(()),
+            _ => Err("WPA passwords are 8 to 63 characters".to_string()),
+        },
+        Security::Wpa3 if password.is_empty() => Err("the password is empty".to_string()),
+        Security::Wpa3 => Ok(()),
+        Security::Enterprise => Err("802.1X networks are not supported".to_string()),
+    }
+}
+
+/// `known` as NetworkManager lists it now, or the result of a new scan if it
+/// is gone or was never seen.
+async fn locate_access_point(
+    connection: &Connection,
+    ssid: &str,
+    known: Option<AccessPoint>,
+) -> zbus::Result<Option<AccessPoint>> {
+    if let Some(known) = known {
+        // Paths of vanished access points fail to read, or get reused
+        if let Ok(access_point) = read_access_point(connection, known.path).await
+            && access_point.ssid == ssid
+        {
+            return Ok(Some(access_point));
+        }
+    }
+    find_access_point(connection, ssid).await
+}
+
+async fn find_access_point(
+    connection: &Connection,
+    ssid: &str,
+) -> zbus::Result<Option<AccessPoint>> {
+    Ok(scan_access_points(connection)
+        .await?
+        .into_iter()
+        .find(|ap| ap.ssid == ssid))
+}
+
+/// "/" lets NetworkManager pick the access point itself.
+fn access_point_path(access_point: Option<&AccessPoint>) -> OwnedObjectPath {
+    access_point
+        .map(|ap| ap.path.clone())
+        .unwrap_or_else(|| ObjectPath::from_static_str_unchecked("/").into())
+}
+
+async fn add_and_activate(
+    connection: &Connection,
+    device: &OwnedObjectPath,
+    access_point: &AccessPoint,
+    settings: HashMap<&str, HashMap<&str, Value<'_>>>,
+    mut progress: impl FnMut(ConnectionProgress),
+) -> zbus::Result<()> {
+    let nm = NetworkManagerProxy::new(connection).await?;
+    let (profile, active) = nm
+        .add_and_activate_connection(settings, device, &access_point.path)
+        .await?;
+    let reason = match wait_for_activation(connection, active).await? {
+        Activation::Activated => {
+            info!("Connected to {}", access_point.ssid);
+            progress(ConnectionProgress::Connected);
+            return Ok(());
+        }
+        Activation::Failed { reason } => Some(reason),
+        Activation::TimedOut => None,
+    };
+
+    // Don't keep a profile that never worked, it would be picked up next time
+    let profile = SettingsConnectionProxy::builder(connection)
+        .path(profile)?
+        .build()
+        .await?;
+    if let Err(e) = profile.delete().await {
+        error!(
+            "Error removing the profile for {}: {}",
+            access_point.ssid, e
+        );
+    }
+    match reason {
+        Some(
+            NM_ACTIVE_CONNECTION_STATE_REASON_NO_SECRETS
+            | NM_ACTIVE_CONNECTION_STATE_REASON_LOGIN_FAILED,
+        ) if access_point.security.is_secured() => {
+            progress(ConnectionProgress::WrongPassword(access_point.security));
+            Ok(())
+        }
+        Some(reason) => Err(activation_failed(reason)),
+        None => Err(activation_timed_out()),
+    }
+}
+
+fn activation_failed(reason: u32) -> zbus::Error {
+    zbus::Error::Failure(format!("connection was deactivated (reason {})", reason))
+}
+
+fn activation_timed_out() -> zbus::Error {
+    zbus::Error::Failure(format!(
+        "connection was not up after {}s",
+        ACTIVATION_TIMEOUT.as_secs()
+    ))
+}
+
+/// Minimal settings for a new Wi-Fi profile.
+pub(crate) fn wifi_settings(ssid: &str) -> HashMap<&str, HashMap<&str, Value<'_>>> {
+    HashMap::from([
+        (
+            "connection",
+            HashMap::from([
+                ("type", Value::from("802-11-wireless")),
+                ("id", Value::from(ssid)),
+            ]),
+        ),
+        (
+            "802-11-wireless",
+            HashMap::from([("ssid", Value::from(ssid.as_bytes().to_vec()))]),
+        ),
+    ])
+}
+
+/// [`wifi_settings`] plus the `802-11-wireless-security` section for `security`.
+pub(crate) fn secured_wifi_settings<'a>(
+    ssid: &'a str,
+    security: Security,
+    password: &'a str,
+) -> Result<HashMap<&'a str, HashMap<&'a str, Value<'a>>>, String> {
+    let wireless_security = match security {
+        Security::Open => return Ok(wifi_settings(ssid)),
+        Security::Wep => HashMap::from([
+            ("key-mgmt", Value::from("none")),
+            ("wep-key0", Value::from(password)),
+            // NM_WEP_KEY_TYPE_KEY: the key itself, ASCII or hex
+            ("wep-key-type", Value::from(1u32)),
+        ]),
+        Security::Wpa | Security::Wpa2 => HashMap::from([
+            ("key-mgmt", Value::from("wpa-psk")),
+            ("psk", Value::from(password)),
+        ]),
+        Security::Wpa3 => HashMap::from([
+            ("key-mgmt", Value::from("sae")),
+            ("psk", Value::from(password)),
+        ]),
+        Security::Enterprise => return Err("802.1X networks are not supported".to_string()),
+    };
+    let mut settings = wifi_settings(ssid);
+    settings
+        .entry("802-11-wireless")
+        .or_default()
+        .insert("security", Value::from("802-11-wireless-security"));
+    settings.insert("802-11-wireless-security", wireless_security);
+    Ok(settings)
+}
+
+pub(crate) enum Activation {
+    Activated,
+    Failed {
+        /// `NM_ACTIVE_CONNECTION_STATE_REASON_*`, 0 if unknown.
+        reason: u32,
+    },
+    /// Still not up after [`ACTIVATION_TIMEOUT`], the activation was
+    /// cancelled.
+    TimedOut,
+}
+
+/// Follows an active connection until it is activated, gives up or takes
+/// longer than [`ACTIVATION_TIMEOUT`].
+pub(crate) async fn wait_for_activation(
+    connection: &Connection,
+    active: OwnedObjectPath,
+) -> zbus::Result<Activation> {
+    let proxy = ActiveConnectionProxy::builder(connection)
+        .path(active.clone())?
+        .build()
+        .await?;
+    let mut changes = proxy.receive_activation_state_changed().await?;
+    let activation = async {
+        // The state may have moved on before we subscribed
+        let mut state = proxy.state().await?;
+        let mut reason = 0;
+        loop {
+            match state {
+                NM_ACTIVE_CONNECTION_STATE_ACTIVATED => return Ok(Activation::Activated),
+                NM_ACTIVE_CONNECTION_STATE_DEACTIVATED => {
+                    return Ok(Activation::Failed { reason });
+                }
+                _ => {}
+            }
+            let Some(signal) = changes.next().await else {
+                return Ok(Activation::Failed { reason });
+            };
+            let args = signal.args()?;
+            state = args.state;
+            reason = args.reason;
+        }
+    };
+    let timeout = async {
+        async_std::task::sleep(ACTIVATION_TIMEOUT).await;
+        Ok(Activation::TimedOut)
+    };
+    let result = future::or(activation, timeout).await;
+    if let Ok(Activation::TimedOut) = result {
+        // Otherwise NetworkManager keeps trying behind the caller's back
+        let nm = NetworkManagerProxy::new(connection).await?;
+        if let Err(e) = nm.deactivate_connection(&active).await {
+            error!("Error cancelling the activation of {}: {}", active, e);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn security_from_flags() {
+        let psk = NM_802_11_AP_SEC_KEY_MGMT_PSK;
+        let privacy = NM_802_11_AP_FLAGS_PRIVACY;
+        assert_eq!(Security::from_flags(0, 0, 0), Security::Open);
+        assert_eq!(Security::from_flags(privacy, 0, 0), Security::Wep);
+        assert_eq!(Security::from_flags(privacy, psk, 0), Security::Wpa);
+        assert_eq!(Security::from_flags(privacy, psk, psk), Security::Wpa2);
+        // WPA3 transition mode offers both, the stronger one wins
+        let sae = NM_802_11_AP_SEC_KEY_MGMT_SAE;
+        assert_eq!(Security::from_flags(privacy, 0, psk | sae), Security::Wpa3);
+        let eap = NM_802_11_AP_SEC_KEY_MGMT_802_1X;
+        assert_eq!(Security::from_flags(privacy, eap, 0), Security::Enterprise);
+        assert_eq!(
+            Security::from_flags(privacy, 0, eap | psk),
+            Security::Enterprise
+        );
+    }
+
+    #[test]
+    fn validate_wep_keys() {
+        assert!(validate_password(Security::Wep, "abcde").is_ok());
+        assert!(validate_password(Security::Wep, "abcdefghijklm").is_ok());
+        assert!(validate_password(Security::Wep, "0123456789").is_ok());
+        assert!(validate_password(Security::Wep, &"a1".repeat(13)).is_ok());
+        assert!(validate_password(Security::Wep, "abcdefghij").is_err());
+        assert!(validate_password(Security::Wep, "abcdef").is_err());
+    }
+
+    #[test]
+    fn validate_wpa_passwords() {
+        for security in [Security::Wpa, Security::Wpa2] {
+            assert!(validate_password(security, "1234567").is_err());
+            assert!(validate_password(security, "12345678").is_ok());
+            assert!(validate_password(security, &"x".repeat(63)).is_ok());
+            assert!(validate_password(security, &"f".repeat(64)).is_ok());
+            assert!(validate_password(security, &"x".repeat(64)).is_err());
+        }
+        assert!(validate_password(Security::Wpa3, "").is_err());
+        assert!(validate_password(Security::Wpa3, "short").is_ok());
+        assert!(validate_password(Security::Open, "").is_ok());
+        assert!(validate_password(Security::Enterprise, "anything").is_err());
+    }
+
+    #[test]
+    fn secured_settings_per_security() {
+        let settings = secured_wifi_settings("Home", Security::Wpa2, "hunter22").unwrap();
+        let security = &settings["802-11-wireless-security"];
+        assert_eq!(security["key-mgmt"], Value::from("wpa-psk"));
+        assert_eq!(security["psk"], Value::from("hunter22"));
+        assert_eq!(
+            settings["802-11-wireless"]["security"],
+            Value::from("802-11-wireless-security")
+        );
+        assert_eq!(settings["connection"]["id"], Value::from("Home"));
+
+        let settings = secured_wifi_settings("Home", Security::Wpa3, "hunter22").unwrap();
+        assert_eq!(
+            settings["802-11-wireless-security"]["key-mgmt"],
+            Value::from("sae")
+        );
+
+        let settings = secured_wifi_settings("Home", Security::Wep, "abcde").unwrap();
+        let security = &settings["802-11-wireless-security"];
+        assert_eq!(security["key-mgmt"], Value::from("none"));
+        assert_eq!(security["wep-key0"], Value::from("abcde"));
+        assert_eq!(security["wep-key-type"], Value::from(1u32));
+
+        let settings = secured_wifi_settings("Cafe", Security::Open, "").unwrap();
+        assert!(!settings.contains_key("802-11-wireless-security"));
+        assert!(!settings["802-11-wireless"].contains_key("security"));
+
+        assert!(secured_wifi_settings("Work", Security::Enterprise, "secret").is_err());
+    }
+
+    #[test]
+    fn settings_ssid_round_trip() {
+        let settings = wifi_settings("Home");
+        let settings: ConnectionSettings = settings
+            .into_iter()
+            .map(|(section, values)| {
+                let values = values
+                    .into_iter()
+                    .map(|(key, value)| (key.to_string(), value.try_to_owned().unwrap()))
+                    .collect();
+                (section.to_string(), values)
+            })
+            .collect();
+        assert_eq!(settings_ssid(&settings), Some(b"Home".to_vec()));
+    }
+}
@@ -0,0 +1,321 @@
+//! Airplane mode: every radio off at once, and back to how they were.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use zbus::{Connection, Result as ZbusResult, fdo::ObjectManagerProxy, zvariant::OwnedObjectPath};
+
+use super::{connections::WwanState, proxies::NetworkManagerProxy, state::NetworkState};
+use crate::bluetooth::proxies::{ADAPTER_INTERFACE, AdapterProxy, BLUEZ_SERVICE};
+
+/// Which radios are switched on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Radios {
+    pub wifi: bool,
+    pub wwan: bool,
+    /// Whether each Bluetooth adapter is powered, by object path. Empty when
+    /// BlueZ or the system bus is missing.
+    pub bluetooth: HashMap<OwnedObjectPath, bool>,
+}
+
+impl Radios {
+    /// Everything off, on the same adapters.
+    fn off(&self) -> Radios {
+        Radios {
+            wifi: false,
+            wwan: false,
+            bluetooth: self
+                .bluetooth
+                .keys()
+                .map(|path| (path.clone(), false))
+                .collect(),
+        }
+    }
+}
+
+/// Whether airplane mode is on. Besides following `AirplaneMode` actions, it
+/// turns off as soon as Wi-Fi or WWAN is switched on some other way, and on
+/// once both are off.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct AirplaneMode {
+    pub enabled: bool,
+    /// Radios as they were when airplane mode was turned on, restored when it
+    /// is turned off.
+    pub saved: Option<Radios>,
+    /// State being switched to while an `AirplaneMode` action runs.
+    pub pending: Option<bool>,
+    /// Asked for while another switch was running, started once it is done.
+    queued: Option<bool>,
+}
+
+impl AirplaneMode {
+    /// Records a request to switch to `enabled`, returning whether to start
+    /// it now. Requests for the current state are dropped, and only the last
+    /// one made while a switch runs is kept for later.
+    pub(crate) fn request(&mut self, enabled: bool) -> bool {
+        if let Some(pending) = self.pending {
+            self.queued = (pending != enabled).then_some(enabled);
+            return false;
+        }
+        if self.enabled == enabled {
+            return false;
+        }
+        self.pending = Some(enabled);
+        true
+    }
+
+    /// Ends the running switch, whether it worked or not. Returns the queued
+    /// request to start next, if it still changes anything.
+    pub(crate) fn finish(&mut self) -> Option<bool> {
+        self.pending = None;
+        self.queued.take().filter(|queued| *queued != self.enabled)
+    }
+}
+
+/// Sent once an `AirplaneMode` action went through, with the radios as they
+/// are now.
+#[derive(Event, Debug, Clone)]
+pub struct AirplaneModeChanged {
+    pub enabled: bool,
+    pub radios: Radios,
+}
+
+/// Reads the radios of NetworkManager on `connection` and of BlueZ on
+/// `system`, the system bus connection if there is one.
+pub async fn read_radios(
+    connection: &Connection,
+    system: Option<&Connection>,
+) -> ZbusResult<Radios> {
+    let nm = NetworkManagerProxy::new(connection).await?;
+    Ok(Radios {
+        wifi: nm.wireless_enabled().await?,
+        wwan: nm.wwan_enabled().await?,
+        bluetooth: bluetooth_powered(system).await,
+    })
+}
+
+/// Switches the radios as given. Adapters gone since `radios` was read are
+/// skipped, ones that appeared since are left alone.
+pub async fn set_radios(
+    connection: &Connection,
+    system: Option<&Connection>,
+    radios: &Radios,
+) -> ZbusResult<()> {
+    let nm = NetworkManagerProxy::new(connection).await?;
+    nm.set_wireless_enabled(radios.wifi).await?;
+    nm.set_wwan_enabled(radios.wwan).await?;
+    if let Some(system) = system
+        && !radios.bluetooth.is_empty()
+    {
+        for adapter in bluetooth_adapters(system).await? {
+            let path = OwnedObjectPath::from(adapter.inner().path().clone());
+            if let Some(powered) = radios.bluetooth.get(&path) {
+                adapter.set_powered(*powered).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Switches every radio off. Returns how they were before, along with how
+/// they are now or why switching them failed. As that may happen part way,
+/// `before` is kept either way so the radios can still be restored.
+pub async fn enable_airplane_mode(
+    connection: &Connection,
+    system: Option<&Connection>,
+) -> ZbusResult<(Radios, ZbusResult<Radios>)> {
+    let before = read_radios(connection, system).await?;
+    let off = before.off();
+    let after = async {
+        set_radios(connection, system, &off).await?;
+        read_radios(connection, system).await
+    };
+    Ok((before, after.await))
+}
+
+/// Restores `saved`, or switches every radio on without it, and returns how
+/// the radios are now.
+pub async fn disable_airplane_mode(
+    connection: &Connection,
+    system: Option<&Connection>,
+    saved: Option<Radios>,
+) -> ZbusResult<Radios> {
+    let restore = match saved {
+        Some(saved) => saved,
+        None => Radios {
+            wifi: true,
+            wwan: true,
+            bluetooth: bluetooth_powered(system)
+                .await
+                .into_keys()
+                .map(|path| (path, true))
+                .collect(),
+        },
+    };
+    set_radios(connection, system, &restore).await?;
+    read_radios(connection, system).await
+}
+
+/// Adapters known to BlueZ, empty when BlueZ isn't running.
+async fn bluetooth_adapters(connection: &Connection) -> ZbusResult<Vec<AdapterProxy<'static>>> {
+    let objects = ObjectManagerProxy::builder(connection)
+        .destination(BLUEZ_SERVICE)?
+        .path("/")?
+        .build()
+        .await?
+        .get_managed_objects()
+        .await?;
+    let mut adapters = Vec::new();
+    for (path, interfaces) in objects {
+        if interfaces
+            .keys()
+            .any(|name| name.as_str() == ADAPTER_INTERFACE)
+        {
+            adapters.push(
+                AdapterProxy::builder(connection)
+                    .path(path)?
+                    .build()
+                    .await?,
+            );
+        }
+    }
+    Ok(adapters)
+}
+
+/// Whether each adapter is powered, empty without BlueZ.
+async fn bluetooth_powered(system: Option<&Connection>) -> HashMap<OwnedObjectPath, bool> {
+    let Some(system) = system else {
+        return HashMap::new();
+    };
+    let adapters = match bluetooth_adapters(system).await {
+        Ok(adapters) => adapters,
+        Err(e) => {
+            debug!("Bluetooth is not available: {e}");
+            return HashMap::new();
+        }
+    };
+    let mut powered = HashMap::new();
+    for adapter in adapters {
+        let path = OwnedObjectPath::from(adapter.inner().path().clone());
+        powered.insert(path, adapter.powered().await.unwrap_or(false));
+    }
+    powered
+}
+
+/// Airplane mode as the radios NetworkManager reports tell it: on while
+/// Wi-Fi and, if there is a modem, WWAN are off.
+pub(crate) fn radios_off(wifi_enabled: bool, wwan: &WwanState) -> bool {
+    let wwan_on = wwan.present && wwan.enabled;
+    !wifi_enabled && !wwan_on
+}
+
+/// Follows radios switched outside of `AirplaneMode` actions. Left alone
+/// while an action runs, as its outcome settles the state.
+pub(crate) fn sync_airplane_mode(
+    network: Res<NetworkState>,
+    wwan: Res<WwanState>,
+    mut airplane_mode: ResMut<AirplaneMode>,
+) {
+    if airplane_mode.pending.is_some() || network.is_added() {
+        return;
+    }
+    if !network.is_changed() && !wwan.is_changed() {
+        return;
+    }
+    let enabled = radios_off(network.wireless_enabled, &wwan);
+    if airplane_mode.enabled != enabled {
+        info!("radios switched elsewhere, airplane mode is now {enabled}");
+        airplane_mode.enabled = enabled;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn adapter(path: &str) -> OwnedObjectPath {
+        OwnedObjectPath::try_from(path).unwrap()
+    }
+
+    #[test]
+    fn off_switches_every_radio_off() {
+        let on = Radios {
+            wifi: true,
+            wwan: true,
+            bluetooth: HashMap::from([
+                (adapter("/org/bluez/hci0"), true),
+                (adapter("/org/bluez/hci1"), false),
+            ]),
+        };
+        assert_eq!(
+            on.off(),
+            Radios {
+                wifi: false,
+                wwan: false,
+                bluetooth: HashMap::from([
+                    (adapter("/org/bluez/hci0"), false),
+                    (adapter("/org/bluez/hci1"), false),
+                ]),
+            }
+        );
+        let without_bluetooth = Radios {
+            bluetooth: HashMap::new(),
+            ..on
+        };
+        assert!(without_bluetooth.off().bluetooth.is_empty());
+    }
+
+    #[test]
+    fn radios_off_ignores_a_missing_modem() {
+        let no_modem = WwanState {
+            present: false,
+            enabled: true,
+            hardware_enabled: true,
+        };
+        assert!(radios_off(false, &no_modem));
+        assert!(!radios_off(true, &no_modem));
+        let modem = WwanState {
+            present: true,
+            ..no_modem
+        };
+        assert!(!radios_off(false, &modem));
+        assert!(radios_off(
+            false,
+            &WwanState {
+                enabled: false,
+                ..modem
+            }
+        ));
+    }
+
+    #[test]
+    fn requests_for_the_current_state_are_dropped() {
+        let mut mode = AirplaneMode::default();
+        assert!(!mode.request(false));
+        assert!(mode.request(true));
+        assert_eq!(mode.pending, Some(true));
+    }
+
+    #[test]
+    fn requests_during_a_switch_wait_for_it() {
+        let mut mode = AirplaneMode::default();
+        assert!(mode.request(true));
+        // Back and forth while switching, only the last one counts
+        assert!(!mode.request(false));
+        assert!(!mode.request(true));
+        assert!(!mode.request(false));
+        mode.enabled = true;
+        assert_eq!(mode.finish(), Some(false));
+        assert_eq!(mode.pending, None);
+        assert!(mode.request(false));
+    }
+
+    #[test]
+    fn queued_request_matching_the_outcome_is_dropped() {
+        let mut mode = AirplaneMode::default();
+        assert!(mode.request(true));
+        assert!(!mode.request(false));
+        // The switch failed, so airplane mode is still off
+        assert_eq!(mode.finish(), None);
+    }
+}
@@ -0,0 +1,322 @@
+//! Wired devices, VPN connections and mobile broadband (WWAN).
+
+use std::time::Duration;
+
+use bevy::{prelude::*, tasks::futures_lite::StreamExt};
+use tokio::sync::watch;
+use zbus::{
+    Connection, MatchRule, MessageStream, message,
+    zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value},
+};
+
+use super::{
+    access_points::{Activation, wait_for_activation},
+    proxies::{
+        ActiveConnectionProxy, ConnectionSettings, DeviceProxy, NM_DEVICE_STATE_ACTIVATED,
+        NM_DEVICE_TYPE_ETHERNET, NM_DEVICE_TYPE_MODEM, NetworkManagerProxy,
+        SettingsConnectionProxy, SettingsProxy, WiredProxy,
+    },
+};
+use crate::debounce::next_burst;
+
+const NM_SERVICE: &str = "org.freedesktop.NetworkManager";
+const NM_PATH: &str = "/org/freedesktop/NetworkManager";
+const DEVICES_PATH: &str = "/org/freedesktop/NetworkManager/Devices";
+const ACTIVE_CONNECTIONS_PATH: &str = "/org/freedesktop/NetworkManager/ActiveConnection";
+const SETTINGS_PATH: &str = "/org/freedesktop/NetworkManager/Settings";
+/// Signals arriving closer together than this cause a single re-read.
+const CHANGE_DEBOUNCE: Duration = Duration::from_millis(250);
+/// Longest a re-read is put off by signals that keep coming.
+const CHANGE_MAX_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WiredDevice {
+    pub interface: String,
+    /// A cable is plugged in.
+    pub carrier: bool,
+    pub speed_mbps: u32,
+    pub connected: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VpnConnection {
+    /// Profile name, as used by `NetworkAction::ActivateVpn`.
+    pub name: String,
+    pub active: bool,
+}
+
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WwanState {
+    /// A modem is present.
+    pub present: bool,
+    pub enabled: bool,
+    /// False when a hardware switch disables the modem.
+    pub hardware_enabled: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectionsState {
+    pub wired: Vec<WiredDevice>,
+    pub vpns: Vec<VpnConnection>,
+    pub wwan: WwanState,
+}
+
+#[derive(Resource, Debug, Clone, Default, PartialEq, Eq)]
+pub struct WiredState(pub Vec<WiredDevice>);
+
+#[derive(Resource, Debug, Clone, Default, PartialEq, Eq)]
+pub struct VpnState(pub Vec<VpnConnection>);
+
+#[derive(Event, Debug, Clone)]
+pub struct WiredStateChanged(pub Vec<WiredDevice>);
+
+#[derive(Event, Debug, Clone)]
+pub struct VpnStateChanged(pub Vec<VpnConnection>);
+
+#[derive(Event, Debug, Clone)]
+pub struct WwanStateChanged(pub WwanState);
+
+pub async fn read_connections(connection: &Connection) -> zbus::Result<ConnectionsState> {
+    let profiles = vpn_profiles(connection).await?;
+    read_connections_with(connection, &profiles).await
+}
+
+/// [`read_connections`] with the VPN profiles already known, as reading them
+/// means fetching the settings of every saved profile.
+async fn read_connections_with(
+    connection: &Connection,
+    profiles: &[(String, OwnedObjectPath)],
+) -> zbus::Result<ConnectionsState> {
+    let nm = NetworkManagerProxy::new(connection).await?;
+    let mut state = ConnectionsState {
+        wwan: WwanState {
+            present: false,
+            enabled: nm.wwan_enabled().await?,
+            hardware_enabled: nm.wwan_hardware_enabled().await?,
+        },
+        ..Default::default()
+    };
+
+    for path in nm.get_devices().await? {
+        let device = DeviceProxy::builder(connection)
+            .path(path.clone())?
+            .build()
+            .await?;
+        match device.device_type().await? {
+            NM_DEVICE_TYPE_ETHERNET => {
+                let wired = WiredProxy::builder(connection).path(path)?.build().await?;
+                state.wired.push(WiredDevice {
+                    interface: device.interface().await?,
+                    carrier: wired.carrier().await?,
+                    speed_mbps: wired.speed().await?,
+                    connected: device.state().await? == NM_DEVICE_STATE_ACTIVATED,
+                });
+            }
+            NM_DEVICE_TYPE_MODEM => state.wwan.present = true,
+            _ => {}
+        }
+    }
+
+    let active: Vec<String> = active_vpns(connection)
+        .await?
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    for (name, _) in profiles {
+        state.vpns.push(VpnConnection {
+            active: active.contains(name),
+            name: name.clone(),
+        });
+    }
+    state.vpns.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(state)
+}
+
+/// Saved VPN (including WireGuard) profiles by name.
+async fn vpn_profiles(connection: &Connection) -> zbus::Result<Vec<(String, OwnedObjectPath)>> {
+    let settings = SettingsProxy::new(connection).await?;
+    let mut profiles = Vec::new();
+    for path in settings.list_connections().await? {
+        let profile = SettingsConnectionProxy::builder(connection)
+            .path(path.clone())?
+            .build()
+            .await?;
+        let Ok(profile_settings) = profile.get_settings().await else {
+            continue;
+        };
+        if let Some(name) = vpn_name(&profile_settings) {
+            profiles.push((name, path));
+        }
+    }
+    Ok(profiles)
+}
+
+/// Active VPN connections by name.
+async fn active_vpns(connection: &Connection) -> zbus::Result<Vec<(String, OwnedObjectPath)>> {
+    let nm = NetworkManagerProxy::new(connection).await?;
+    let mut active = Vec::new();
+    for path in nm.active_connections().await? {
+        let proxy = ActiveConnectionProxy::builder(connection)
+            .path(path.clone())?
+            .build()
+            .await?;
+        if proxy.vpn().await? || proxy.connection_type().await? == "wireguard" {
+            active.push((proxy.id().await?, path));
+        }
+    }
+    Ok(active)
+}
+
+fn vpn_name(settings: &ConnectionSettings) -> Option<String> {
+    let section = settings.get("connection")?;
+    let kind = string_value(section.get("type")?)?;
+    if kind != "vpn" && kind != "wireguard" {
+        return None;
+    }
+    string_value(section.get("id")?)
+}
+
+fn string_value(value: &OwnedValue) -> Option<String> {
+    match &**value {
+        Value::Str(s) => Some(s.to_string()),
+        _ => None,
+    }
+}
+
+/// Activates the VPN profile `name` and waits until it is up.
+pub async fn activate_vpn(connection: &Connection, name: &str) -> zbus::Result<()> {
+    let profile = vpn_profiles(connection)
+        .await?
+        .into_iter()
+        .find(|(profile, _)| profile == name)
+        .map(|(_, path)| path)
+        .ok_or_else(|| zbus::Error::Failure(format!("no VPN named {}", name)))?;
+    let nm = NetworkManagerProxy::new(connection).await?;
+    // VPNs run on top of whatever device carries the default route
+    let any = ObjectPath::from_static_str_unchecked("/");
+    let active = nm.activate_connection(&profile, &any, &any).await?;
+    match wait_for_activation(connection, active).await? {
+        Activation::Activated => Ok(()),
+        Activation::Failed { reason } => Err(zbus::Error::Failure(format!(
+            "{} was deactivated (reason {})",
+            name, reason
+        ))),
+        Activation::TimedOut => Err(zbus::Error::Failure(format!("{} was not up in time", name))),
+    }
+}
+
+pub async fn deactivate_vpn(connection: &Connection, name: &str) -> zbus::Result<()> {
+    let nm = NetworkManagerProxy::new(connection).await?;
+    for (active, path) in active_vpns(connection).await? {
+        if active == name {
+            return nm.deactivate_connection(&path).await;
+        }
+    }
+    Err(zbus::Error::Failure(format!("{} is not active", name)))
+}
+
+pub async fn set_wwan_enabled(connection: &Connection, enabled: bool) -> zbus::Result<()> {
+    NetworkManagerProxy::new(connection)
+        .await?
+        .set_wwan_enabled(enabled)
+        .await
+}
+
+/// Sends the current state, then a new one after NetworkManager signals
+/// changes to a device, an active connection or the profiles.
+/// Returns once `tx` has no receivers.
+pub async fn follow_connections(
+    connection: &Connection,
+    tx: &watch::Sender<ConnectionsState>,
+) -> zbus::Result<()> {
+    // Access points are left out, their strength changes all the time.
+    // Profiles are only re-read after a signal from the settings, so each
+    // stream tells whether it is that one
+    let settings = signals(connection, SETTINGS_PATH, true).await?;
+    let devices = signals(connection, DEVICES_PATH, true).await?;
+    let active = signals(connection, ACTIVE_CONNECTIONS_PATH, true).await?;
+    let root = signals(connection, NM_PATH, false).await?;
+    let mut changes = settings
+        .map(|_| true)
+        .or(devices.or(active).or(root).map(|_| false));
+
+    let mut profiles = None;
+    loop {
+        let state = async {
+            if profiles.is_none() {
+                profiles = Some(vpn_profiles(connection).await?);
+            }
+            read_connections_with(connection, profiles.as_deref().unwrap_or_default()).await
+        };
+        match state.await {
+            Ok(state) => {
+                tx.send_if_modified(|current| {
+                    let modified = *current != state;
+                    *current = state;
+                    modified
+                });
+            }
+            Err(e) => error!("Error reading connections: {e}"),
+        }
+        if tx.is_closed() {
+            return Ok(());
+        }
+        let Some(burst) = next_burst(&mut changes, CHANGE_DEBOUNCE, CHANGE_MAX_DELAY).await else {
+            return Ok(());
+        };
+        if burst.contains(&true) {
+            profiles = None;
+        }
+    }
+}
+
+/// NetworkManager's signals from `path`, or from everything below it too
+/// with `namespace`.
+async fn signals(
+    connection: &Connection,
+    path: &'static str,
+    namespace: bool,
+) -> zbus::Result<MessageStream> {
+    let rule = MatchRule::builder()
+        .msg_type(message::Type::Signal)
+        .sender(NM_SERVICE)?;
+    let rule = if namespace {
+        rule.path_namespace(path)?
+    } else {
+        rule.path(path)?
+    };
+    MessageStream::for_match_rule(rule.build(), connection, None).await
+}
+
+#[derive(Resource)]
+pub(crate) struct ConnectionsReceiver(pub(crate) watch::Receiver<ConnectionsState>);
+
+pub(crate) fn sync_connections(
+    receiver: Option<ResMut<ConnectionsReceiver>>,
+    mut wired: ResMut<WiredState>,
+    mut vpns: ResMut<VpnState>,
+    mut wwan: ResMut<WwanState>,
+    mut wired_writer: EventWriter<WiredStateChanged>,
+    mut vpn_writer: EventWriter<VpnStateChanged>,
+    mut wwan_writer: EventWriter<WwanStateChanged>,
+) {
+    let Some(mut receiver) = receiver else {
+        return;
+    };
+    if !receiver.0.has_changed().unwrap_or(false) {
+        return;
+    }
+    let state = receiver.0.borrow_and_update().clone();
+    if wired.0 != state.wired {
+        wired.0 = state.wired.clone();
+        wired_writer.write(WiredStateChanged(state.wired));
+    }
+    if vpns.0 != state.vpns {
+        vpns.0 = state.vpns.clone();
+        vpn_writer.write(VpnStateChanged(state.vpns));
+    }
+    if *wwan != state.wwan {
+        *wwan = state.wwan;
+        wwan_writer.write(WwanStateChanged(state.wwan));
+    }
+}
@@ -0,0 +1,1170 @@
+//! Stand-in for NetworkManager, serving the part of its D-Bus API the plugin
+//! uses (Wi-Fi radio, one Wi-Fi device, access points, saved profiles and
+//! activation) so the plugin can be developed and tested without real
+//! hardware. What it shows is driven by a [`Scenario`].
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_std::channel::{self, Sender};
+use bevy::log::{error, info};
+use zbus::{
+    Connection, connection, fdo, interface,
+    object_server::SignalContext,
+    zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value},
+};
+
+use super::{
+    access_points::{Security, secured_wifi_settings, settings_ssid},
+    proxies::{
+        ConnectionSettings, NM_802_11_AP_FLAGS_PRIVACY, NM_802_11_AP_SEC_KEY_MGMT_802_1X,
+        NM_802_11_AP_SEC_KEY_MGMT_PSK, NM_802_11_AP_SEC_KEY_MGMT_SAE,
+        NM_ACTIVE_CONNECTION_STATE_ACTIVATED, NM_ACTIVE_CONNECTION_STATE_ACTIVATING,
+        NM_ACTIVE_CONNECTION_STATE_DEACTIVATED, NM_ACTIVE_CONNECTION_STATE_REASON_NO_SECRETS,
+        NM_ACTIVE_CONNECTION_STATE_REASON_NONE,
+        NM_ACTIVE_CONNECTION_STATE_REASON_USER_DISCONNECTED, NM_CONNECTIVITY_FULL,
+        NM_CONNECTIVITY_NONE, NM_DEVICE_STATE_ACTIVATED, NM_DEVICE_STATE_DISCONNECTED,
+        NM_DEVICE_STATE_UNAVAILABLE, NM_DEVICE_TYPE_WIFI, NM_STATE_CONNECTED_GLOBAL,
+        NM_STATE_DISCONNECTED,
+    },
+};
+use crate::bus::Bus;
+
+pub const SERVICE_NAME: &str = "org.freedesktop.NetworkManager";
+const NM_PATH: &str = "/org/freedesktop/NetworkManager";
+const DEVICE_PATH: &str = "/org/freedesktop/NetworkManager/Devices/1";
+const SETTINGS_PATH: &str = "/org/freedesktop/NetworkManager/Settings";
+
+/// Time between `ActivateConnection` and the connection going up (or down).
+const ACTIVATION_DELAY: Duration = Duration::from_millis(800);
+const IP_ADDRESS: &str = "192.168.1.42";
+
+pub const SCENARIO_USAGE: &str = "\
+One command per line, `#` starts a comment:
+  ap <ssid> <strength> <open|wep|wpa|wpa2|wpa3|802.1x> [password]
+  remove <ssid>
+  strength <ssid> <percent>
+  saved <ssid>          save a profile with the access point's password
+  wireless on|off
+  connect <ssid>
+  disconnect
+Prefix a command with `at <seconds>` to run it that long after startup.";
+
+/// Used when no scenario file is given.
+pub const DEFAULT_SCENARIO: &str = "\
+ap HomeWifi 82 wpa2 hunter22
+ap CafeFree 60 open
+ap Office 45 wpa3 correct-horse
+ap Campus 30 802.1x
+saved HomeWifi
+connect HomeWifi
+at 20 strength HomeWifi 35
+at 40 ap Airport 70 open
+";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScenarioCommand {
+    AddAccessPoint {
+        ssid: String,
+        strength: u8,
+        security: Security,
+        /// Accepted password, `None` accepts anything.
+        password: Option<String>,
+    },
+    RemoveAccessPoint(String),
+    Strength {
+        ssid: String,
+        strength: u8,
+    },
+    SaveProfile(String),
+    Wireless(bool),
+    Connect(String),
+    Disconnect,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Scenario {
+    /// Applied in order once the service is up.
+    pub setup: Vec<ScenarioCommand>,
+    /// Sorted by time since startup.
+    pub steps: Vec<(Duration, ScenarioCommand)>,
+}
+
+impl Scenario {
+    /// Parses the format described in [`SCENARIO_USAGE`].
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut scenario = Scenario::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let result = match words.as_slice() {
+                [] => continue,
+                ["at", seconds, command @ ..] => parse_time(seconds).and_then(|at| {
+                    scenario.steps.push((at, parse_command(command)?));
+                    Ok(())
+                }),
+                command => parse_command(command).map(|command| scenario.setup.push(command)),
+            };
+            result.map_err(|e| format!("line {}: {}", number + 1, e))?;
+        }
+        scenario.steps.sort_by_key(|(at, _)| *at);
+        Ok(scenario)
+    }
+}
+
+fn parse_command(words: &[&str]) -> Result<ScenarioCommand, String> {
+    let command = match words {
+        ["ap", ssid, strength, security, password @ ..] if password.len() <= 1 => {
+            ScenarioCommand::AddAccessPoint {
+                ssid: ssid.to_string(),
+                strength: parse_strength(strength)?,
+                security: parse_security(security)?,
+                password: password.first().map(|password| password.to_string()),
+            }
+        }
+        ["remove", ssid] => ScenarioCommand::RemoveAccessPoint(ssid.to_string()),
+        ["strength", ssid, strength] => ScenarioCommand::Strength {
+            ssid: ssid.to_string(),
+            strength: parse_strength(strength)?,
+        },
+        ["saved", ssid] => ScenarioCommand::SaveProfile(ssid.to_string()),
+        ["wireless", "on"] => ScenarioCommand::Wireless(true),
+        ["wireless", "off"] => ScenarioCommand::Wireless(false),
+        ["connect", ssid] => ScenarioCommand::Connect(ssid.to_string()),
+        ["disconnect"] => ScenarioCommand::Disconnect,
+        _ => return Err(format!("unknown command {:?}", words.join(" "))),
+    };
+    Ok(command)
+}
+
+fn parse_time(s: &str) -> Result<Duration, String> {
+    let seconds = s
+        .parse::<f64>()
+        .map_err(|e| format!("invalid time {:?}: {}", s, e))?;
+    // Rejects negative, NaN and too large values
+    Duration::try_from_secs_f64(seconds).map_err(|e| format!("invalid time {:?}: {}", s, e))
+}
+
+fn parse_strength(s: &str) -> Result<u8, String> {
+    s.parse::<u8>()
+        .ok()
+        .filter(|strength| *strength <= 100)
+        .ok_or(format!("invalid strength {:?}, expected 0 to 100", s))
+}
+
+fn parse_security(s: &str) -> Result<Security, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "open" => Ok(Security::Open),
+        "wep" => Ok(Security::Wep),
+        "wpa" => Ok(Security::Wpa),
+        "wpa2" => Ok(Security::Wpa2),
+        "wpa3" => Ok(Security::Wpa3),
+        "802.1x" => Ok(Security::Enterprise),
+        _ => Err(format!("unknown security {:?}", s)),
+    }
+}
+
+/// `Flags`, `WpaFlags` and `RsnFlags` of an access point using `security`.
+fn security_flags(security: Security) -> (u32, u32, u32) {
+    match security {
+        Security::Open => (0, 0, 0),
+        Security::Wep => (NM_802_11_AP_FLAGS_PRIVACY, 0, 0),
+        Security::Wpa => (NM_802_11_AP_FLAGS_PRIVACY, NM_802_11_AP_SEC_KEY_MGMT_PSK, 0),
+        Security::Wpa2 => (NM_802_11_AP_FLAGS_PRIVACY, 0, NM_802_11_AP_SEC_KEY_MGMT_PSK),
+        Security::Wpa3 => (NM_802_11_AP_FLAGS_PRIVACY, 0, NM_802_11_AP_SEC_KEY_MGMT_SAE),
+        Security::Enterprise => (
+            NM_802_11_AP_FLAGS_PRIVACY,
+            0,
+            NM_802_11_AP_SEC_KEY_MGMT_802_1X,
+        ),
+    }
+}
+
+struct MockAccessPoint {
+    id: u32,
+    ssid: String,
+    strength: u8,
+    security: Security,
+    password: Option<String>,
+}
+
+struct ActiveConnection {
+    id: u32,
+    access_point: u32,
+}
+
+struct MockState {
+    wireless_enabled: bool,
+    /// There is no modem, but the switch is still there to flip.
+    wwan_enabled: bool,
+    access_points: Vec<MockAccessPoint>,
+    profiles: HashMap<u32, ConnectionSettings>,
+    active: Option<ActiveConnection>,
+    started: Instant,
+    /// Milliseconds since `started`, -1 before the first scan.
+    last_scan: i64,
+    next_id: u32,
+}
+
+impl MockState {
+    fn new() -> Self {
+        Self {
+            wireless_enabled: true,
+            wwan_enabled: true,
+            access_points: Vec::new(),
+            profiles: HashMap::new(),
+            active: None,
+            started: Instant::now(),
+            last_scan: -1,
+            next_id: 1,
+        }
+    }
+
+    fn next_id(&mut self) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn access_point(&self, ssid: &str) -> Option<&MockAccessPoint> {
+        self.access_points.iter().find(|ap| ap.ssid == ssid)
+    }
+}
+
+type Shared = Arc<Mutex<MockState>>;
+
+fn object_path(path: String) -> OwnedObjectPath {
+    ObjectPath::from_string_unchecked(path).into()
+}
+
+fn root_path() -> OwnedObjectPath {
+    ObjectPath::from_static_str_unchecked("/").into()
+}
+
+fn access_point_path(id: u32) -> OwnedObjectPath {
+    object_path(format!(
+        "/org/freedesktop/NetworkManager/AccessPoint/{}",
+        id
+    ))
+}
+
+fn profile_path(id: u32) -> OwnedObjectPath {
+    object_path(format!("{}/{}", SETTINGS_PATH, id))
+}
+
+fn active_path(id: u32) -> OwnedObjectPath {
+    object_path(format!(
+        "/org/freedesktop/NetworkManager/ActiveConnection/{}",
+        id
+    ))
+}
+
+fn ip4_config_path(id: u32) -> OwnedObjectPath {
+    object_path(format!("/org/freedesktop/NetworkManager/IP4Config/{}", id))
+}
+
+fn profile_id(path: &ObjectPath<'_>) -> fdo::Result<u32> {
+    path.as_str()
+        .strip_prefix(SETTINGS_PATH)
+        .and_then(|id| id.strip_prefix('/'))
+        .and_then(|id| id.parse().ok())
+        .ok_or_else(|| fdo::Error::UnknownObject(format!("no connection {}", path)))
+}
+
+/// Password stored in a profile, if any.
+fn profile_secret(settings: &ConnectionSettings) -> Option<String> {
+    let security = settings.get("802-11-wireless-security")?;
+    let secret = security.get("psk").or_else(|| security.get("wep-key0"))?;
+    match &**secret {
+        Value::Str(secret) => Some(secret.to_string()),
+        _ => None,
+    }
+}
+
+fn clone_settings(settings: &ConnectionSettings) -> zbus::Result<ConnectionSettings> {
+    settings
+        .iter()
+        .map(|(name, section)| {
+            let section = section
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), value.try_clone()?)))
+                .collect::<zbus::Result<_>>()?;
+            Ok((name.clone(), section))
+        })
+        .collect()
+}
+
+fn owned_settings(
+    settings: HashMap<&str, HashMap<&str, Value<'_>>>,
+) -> zbus::Result<ConnectionSettings> {
+    settings
+        .into_iter()
+        .map(|(name, section)| {
+            let section = section
+                .into_iter()
+                .map(|(key, value)| Ok((key.to_string(), value.try_to_owned()?)))
+                .collect::<zbus::Result<_>>()?;
+            Ok((name.to_string(), section))
+        })
+        .collect()
+}
+
+struct MockNetworkManager {
+    state: Shared,
+    commands: Sender<ScenarioCommand>,
+}
+
+#[interface(name = "org.freedesktop.NetworkManager")]
+impl MockNetworkManager {
+    async fn get_devices(&self) -> Vec<OwnedObjectPath> {
+        vec![object_path(DEVICE_PATH.to_string())]
+    }
+
+    async fn activate_connection(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        connection: OwnedObjectPath,
+        _device: OwnedObjectPath,
+        _specific_object: OwnedObjectPath,
+    ) -> fdo::Result<OwnedObjectPath> {
+        activate(conn, &self.state, profile_id(&connection)?).await
+    }
+
+    async fn add_and_activate_connection(
+        &self,
+        #[zbus(connection)] conn: &Connection,
+        connection: ConnectionSettings,
+        _device: OwnedObjectPath,
+        _specific_object: OwnedObjectPath,
+    ) -> fdo::Result<(OwnedObjectPath, OwnedObjectPath)> {
+        let profile = add_profile(conn, &self.state, connection).await?;
+        let active = activate(conn, &self.state, profile).await?;
+        Ok((profile_path(profile), active))
+    }
+
+    #[zbus(property)]
+    async fn wireless_enabled(&self) -> bool {
+        self.state.lock().unwrap().wireless_enabled
+    }
+
+    #[zbus(property)]
+    async fn set_wireless_enabled(&mut self, enabled: bool) {
+        self.state.lock().unwrap().wireless_enabled = enabled;
+        // Dropping the connection and notifying the device need the object server
+        let _ = self.commands.try_send(ScenarioCommand::Wireless(enabled));
+    }
+
+    #[zbus(property)]
+    async fn wwan_enabled(&self) -> bool {
+        self.state.lock().unwrap().wwan_enabled
+    }
+
+    #[zbus(property)]
+    async fn set_wwan_enabled(&mut self, enabled: bool) {
+        self.state.lock().unwrap().wwan_enabled = enabled;
+    }
+
+    #[zbus(property)]
+    async fn wwan_hardware_enabled(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    async fn active_connections(&self) -> Vec<OwnedObjectPath> {
+        match &self.state.lock().unwrap().active {
+            Some(active) => vec![active_path(active.id)],
+            None => Vec::new(),
+        }
+    }
+
+    #[zbus(property)]
+    async fn primary_connection(&self) -> OwnedObjectPath {
+        match &self.state.lock().unwrap().active {
+            Some(active) => active_path(active.id),
+            None => root_path(),
+        }
+    }
+
+    #[zbus(property)]
+    async fn connectivity(&self) -> u32 {
+        if self.state.lock().unwrap().active.is_some() {
+            NM_CONNECTIVITY_FULL
+        } else {
+            NM_CONNECTIVITY_NONE
+        }
+    }
+
+    #[zbus(signal)]
+    async fn state_changed(&self, ctxt: &SignalContext<'_>, state: u32) -> zbus::Result<()>;
+}
+
+struct MockDevice {
+    state: Shared,
+}
+
+#[interface(name = "org.freedesktop.NetworkManager.Device")]
+impl MockDevice {
+    #[zbus(property)]
+    async fn device_type(&self) -> u32 {
+        NM_DEVICE_TYPE_WIFI
+    }
+
+    #[zbus(property)]
+    async fn interface(&self) -> String {
+        "wlan0".to_string()
+    }
+
+    #[zbus(property)]
+    async fn state(&self) -> u32 {
+        let state = self.state.lock().unwrap();
+        if !state.wireless_enabled {
+            NM_DEVICE_STATE_UNAVAILABLE
+        } else if state.active.is_some() {
+            NM_DEVICE_STATE_ACTIVATED
+        } else {
+            NM_DEVICE_STATE_DISCONNECTED
+        }
+    }
+}
+
+struct MockWireless {
+    state: Shared,
+}
+
+#[interface(name = "org.freedesktop.NetworkManager.Device.Wireless")]
+impl MockWireless {
+    async fn get_all_access_points(&self) -> Vec<OwnedObjectPath> {
+        let state = self.state.lock().unwrap();
+        if !state.wireless_enabled {
+            return Vec::new();
+        }
+        state
+            .access_points
+            .iter()
+            .map(|ap| access_point_path(ap.id))
+            .collect()
+    }
+
+    async fn request_scan(
+        &self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        _options: HashMap<String, OwnedValue>,
+    ) -> fdo::Result<()> {
+        {
+            let mut state = self.state.lock().unwrap();
+            if !state.wireless_enabled {
+                return Err(fdo::Error::Failed("Wi-Fi is disabled".to_string()));
+            }
+            state.last_scan = state.started.elapsed().as_millis() as i64;
+        }
+        self.last_scan_changed(&ctxt).await?;
+        Ok(())
+    }
+
+    #[zbus(property)]
+    async fn last_scan(&self) -> i64 {
+        self.state.lock().unwrap().last_scan
+    }
+}
+
+struct MockAccessPointObject {
+    state: Shared,
+    id: u32,
+}
+
+impl MockAccessPointObject {
+    fn read<T: Default>(&self, f: impl FnOnce(&MockAccessPoint) -> T) -> T {
+        let state = self.state.lock().unwrap();
+        state
+            .access_points
+            .iter()
+            .find(|ap| ap.id == self.id)
+            .map(f)
+            .unwrap_or_default()
+    }
+}
+
+#[interface(name = "org.freedesktop.NetworkManager.AccessPoint")]
+impl MockAccessPointObject {
+    #[zbus(property)]
+    async fn ssid(&self) -> Vec<u8> {
+        self.read(|ap| ap.ssid.as_bytes().to_vec())
+    }
+
+    #[zbus(property)]
+    async fn strength(&self) -> u8 {
+        self.read(|ap| ap.strength)
+    }
+
+    #[zbus(property)]
+    async fn flags(&self) -> u32 {
+        self.read(|ap| security_flags(ap.security).0)
+    }
+
+    #[zbus(property)]
+    async fn wpa_flags(&self) -> u32 {
+        self.read(|ap| security_flags(ap.security).1)
+    }
+
+    #[zbus(property)]
+    async fn rsn_flags(&self) -> u32 {
+        self.read(|ap| security_flags(ap.security).2)
+    }
+}
+
+struct MockSettings {
+    state: Shared,
+}
+
+#[interface(name = "org.freedesktop.NetworkManager.Settings")]
+impl MockSettings {
+    async fn list_connections(&self) -> Vec<OwnedObjectPath> {
+        let state = self.state.lock().unwrap();
+        let mut ids: Vec<u32> = state.profiles.keys().copied().collect();
+        ids.sort();
+        ids.into_iter().map(profile_path).collect()
+    }
+}
+
+struct MockProfile {
+    state: Shared,
+    id: u32,
+}
+
+#[interface(name = "org.freedesktop.NetworkManager.Settings.Connection")]
+impl MockProfile {
+    async fn get_settings(&self) -> fdo::Result<ConnectionSettings> {
+        let state = self.state.lock().unwrap();
+        let settings = state
+            .profiles
+            .get(&self.id)
+            .ok_or_else(|| fdo::Error::UnknownObject(format!("no connection {}", self.id)))?;
+        Ok(clone_settings(settings)?)
+    }
+
+    async fn delete(&self, #[zbus(connection)] conn: &Connection) -> fdo::Result<()> {
+        self.state.lock().unwrap().profiles.remove(&self.id);
+        conn.object_server()
+            .remove::<MockProfile, _>(profile_path(self.id))
+            .await?;
+        Ok(())
+    }
+}
+
+struct MockActiveConnection {
+    id: u32,
+    access_point: u32,
+    ssid: String,
+    activation: u32,
+}
+
+#[interface(name = "org.freedesktop.NetworkManager.Connection.Active")]
+impl MockActiveConnection {
+    #[zbus(property)]
+    async fn id(&self) -> String {
+        self.ssid.clone()
+    }
+
+    #[zbus(property, name = "Type")]
+    async fn connection_type(&self) -> String {
+        "802-11-wireless".to_string()
+    }
+
+    #[zbus(property)]
+    async fn state(&self) -> u32 {
+        self.activation
+    }
+
+    #[zbus(property)]
+    async fn vpn(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    async fn specific_object(&self) -> OwnedObjectPath {
+        access_point_path(self.access_point)
+    }
+
+    #[zbus(property)]
+    async fn ip4_config(&self) -> OwnedObjectPath {
+        if self.activation == NM_ACTIVE_CONNECTION_STATE_ACTIVATED {
+            ip4_config_path(self.id)
+        } else {
+            root_path()
+        }
+    }
+
+    // `state_changed` is taken by the notifier of the `State` property
+    #[zbus(signal, name = "StateChanged")]
+    async fn activation_state_changed(
+        &self,
+        ctxt: &SignalContext<'_>,
+        state: u32,
+        reason: u32,
+    ) -> zbus::Result<()>;
+}
+
+struct MockIp4Config;
+
+#[interface(name = "org.freedesktop.NetworkManager.IP4Config")]
+impl MockIp4Config {
+    #[zbus(property)]
+    async fn address_data(&self) -> Vec<HashMap<String, Value<'static>>> {
+        vec![HashMap::from([
+            ("address".to_string(), Value::from(IP_ADDRESS.to_string())),
+            ("prefix".to_string(), Value::from(24u32)),
+        ])]
+    }
+}
+
+async fn add_profile(
+    conn: &Connection,
+    shared: &Shared,
+    settings: ConnectionSettings,
+) -> zbus::Result<u32> {
+    let id = {
+        let mut state = shared.lock().unwrap();
+        let id = state.next_id();
+        state.profiles.insert(id, settings);
+        id
+    };
+    conn.object_server()
+        .at(
+            profile_path(id),
+            MockProfile {
+                state: shared.clone(),
+                id,
+            },
+        )
+        .await?;
+    Ok(id)
+}
+
+/// Adds a profile holding the access point's own password.
+async fn save_profile(conn: &Connection, shared: &Shared, ssid: &str) -> zbus::Result<u32> {
+    let settings = {
+        let state = shared.lock().unwrap();
+        let ap = state
+            .access_point(ssid)
+            .ok_or_else(|| zbus::Error::Failure(format!("no access point {}", ssid)))?;
+        let password = ap.password.clone().unwrap_or_default();
+        let settings =
+            secured_wifi_settings(ssid, ap.security, &password).map_err(zbus::Error::Failure)?;
+        owned_settings(settings)?
+    };
+    add_profile(conn, shared, settings).await
+}
+
+/// Starts activating `profile`, the outcome follows after [`ACTIVATION_DELAY`].
+async fn activate(
+    conn: &Connection,
+    shared: &Shared,
+    profile: u32,
+) -> fdo::Result<OwnedObjectPath> {
+    let (id, access_point, ssid, accepted) = {
+        let mut state = shared.lock().unwrap();
+        if !state.wireless_enabled {
+            return Err(fdo::Error::Failed("Wi-Fi is disabled".to_string()));
+        }
+        let settings = state
+            .profiles
+            .get(&profile)
+            .ok_or_else(|| fdo::Error::UnknownObject(format!("no connection {}", profile)))?;
+        let ssid = settings_ssid(settings)
+            .map(|ssid| String::from_utf8_lossy(&ssid).into_owned())
+            .unwrap_or_default();
+        let secret = profile_secret(settings);
+        let ap = state
+            .access_point(&ssid)
+            .ok_or_else(|| fdo::Error::Failed(format!("{} is not in range", ssid)))?;
+        let accepted = ap.password.is_none() || ap.password == secret;
+        let access_point = ap.id;
+        (state.next_id(), access_point, ssid, accepted)
+    };
+    info!(
+        "Activating {} ({})",
+        ssid,
+        if accepted {
+            "accepted"
+        } else {
+            "wrong password"
+        }
+    );
+
+    let active = MockActiveConnection {
+        id,
+        access_point,
+        ssid,
+        activation: NM_ACTIVE_CONNECTION_STATE_ACTIVATING,
+    };
+    conn.object_server().at(active_path(id), active).await?;
+
+    let (conn, shared) = (conn.clone(), shared.clone());
+    async_std::task::spawn(async move {
+        async_std::task::sleep(ACTIVATION_DELAY).await;
+        if let Err(e) = finish_activation(&conn, &shared, id, access_point, accepted).await {
+            error!("Error finishing activation {}: {}", id, e);
+        }
+    });
+    Ok(active_path(id))
+}
+
+async fn finish_activation(
+    conn: &Connection,
+    shared: &Shared,
+    id: u32,
+    access_point: u32,
+    accepted: bool,
+) -> zbus::Result<()> {
+    if !accepted {
+        // Real NetworkManager gives up after asking its agents for new secrets
+        set_activation(
+            conn,
+            id,
+            NM_ACTIVE_CONNECTION_STATE_DEACTIVATED,
+            NM_ACTIVE_CONNECTION_STATE_REASON_NO_SECRETS,
+        )
+        .await?;
+        conn.object_server()
+            .remove::<MockActiveConnection, _>(active_path(id))
+            .await?;
+        return Ok(());
+    }
+
+    deactivate(
+        conn,
+        shared,
+        NM_ACTIVE_CONNECTION_STATE_REASON_USER_DISCONNECTED,
+    )
+    .await?;
+    conn.object_server()
+        .at(ip4_config_path(id), MockIp4Config)
+        .await?;
+    shared.lock().unwrap().active = Some(ActiveConnection { id, access_point });
+    set_activation(
+        conn,
+        id,
+        NM_ACTIVE_CONNECTION_STATE_ACTIVATED,
+        NM_ACTIVE_CONNECTION_STATE_REASON_NONE,
+    )
+    .await?;
+    network_changed(conn, shared).await
+}
+
+/// Takes down the active connection, if any.
+async fn deactivate(conn: &Connection, shared: &Shared, reason: u32) -> zbus::Result<()> {
+    let Some(active) = shared.lock().unwrap().active.take() else {
+        return Ok(());
+    };
+    set_activation(
+        conn,
+        active.id,
+        NM_ACTIVE_CONNECTION_STATE_DEACTIVATED,
+        reason,
+    )
+    .await?;
+    let server = conn.object_server();
+    server
+        .remove::<MockActiveConnection, _>(active_path(active.id))
+        .await?;
+    server
+        .remove::<MockIp4Config, _>(ip4_config_path(active.id))
+        .await?;
+    network_changed(conn, shared).await
+}
+
+async fn set_activation(
+    conn: &Connection,
+    id: u32,
+    activation: u32,
+    reason: u32,
+) -> zbus::Result<()> {
+    let iface = conn
+        .object_server()
+        .interface::<_, MockActiveConnection>(active_path(id))
+        .await?;
+    iface.get_mut().await.activation = activation;
+    let active = iface.get().await;
+    let ctxt = iface.signal_context();
+    active.state_changed(ctxt).await?;
+    active.ip4_config_changed(ctxt).await?;
+    active
+        .activation_state_changed(ctxt, activation, reason)
+        .await
+}
+
+/// Announces changes of the radio or the active connection.
+async fn network_changed(conn: &Connection, shared: &Shared) -> zbus::Result<()> {
+    let server = conn.object_server();
+    let nm = server.interface::<_, MockNetworkManager>(NM_PATH).await?;
+    let ctxt = nm.signal_context();
+    let state = if shared.lock().unwrap().active.is_some() {
+        NM_STATE_CONNECTED_GLOBAL
+    } else {
+        NM_STATE_DISCONNECTED
+    };
+    {
+        let nm = nm.get().await;
+        nm.wireless_enabled_changed(ctxt).await?;
+        nm.active_connections_changed(ctxt).await?;
+        nm.primary_connection_changed(ctxt).await?;
+        nm.connectivity_changed(ctxt).await?;
+        nm.state_changed(ctxt, state).await?;
+    }
+    let device = server.interface::<_, MockDevice>(DEVICE_PATH).await?;
+    device
+        .get()
+        .await
+        .state_changed(device.signal_context())
+        .await
+}
+
+async fn apply(conn: &Connection, shared: &Shared, command: ScenarioCommand) -> zbus::Result<()> {
+    let not_found = |ssid: &str| zbus::Error::Failure(format!("no access point {}", ssid));
+    match command {
+        ScenarioCommand::AddAccessPoint {
+            ssid,
+            strength,
+            security,
+            password,
+        } => {
+            let id = {
+                let mut state = shared.lock().unwrap();
+                let id = state.next_id();
+                state.access_points.push(MockAccessPoint {
+                    id,
+                    ssid,
+                    strength,
+                    security,
+                    password,
+                });
+                id
+            };
+            conn.object_server()
+                .at(
+                    access_point_path(id),
+                    MockAccessPointObject {
+                        state: shared.clone(),
+                        id,
+                    },
+                )
+                .await?;
+        }
+        ScenarioCommand::RemoveAccessPoint(ssid) => {
+            let (id, was_active) = {
+                let mut state = shared.lock().unwrap();
+                let index = state
+                    .access_points
+                    .iter()
+                    .position(|ap| ap.ssid == ssid)
+                    .ok_or_else(|| not_found(&ssid))?;
+                let id = state.access_points.remove(index).id;
+                let was_active = state
+                    .active
+                    .as_ref()
+                    .is_some_and(|active| active.access_point == id);
+                (id, was_active)
+            };
+            if was_active {
+                deactivate(conn, shared, NM_ACTIVE_CONNECTION_STATE_REASON_NONE).await?;
+            }
+            conn.object_server()
+                .remove::<MockAccessPointObject, _>(access_point_path(id))
+                .await?;
+        }
+        ScenarioCommand::Strength { ssid, strength } => {
+            let id = {
+                let mut state = shared.lock().unwrap();
+                let ap = state
+                    .access_points
+                    .iter_mut()
+                    .find(|ap| ap.ssid == ssid)
+                    .ok_or_else(|| not_found(&ssid))?;
+                ap.strength = strength;
+                ap.id
+            };
+            let iface = conn
+                .object_server()
+                .interface::<_, MockAccessPointObject>(access_point_path(id))
+                .await?;
+            iface
+                .get()
+                .await
+                .strength_changed(iface.signal_context())
+                .await?;
+        }
+        ScenarioCommand::SaveProfile(ssid) => {
+            save_profile(conn, shared, &ssid).await?;
+        }
+        ScenarioCommand::Wireless(enabled) => {
+            shared.lock().unwrap().wireless_enabled = enabled;
+            if !enabled {
+                deactivate(conn, shared, NM_ACTIVE_CONNECTION_STATE_REASON_NONE).await?;
+            }
+            network_changed(conn, shared).await?;
+        }
+        ScenarioCommand::Connect(ssid) => {
+            let profile = {
+                let state = shared.lock().unwrap();
+                state
+                    .profiles
+                    .iter()
+                    .find(|(_, settings)| {
+                        settings_ssid(settings).as_deref() == Some(ssid.as_bytes())
+                    })
+                    .map(|(id, _)| *id)
+            };
+            let profile = match profile {
+                Some(profile) => profile,
+                None => save_profile(conn, shared, &ssid).await?,
+            };
+            activate(conn, shared, profile).await?;
+        }
+        ScenarioCommand::Disconnect => {
+            deactivate(
+                conn,
+                shared,
+                NM_ACTIVE_CONNECTION_STATE_REASON_USER_DISCONNECTED,
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Claims the NetworkManager name on `bus`, applies the scenario's setup and
+/// then runs its steps. Never returns unless the bus connection fails.
+pub async fn serve(bus: Bus, scenario: Scenario) -> zbus::Result<()> {
+    serve_on(bus.builder()?.name(SERVICE_NAME)?, scenario).await
+}
+
+/// [`serve`] on a connection still to be built, e.g. a peer-to-peer one.
+async fn serve_on(builder: connection::Builder<'_>, scenario: Scenario) -> zbus::Result<()> {
+    let shared: Shared = Arc::new(Mutex::new(MockState::new()));
+    let (commands, receiver) = channel::unbounded();
+
+    let conn = builder
+        .serve_at(
+            NM_PATH,
+            MockNetworkManager {
+                state: shared.clone(),
+                commands: commands.clone(),
+            },
+        )?
+        .serve_at(
+            DEVICE_PATH,
+            MockDevice {
+                state: shared.clone(),
+            },
+        )?
+        .serve_at(
+            DEVICE_PATH,
+            MockWireless {
+                state: shared.clone(),
+            },
+        )?
+        .serve_at(
+            SETTINGS_PATH,
+            MockSettings {
+                state: shared.clone(),
+            },
+        )?
+        .build()
+        .await?;
+
+    for command in scenario.setup {
+        apply(&conn, &shared, command).await?;
+    }
+
+    // Timed steps go through the same queue as property writes from clients
+    let steps = scenario.steps;
+    async_std::task::spawn(async move {
+        let started = Instant::now();
+        for (at, command) in steps {
+            if let Some(wait) = at.checked_sub(started.elapsed()) {
+                async_std::task::sleep(wait).await;
+            }
+            if commands.send(command).await.is_err() {
+                return;
+            }
+        }
+    });
+
+    while let Ok(command) = receiver.recv().await {
+        let description = format!("{:?}", command);
+        info!("Applying {}", description);
+        if let Err(e) = apply(&conn, &shared, command).await {
+            error!("Error applying {}: {}", description, e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::net::UnixStream;
+
+    use bevy::{
+        input::InputPlugin,
+        prelude::{App, EventReader, MinimalPlugins, ResMut, Resource, Update, World},
+    };
+    use zbus::Guid;
+
+    use super::*;
+    use crate::network_manager::{
+        AccessPointList, ConnectionProgressEvent, NetworkAction, NetworkActionEvent,
+        NetworkManagerBus, NetworkManagerServicePlugin, access_points::ConnectionProgress,
+        state::NetworkState,
+    };
+    use crate::testing::update_until;
+
+    #[test]
+    fn parses_the_default_scenario() {
+        let scenario = Scenario::parse(DEFAULT_SCENARIO).unwrap();
+        assert_eq!(scenario.setup.len(), 6);
+        assert_eq!(
+            scenario.setup[0],
+            ScenarioCommand::AddAccessPoint {
+                ssid: "HomeWifi".to_string(),
+                strength: 82,
+                security: Security::Wpa2,
+                password: Some("hunter22".to_string()),
+            }
+        );
+        assert_eq!(scenario.steps.len(), 2);
+        assert_eq!(scenario.steps[0].0, Duration::from_secs(20));
+    }
+
+    #[test]
+    fn sorts_steps_and_skips_comments() {
+        let scenario = Scenario::parse(
+            "# comment only\n\
+             \n\
+             at 5 disconnect # trailing comment\n\
+             at 0.5 wireless off\n\
+             ap Open 10 open\n",
+        )
+        .unwrap();
+        assert_eq!(
+            scenario.steps,
+            vec![
+                (Duration::from_millis(500), ScenarioCommand::Wireless(false)),
+                (Duration::from_secs(5), ScenarioCommand::Disconnect),
+            ]
+        );
+        assert_eq!(scenario.setup.len(), 1);
+    }
+
+    #[test]
+    fn rejects_invalid_lines() {
+        for line in [
+            "at -1 disconnect",
+            "at NaN disconnect",
+            "at 1e30 disconnect",
+            "at soon disconnect",
+            "at 1 teleport",
+            "ap Home 101 open",
+            "ap Home 50 wpa4",
+            "ap Home 50 wpa2 two words",
+            "wireless maybe",
+            "remove",
+        ] {
+            let text = format!("ap Fine 50 open\n{}\n", line);
+            let error = Scenario::parse(&text).unwrap_err();
+            assert!(error.starts_with("line 2:"), "{:?}: {}", line, error);
+        }
+    }
+
+    #[derive(Resource, Default)]
+    struct Progress(Vec<ConnectionProgress>);
+
+    fn record_progress(
+        mut events: EventReader<ConnectionProgressEvent>,
+        mut progress: ResMut<Progress>,
+    ) {
+        progress
+            .0
+            .extend(events.read().map(|event| event.progress.clone()));
+    }
+
+    fn act(app: &mut App, action: NetworkAction) {
+        app.world_mut().send_event(NetworkActionEvent(action));
+    }
+
+    fn last_progress(world: &World) -> Option<&ConnectionProgress> {
+        world.resource::<Progress>().0.last()
+    }
+
+    #[test]
+    fn plugin_scans_and_connects_through_the_mock() {
+        let scenario =
+            Scenario::parse("ap HomeWifi 82 wpa2 hunter22\nap CafeFree 60 open\n").unwrap();
+        // Peer-to-peer, so the test needs no bus daemon
+        let (server, client) = UnixStream::pair().unwrap();
+        let server = connection::Builder::unix_stream(server)
+            .server(Guid::generate())
+            .unwrap()
+            .p2p();
+        std::thread::spawn(move || async_std::task::block_on(serve_on(server, scenario)));
+        let connection =
+            async_std::task::block_on(connection::Builder::unix_stream(client).p2p().build())
+                .unwrap();
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, InputPlugin))
+            .insert_resource(NetworkManagerBus {
+                connection: Some(connection),
+            })
+            .add_plugins(NetworkManagerServicePlugin)
+            .init_resource::<Progress>()
+            .add_systems(Update, record_progress);
+        act(&mut app, NetworkAction::ScanAccessPoints);
+        assert!(update_until(&mut app, |world| {
+            !world.resource::<AccessPointList>().0.is_empty()
+        }));
+        let ssids: Vec<String> = app
+            .world()
+            .resource::<AccessPointList>()
+            .0
+            .iter()
+            .map(|ap| ap.ssid.clone())
+            .collect();
+        assert_eq!(ssids, ["HomeWifi", "CafeFree"]);
+
+        act(
+            &mut app,
+            NetworkAction::SwitchNetwork("HomeWifi".to_string()),
+        );
+        assert!(update_until(&mut app, |world| {
+            last_progress(world) == Some(&ConnectionProgress::PasswordRequired(Security::Wpa2))
+        }));
+
+        act(
+            &mut app,
+            NetworkAction::ConnectWithPassword {
+                ssid: "HomeWifi".to_string(),
+                password: "wrong-password".to_string(),
+            },
+        );
+        assert!(update_until(&mut app, |world| {
+            last_progress(world) == Some(&ConnectionProgress::WrongPassword(Security::Wpa2))
+        }));
+
+        act(
+            &mut app,
+            NetworkAction::ConnectWithPassword {
+                ssid: "HomeWifi".to_string(),
+                password: "hunter22".to_string(),
+            },
+        );
+        assert!(update_until(&mut app, |world| {
+            last_progress(world) == Some(&ConnectionProgress::Connected)
+        }));
+        assert!(update_until(&mut app, |world| {
+            world.resource::<NetworkState>().active_ssid.as_deref() == Some("HomeWifi")
+        }));
+    }
+}
@@ -0,0 +1,214 @@
+//! Subset of the `org.freedesktop.NetworkManager` D-Bus API used by the plugin.
+
+use std::collections::HashMap;
+
+use zbus::{
+    Result as ZbusResult, proxy,
+    zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value},
+};
+
+pub const NM_DEVICE_TYPE_ETHERNET: u32 = 1;
+pub const NM_DEVICE_TYPE_WIFI: u32 = 2;
+pub const NM_DEVICE_TYPE_MODEM: u32 = 8;
+
+pub const NM_STATE_DISCONNECTED: u32 = 20;
+pub const NM_STATE_CONNECTED_GLOBAL: u32 = 70;
+
+pub const NM_DEVICE_STATE_UNAVAILABLE: u32 = 20;
+pub const NM_DEVICE_STATE_DISCONNECTED: u32 = 30;
+pub const NM_DEVICE_STATE_ACTIVATED: u32 = 100;
+
+pub const NM_CONNECTIVITY_NONE: u32 = 1;
+pub const NM_CONNECTIVITY_PORTAL: u32 = 2;
+pub const NM_CONNECTIVITY_LIMITED: u32 = 3;
+pub const NM_CONNECTIVITY_FULL: u32 = 4;
+
+pub const NM_ACTIVE_CONNECTION_STATE_ACTIVATING: u32 = 1;
+pub const NM_ACTIVE_CONNECTION_STATE_ACTIVATED: u32 = 2;
+pub const NM_ACTIVE_CONNECTION_STATE_DEACTIVATED: u32 = 4;
+
+pub const NM_ACTIVE_CONNECTION_STATE_REASON_NONE: u32 = 1;
+pub const NM_ACTIVE_CONNECTION_STATE_REASON_USER_DISCONNECTED: u32 = 2;
+pub const NM_ACTIVE_CONNECTION_STATE_REASON_NO_SECRETS: u32 = 9;
+pub const NM_ACTIVE_CONNECTION_STATE_REASON_LOGIN_FAILED: u32 = 10;
+
+pub const NM_802_11_AP_FLAGS_PRIVACY: u32 = 0x1;
+pub const NM_802_11_AP_SEC_KEY_MGMT_PSK: u32 = 0x100;
+pub const NM_802_11_AP_SEC_KEY_MGMT_802_1X: u32 = 0x200;
+pub const NM_802_11_AP_SEC_KEY_MGMT_SAE: u32 = 0x400;
+
+/// Connection settings as exchanged with NetworkManager (`a{sa{sv}}`).
+pub type ConnectionSettings = HashMap<String, HashMap<String, OwnedValue>>;
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager"
+)]
+pub trait NetworkManager {
+    fn get_devices(&self) -> ZbusResult<Vec<OwnedObjectPath>>;
+
+    fn activate_connection(
+        &self,
+        connection: &ObjectPath<'_>,
+        device: &ObjectPath<'_>,
+        specific_object: &ObjectPath<'_>,
+    ) -> ZbusResult<OwnedObjectPath>;
+
+    fn add_and_activate_connection(
+        &self,
+        connection: HashMap<&str, HashMap<&str, Value<'_>>>,
+        device: &ObjectPath<'_>,
+        specific_object: &ObjectPath<'_>,
+    ) -> ZbusResult<(OwnedObjectPath, OwnedObjectPath)>;
+
+    #[zbus(property)]
+    fn wireless_enabled(&self) -> ZbusResult<bool>;
+
+    #[zbus(property)]
+    fn set_wireless_enabled(&self, enabled: bool) -> ZbusResult<()>;
+
+    fn deactivate_connection(&self, active_connection: &ObjectPath<'_>) -> ZbusResult<()>;
+
+    #[zbus(property)]
+    fn wwan_enabled(&self) -> ZbusResult<bool>;
+
+    #[zbus(property)]
+    fn set_wwan_enabled(&self, enabled: bool) -> ZbusResult<()>;
+
+    #[zbus(property)]
+    fn wwan_hardware_enabled(&self) -> ZbusResult<bool>;
+
+    #[zbus(property)]
+    fn active_connections(&self) -> ZbusResult<Vec<OwnedObjectPath>>;
+
+    #[zbus(property)]
+    fn primary_connection(&self) -> ZbusResult<OwnedObjectPath>;
+
+    #[zbus(property)]
+    fn connectivity(&self) -> ZbusResult<u32>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Device",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait Device {
+    #[zbus(property)]
+    fn device_type(&self) -> ZbusResult<u32>;
+
+    #[zbus(property)]
+    fn interface(&self) -> ZbusResult<String>;
+
+    #[zbus(property)]
+    fn state(&self) -> ZbusResult<u32>;
+
+    #[zbus(property)]
+    fn active_connection(&self) -> ZbusResult<OwnedObjectPath>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Device.Wired",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait Wired {
+    #[zbus(property)]
+    fn carrier(&self) -> ZbusResult<bool>;
+
+    /// Link speed in Mb/s.
+    #[zbus(property)]
+    fn speed(&self) -> ZbusResult<u32>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Device.Wireless",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait Wireless {
+    fn get_all_access_points(&self) -> ZbusResult<Vec<OwnedObjectPath>>;
+
+    fn request_scan(&self, options: HashMap<&str, Value<'_>>) -> ZbusResult<()>;
+
+    #[zbus(property)]
+    fn last_scan(&self) -> ZbusResult<i64>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.AccessPoint",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait AccessPoint {
+    #[zbus(property)]
+    fn ssid(&self) -> ZbusResult<Vec<u8>>;
+
+    #[zbus(property)]
+    fn strength(&self) -> ZbusResult<u8>;
+
+    #[zbus(property)]
+    fn flags(&self) -> ZbusResult<u32>;
+
+    #[zbus(property)]
+    fn wpa_flags(&self) -> ZbusResult<u32>;
+
+    #[zbus(property)]
+    fn rsn_flags(&self) -> ZbusResult<u32>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Settings",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager/Settings"
+)]
+pub trait Settings {
+    fn list_connections(&self) -> ZbusResult<Vec<OwnedObjectPath>>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Settings.Connection",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait SettingsConnection {
+    fn get_settings(&self) -> ZbusResult<ConnectionSettings>;
+
+    fn delete(&self) -> ZbusResult<()>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Connection.Active",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait ActiveConnection {
+    #[zbus(property)]
+    fn id(&self) -> ZbusResult<String>;
+
+    #[zbus(property, name = "Type")]
+    fn connection_type(&self) -> ZbusResult<String>;
+
+    #[zbus(property)]
+    fn state(&self) -> ZbusResult<u32>;
+
+    #[zbus(property)]
+    fn specific_object(&self) -> ZbusResult<OwnedObjectPath>;
+
+    #[zbus(property)]
+    fn ip4_config(&self) -> ZbusResult<OwnedObjectPath>;
+
+    #[zbus(property)]
+    fn connection(&self) -> ZbusResult<OwnedObjectPath>;
+
+    #[zbus(property)]
+    fn vpn(&self) -> ZbusResult<bool>;
+
+    // `receive_state_changed` is taken by the stream of the `State` property
+    #[zbus(signal, name = "StateChanged")]
+    fn activation_state_changed(&self, state: u32, reason: u32) -> ZbusResult<()>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.IP4Config",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait Ip4Config {
+    #[zbus(property)]
+    fn address_data(&self) -> ZbusResult<Vec<HashMap<String, OwnedValue>>>;
+}
@@ -0,0 +1,297 @@
+//! Current network status read from NetworkManager and kept up to date.
+
+use std::fmt;
+
+use bevy::{
+    prelude::*,
+    tasks::futures_lite::{StreamExt, future},
+};
+use tokio::sync::watch;
+use zbus::{
+    Connection,
+    fdo::PropertiesProxy,
+    zvariant::{OwnedObjectPath, Value},
+};
+
+use super::proxies::{
+    AccessPointProxy, ActiveConnectionProxy, Ip4ConfigProxy, NM_CONNECTIVITY_FULL,
+    NM_CONNECTIVITY_LIMITED, NM_CONNECTIVITY_NONE, NM_CONNECTIVITY_PORTAL, NetworkManagerProxy,
+};
+
+const NM_SERVICE: &str = "org.freedesktop.NetworkManager";
+const NM_PATH: &str = "/org/freedesktop/NetworkManager";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Connectivity {
+    #[default]
+    Unknown,
+    None,
+    /// Behind a captive portal.
+    Portal,
+    /// Connected, but the internet is not reachable.
+    Limited,
+    Full,
+}
+
+impl From<u32> for Connectivity {
+    fn from(value: u32) -> Self {
+        match value {
+            NM_CONNECTIVITY_NONE => Connectivity::None,
+            NM_CONNECTIVITY_PORTAL => Connectivity::Portal,
+            NM_CONNECTIVITY_LIMITED => Connectivity::Limited,
+            NM_CONNECTIVITY_FULL => Connectivity::Full,
+            _ => Connectivity::Unknown,
+        }
+    }
+}
+
+/// What NetworkManager currently reports, updated by the plugin as it changes.
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct NetworkState {
+    pub wireless_enabled: bool,
+    /// SSID of the primary connection when it is a Wi-Fi one.
+    pub active_ssid: Option<String>,
+    pub ip_address: Option<String>,
+    /// Signal strength of the connected access point, in percent.
+    pub strength: Option<u8>,
+    pub connectivity: Connectivity,
+}
+
+impl NetworkState {
+    /// Number of signal bars to light, out of 4.
+    pub fn signal_bars(&self) -> usize {
+        match self.strength {
+            Some(strength) => (1 + usize::from(strength) / 25).min(4),
+            None => 0,
+        }
+    }
+}
+
+impl fmt::Display for NetworkState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(ssid) = &self.active_ssid else {
+            return if self.wireless_enabled {
+                write!(f, "Disconnected")
+            } else {
+                write!(f, "Wi-Fi off")
+            };
+        };
+        write!(f, "{}", ssid)?;
+        if let Some(strength) = self.strength {
+            write!(f, " {}%", strength)?;
+        }
+        if let Some(ip_address) = &self.ip_address {
+            write!(f, " {}", ip_address)?;
+        }
+        match self.connectivity {
+            Connectivity::Portal => write!(f, " (login required)"),
+            Connectivity::Limited | Connectivity::None => write!(f, " (no internet)"),
+            Connectivity::Unknown | Connectivity::Full => Ok(()),
+        }
+    }
+}
+
+pub async fn read_network_state(connection: &Connection) -> zbus::Result<NetworkState> {
+    Ok(read_state(connection).await?.0)
+}
+
+/// Reads the state along with the access point it depends on, if any.
+async fn read_state(
+    connection: &Connection,
+) -> zbus::Result<(NetworkState, Option<OwnedObjectPath>)> {
+    let nm = NetworkManagerProxy::new(connection).await?;
+    let mut state = NetworkState {
+        wireless_enabled: nm.wireless_enabled().await?,
+        connectivity: Connectivity::from(nm.connectivity().await?),
+        ..default()
+    };
+    let primary = nm.primary_connection().await?;
+    if primary.as_str() == "/" {
+        return Ok((state, None));
+    }
+
+    let active = ActiveConnectionProxy::builder(connection)
+        .path(primary)?
+        .build()
+        .await?;
+    let mut access_point = None;
+    if active.connection_type().await? == "802-11-wireless" {
+        let path = active.specific_object().await?;
+        if path.as_str() != "/" {
+            let proxy = AccessPointProxy::builder(connection)
+                .path(path.clone())?
+                .build()
+                .await?;
+            state.active_ssid = Some(String::from_utf8_lossy(&proxy.ssid().await?).into_owned());
+            state.strength = Some(proxy.strength().await?);
+            access_point = Some(path);
+        }
+    }
+    let ip4_config = active.ip4_config().await?;
+    if ip4_config.as_str() != "/" {
+        let config = Ip4ConfigProxy::builder(connection)
+            .path(ip4_config)?
+            .build()
+            .await?;
+        state.ip_address = config.address_data().await?.iter().find_map(|address| {
+            match address.get("address").map(|value| &**value) {
+                Some(Value::Str(address)) => Some(address.to_string()),
+                _ => None,
+            }
+        });
+    }
+    Ok((state, access_point))
+}
+
+/// Sends the current state, then a new one whenever NetworkManager or the
+/// connected access point changes. Returns once `tx` has no receivers.
+pub async fn follow_network_state(
+    connection: &Connection,
+    tx: &watch::Sender<NetworkState>,
+) -> zbus::Result<()> {
+    let nm = PropertiesProxy::builder(connection)
+        .destination(NM_SERVICE)?
+        .path(NM_PATH)?
+        .build()
+        .await?;
+    let mut nm_changes = nm.receive_properties_changed().await?;
+
+    loop {
+        let access_point = match read_state(connection).await {
+            Ok((state, access_point)) => {
+                tx.send_if_modified(|current| {
+                    let modified = *current != state;
+                    *current = state;
+                    modified
+                });
+                access_point
+            }
+            // Objects come and go while connections change, the next
+            // change will bring a consistent state
+            Err(e) => {
+                error!("Error reading the network state: {}", e);
+                None
+            }
+        };
+        if tx.is_closed() {
+            return Ok(());
+        }
+
+        let mut ap_changes = match access_point {
+            Some(path) => Some(
+                PropertiesProxy::builder(connection)
+                    .destination(NM_SERVICE)?
+                    .path(path)?
+                    .build()
+                    .await?
+                    .receive_properties_changed()
+                    .await?,
+            ),
+            None => None,
+        };
+        let nm_changed = async { nm_changes.next().await.is_some() };
+        let ap_changed = async {
+            match ap_changes.as_mut() {
+                Some(changes) => {
+                    changes.next().await;
+                    true
+                }
+                None => future::pending().await,
+            }
+        };
+        if !future::or(nm_changed, ap_changed).await {
+            return Ok(());
+        }
+    }
+}
+
+#[derive(Resource)]
+pub(crate) struct NetworkStateReceiver(pub(crate) watch::Receiver<NetworkState>);
+
+pub(crate) fn sync_network_state(
+    receiver: Option<ResMut<NetworkStateReceiver>>,
+    mut state: ResMut<NetworkState>,
+) {
+    let Some(mut receiver) = receiver else {
+        return;
+    };
+    if receiver.0.has_changed().unwrap_or(false) {
+        *state = receiver.0.borrow_and_update().clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connected(connectivity: Connectivity) -> NetworkState {
+        NetworkState {
+            wireless_enabled: true,
+            active_ssid: Some("HomeWifi".to_string()),
+            ip_address: Some("192.168.1.20".to_string()),
+            strength: Some(82),
+            connectivity,
+        }
+    }
+
+    #[test]
+    fn display_without_connection() {
+        let off = NetworkState::default();
+        assert_eq!(off.to_string(), "Wi-Fi off");
+        let on = NetworkState {
+            wireless_enabled: true,
+            ..default()
+        };
+        assert_eq!(on.to_string(), "Disconnected");
+    }
+
+    #[test]
+    fn display_connected() {
+        assert_eq!(
+            connected(Connectivity::Full).to_string(),
+            "HomeWifi 82% 192.168.1.20"
+        );
+        assert_eq!(
+            connected(Connectivity::Portal).to_string(),
+            "HomeWifi 82% 192.168.1.20 (login required)"
+        );
+        assert_eq!(
+            connected(Connectivity::Limited).to_string(),
+            "HomeWifi 82% 192.168.1.20 (no internet)"
+        );
+        let bare = NetworkState {
+            strength: None,
+            ip_address: None,
+            ..connected(Connectivity::Unknown)
+        };
+        assert_eq!(bare.to_string(), "HomeWifi");
+    }
+
+    #[test]
+    fn signal_bars_per_strength() {
+        let bars = |strength| {
+            NetworkState {
+                strength,
+                ..default()
+            }
+            .signal_bars()
+        };
+        assert_eq!(bars(None), 0);
+        assert_eq!(bars(Some(0)), 1);
+        assert_eq!(bars(Some(24)), 1);
+        assert_eq!(bars(Some(25)), 2);
+        assert_eq!(bars(Some(74)), 3);
+        assert_eq!(bars(Some(75)), 4);
+        assert_eq!(bars(Some(100)), 4);
+    }
+
+    #[test]
+    fn connectivity_from_nm_values() {
+        assert_eq!(Connectivity::from(NM_CONNECTIVITY_FULL), Connectivity::Full);
+        assert_eq!(
+            Connectivity::from(NM_CONNECTIVITY_PORTAL),
+            Connectivity::Portal
+        );
+        assert_eq!(Connectivity::from(0), Connectivity::Unknown);
+    }
+}
@@ -0,0 +1,667 @@
+//! Ready-made Bevy UI for the NetworkManager plugin.
+
+use bevy::{
+    input::{
+        ButtonState,
+        keyboard::{Key, KeyboardInput},
+    },
+    prelude::*,
+};
+
+use super::{
+    AccessPointList, ConnectionProgressEvent, NetworkAction, NetworkActionEvent, WifiStatusText,
+    access_points::{ConnectionProgress, Security, validate_password},
+    connections::{VpnState, WiredState, WwanState},
+    state::{Connectivity, NetworkState},
+};
+
+const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
+const HOVERED_BUTTON: Color = Color::srgb(0.25, 0.25, 0.25);
+const ERROR_TEXT: Color = Color::srgb(0.95, 0.4, 0.4);
+const SIGNAL_BAR_OFF: Color = Color::srgb(0.3, 0.3, 0.3);
+
+/// Column the access point rows are spawned into. Holds the font used for them.
+#[derive(Component)]
+pub struct AccessPointListNode {
+    font: Handle<Font>,
+}
+
+#[derive(Component)]
+pub struct ScanButton;
+
+#[derive(Component)]
+pub struct AccessPointButton {
+    pub ssid: String,
+}
+
+#[derive(Component)]
+pub struct ConnectionStatusText;
+
+/// Spawns a panel with a scan button, the list of networks found by the last
+/// scan and the progress of the current connection attempt. Pressing a network
+/// connects to it.
+pub fn spawn_access_point_list(commands: &mut Commands, font: Handle<Font>, node: Node) -> Entity {
+    let text_font = TextFont {
+        font: font.clone(),
+        font_size: 20.0,
+        ..default()
+    };
+    commands
+        .spawn((
+            Node {
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(6.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                ..node
+            },
+            BackgroundColor(Color::srgb(0.08, 0.08, 0.08)),
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Button,
+                    Node {
+                        padding: UiRect::axes(Val::Px(12.0), Val::Px(6.0)),
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    BorderRadius::MAX,
+                    BackgroundColor(NORMAL_BUTTON),
+                    ScanButton,
+                ))
+                .with_child((
+                    Text::new("Scan"),
+                    text_font.clone(),
+                    TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                ));
+            parent.spawn((
+                Text::new(""),
+                text_font.clone(),
+                TextColor(Color::srgb(0.7, 0.7, 0.7)),
+                ConnectionStatusText,
+            ));
+            parent.spawn((
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.0),
+                    ..default()
+                },
+                AccessPointListNode { font },
+            ));
+        })
+        .id()
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn access_point_button_system(
+    query: Query<
+        (
+            &Interaction,
+            Option<&ScanButton>,
+            Option<&AccessPointButton>,
+        ),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut colors: Query<
+        (&Interaction, &mut BackgroundColor),
+        (
+            Changed<Interaction>,
+            Or<(With<ScanButton>, With<AccessPointButton>)>,
+        ),
+    >,
+    mut writer: EventWriter<NetworkActionEvent>,
+) {
+    for (interaction, scan, access_point) in query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if scan.is_some() {
+            writer.write(NetworkActionEvent(NetworkAction::ScanAccessPoints));
+        } else if let Some(access_point) = access_point {
+            writer.write(NetworkActionEvent(NetworkAction::SwitchNetwork(
+                access_point.ssid.clone(),
+            )));
+        }
+    }
+    for (interaction, mut color) in colors.iter_mut() {
+        color.0 = match interaction {
+            Interaction::Hovered | Interaction::Pressed => HOVERED_BUTTON,
+            Interaction::None => NORMAL_BUTTON,
+        };
+    }
+}
+
+pub(crate) fn update_access_point_list(
+    mut commands: Commands,
+    access_points: Res<AccessPointList>,
+    lists: Query<(Entity, &AccessPointListNode)>,
+) {
+    if !access_points.is_changed() {
+        return;
+    }
+    for (entity, list) in lists.iter() {
+        commands
+            .entity(entity)
+            .despawn_related::<Children>()
+            .with_children(|parent| {
+                for ap in access_points.0.iter() {
+                    parent
+                        .spawn((
+                            Button,
+                            Node {
+                                padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+                                justify_content: JustifyContent::SpaceBetween,
+                                column_gap: Val::Px(16.0),
+                                ..default()
+                            },
+                            BackgroundColor(NORMAL_BUTTON),
+                            AccessPointButton {
+                                ssid: ap.ssid.clone(),
+                            },
+                        ))
+                        .with_child((
+                            Text::new(format!(
+                                "{}   {}%   {}",
+                                ap.ssid,
+                                ap.strength,
+                                ap.security.label()
+                            )),
+                            TextFont {
+                                font: list.font.clone(),
+                                font_size: 18.0,
+                                ..default()
+                            },
+                            TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                        ));
+                }
+            });
+    }
+}
+
+pub(crate) fn update_connection_status_text(
+    mut events: EventReader<ConnectionProgressEvent>,
+    mut query: Query<&mut Text, With<ConnectionStatusText>>,
+) {
+    for event in events.read() {
+        let status = match &event.progress {
+            ConnectionProgress::PasswordRequired(_) => {
+                format!("Enter the password for {}", event.ssid)
+            }
+            ConnectionProgress::Connecting => format!("Connecting to {}...", event.ssid),
+            ConnectionProgress::Connected => format!("Connected to {}", event.ssid),
+            ConnectionProgress::WrongPassword(_) => format!("Wrong password for {}", event.ssid),
+            ConnectionProgress::Failed(reason) => {
+                format!("Could not connect to {}: {}", event.ssid, reason)
+            }
+        };
+        for mut text in query.iter_mut() {
+            text.0 = status.clone();
+        }
+    }
+}
+
+/// Passphrase prompt for secured networks, opened when a connection attempt
+/// reports `PasswordRequired` or `WrongPassword`.
+#[derive(Resource, Default)]
+pub struct PasswordDialog {
+    prompt: Option<PasswordPrompt>,
+    font: Handle<Font>,
+}
+
+struct PasswordPrompt {
+    ssid: String,
+    security: Security,
+    input: String,
+    show_password: bool,
+    error: Option<String>,
+}
+
+impl PasswordDialog {
+    pub fn is_open(&self) -> bool {
+        self.prompt.is_some()
+    }
+
+    fn submit(&mut self, writer: &mut EventWriter<NetworkActionEvent>) {
+        let Some(prompt) = &mut self.prompt else {
+            return;
+        };
+        if let Err(e) = validate_password(prompt.security, &prompt.input) {
+            prompt.error = Some(e);
+            return;
+        }
+        writer.write(NetworkActionEvent(NetworkAction::ConnectWithPassword {
+            ssid: prompt.ssid.clone(),
+            password: std::mem::take(&mut prompt.input),
+        }));
+        self.prompt = None;
+    }
+}
+
+#[derive(Component)]
+pub(crate) struct PasswordDialogNode;
+
+#[derive(Component, Clone, Copy)]
+pub(crate) enum PasswordDialogButton {
+    ToggleShow,
+    Cancel,
+    Connect,
+}
+
+pub(crate) fn open_password_dialog(
+    mut events: EventReader<ConnectionProgressEvent>,
+    mut dialog: ResMut<PasswordDialog>,
+    lists: Query<&AccessPointListNode>,
+) {
+    for event in events.read() {
+        let (security, error) = match &event.progress {
+            ConnectionProgress::PasswordRequired(security) => (*security, None),
+            ConnectionProgress::WrongPassword(security) => {
+                (*security, Some("Wrong password, try again".to_string()))
+            }
+            _ => continue,
+        };
+        if let Some(list) = lists.iter().next() {
+            dialog.font = list.font.clone();
+        }
+        dialog.prompt = Some(PasswordPrompt {
+            ssid: event.ssid.clone(),
+            security,
+            input: String::new(),
+            show_password: false,
+            error,
+        });
+    }
+}
+
+pub(crate) fn password_dialog_input(
+    mut keys: EventReader<KeyboardInput>,
+    mut dialog: ResMut<PasswordDialog>,
+    mut writer: EventWriter<NetworkActionEvent>,
+) {
+    if !dialog.is_open() {
+        keys.clear();
+        return;
+    }
+    for key in keys.read() {
+        if key.state != ButtonState::Pressed {
+            continue;
+        }
+        let Some(prompt) = dialog.prompt.as_mut() else {
+            return;
+        };
+        match &key.logical_key {
+            Key::Character(chars) => {
+                prompt
+                    .input
+                    .extend(chars.chars().filter(|c| !c.is_control()));
+                prompt.error = None;
+            }
+            Key::Space => prompt.input.push(' '),
+            Key::Backspace => {
+                prompt.input.pop();
+            }
+            Key::Enter => dialog.submit(&mut writer),
+            Key::Escape => dialog.prompt = None,
+            _ => {}
+        }
+    }
+}
+
+pub(crate) fn password_dialog_buttons(
+    query: Query<(&Interaction, &PasswordDialogButton), Changed<Interaction>>,
+    mut dialog: ResMut<PasswordDialog>,
+    mut writer: EventWriter<NetworkActionEvent>,
+) {
+    for (interaction, button) in query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            PasswordDialogButton::ToggleShow => {
+                if let Some(prompt) = dialog.prompt.as_mut() {
+                    prompt.show_password = !prompt.show_password;
+                }
+            }
+            PasswordDialogButton::Cancel => dialog.prompt = None,
+            PasswordDialogButton::Connect => dialog.submit(&mut writer),
+        }
+    }
+}
+
+/// Rebuilds the dialog whenever its state changes.
+pub(crate) fn render_password_dialog(
+    mut commands: Commands,
+    dialog: Res<PasswordDialog>,
+    nodes: Query<Entity, With<PasswordDialogNode>>,
+) {
+    if !dialog.is_changed() {
+        return;
+    }
+    for entity in nodes.iter() {
+        commands.entity(entity).despawn();
+    }
+    let Some(prompt) = &dialog.prompt else {
+        return;
+    };
+
+    let text_font = TextFont {
+        font: dialog.font.clone(),
+        font_size: 20.0,
+        ..default()
+    };
+    let shown = if prompt.show_password {
+        prompt.input.clone()
+    } else {
+        "\u{2022}".repeat(prompt.input.chars().count())
+    };
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+            GlobalZIndex(10),
+            PasswordDialogNode,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(10.0),
+                        padding: UiRect::all(Val::Px(16.0)),
+                        min_width: Val::Px(320.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.12, 0.12, 0.12)),
+                ))
+                .with_children(|panel| {
+                    panel.spawn((
+                        Text::new(format!(
+                            "Password for {} ({})",
+                            prompt.ssid,
+                            prompt.security.label()
+                        )),
+                        text_font.clone(),
+                        TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                    ));
+                    panel
+                        .spawn((
+                            Node {
+                                padding: UiRect::all(Val::Px(6.0)),
+                                min_height: Val::Px(32.0),
+                                ..default()
+                            },
+                            BackgroundColor(Color::srgb(0.05, 0.05, 0.05)),
+                        ))
+                        .with_child((
+                            Text::new(format!("{}|", shown)),
+                            text_font.clone(),
+                            TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                        ));
+                    if let Some(error) = &prompt.error {
+                        panel.spawn((
+                            Text::new(error.clone()),
+                            text_font.clone(),
+                            TextColor(ERROR_TEXT),
+                        ));
+                    }
+                    panel
+                        .spawn(Node {
+                            column_gap: Val::Px(8.0),
+                            justify_content: JustifyContent::End,
+                            ..default()
+                        })
+                        .with_children(|row| {
+                            let toggle_label = if prompt.show_password { "Hide" } else { "Show" };
+                            for (label, button) in [
+                                (toggle_label, PasswordDialogButton::ToggleShow),
+                                ("Cancel", PasswordDialogButton::Cancel),
+                                ("Connect", PasswordDialogButton::Connect),
+                            ] {
+                                row.spawn((
+                                    Button,
+                                    Node {
+                                        padding: UiRect::axes(Val::Px(12.0), Val::Px(6.0)),
+                                        ..default()
+                                    },
+                                    BackgroundColor(NORMAL_BUTTON),
+                                    button,
+                                ))
+                                .with_child((
+                                    Text::new(label),
+                                    text_font.clone(),
+                                    TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                                ));
+                            }
+                        });
+                });
+        });
+}
+
+/// Signal strength icon driven by [`NetworkState`].
+#[derive(Component)]
+pub struct NetworkStatusIcon;
+
+#[derive(Component)]
+pub(crate) struct SignalBar(usize);
+
+/// Spawns four bars of growing height, lit according to the signal strength
+/// and colored by connectivity.
+pub fn spawn_network_status_icon(commands: &mut Commands, node: Node) -> Entity {
+    commands
+        .spawn((
+            Node {
+                width: Val::Px(32.0),
+                height: Val::Px(28.0),
+                align_items: AlignItems::End,
+                column_gap: Val::Px(3.0),
+                ..node
+            },
+            NetworkStatusIcon,
+        ))
+        .with_children(|parent| {
+            for i in 0..4 {
+                parent.spawn((
+                    Node {
+                        width: Val::Px(6.0),
+                        height: Val::Percent(25.0 * (i + 1) as f32),
+                        ..default()
+                    },
+                    BackgroundColor(SIGNAL_BAR_OFF),
+                    SignalBar(i),
+                ));
+            }
+        })
+        .id()
+}
+
+pub(crate) fn update_network_status_text(
+    state: Res<NetworkState>,
+    mut query: Query<&mut Text, With<WifiStatusText>>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    for mut text in query.iter_mut() {
+        text.0 = state.to_string();
+    }
+}
+
+pub(crate) fn update_network_status_icon(
+    state: Res<NetworkState>,
+    mut bars: Query<(&SignalBar, &mut BackgroundColor)>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    let lit = match state.connectivity {
+        Connectivity::Full | Connectivity::Unknown => Color::srgb(0.3, 0.85, 0.4),
+        Connectivity::Portal | Connectivity::Limited => Color::srgb(0.95, 0.8, 0.3),
+        Connectivity::None => ERROR_TEXT,
+    };
+    let lit_bars = if state.wireless_enabled {
+        state.signal_bars()
+    } else {
+        0
+    };
+    for (bar, mut color) in bars.iter_mut() {
+        color.0 = if bar.0 < lit_bars {
+            lit
+        } else {
+            SIGNAL_BAR_OFF
+        };
+    }
+}
+
+/// Column listing wired devices, VPN profiles and the modem. Holds the font
+/// used for its rows.
+#[derive(Component)]
+pub struct ConnectionsPanel {
+    font: Handle<Font>,
+}
+
+/// What pressing a button of the connections panel does.
+#[derive(Component, Clone)]
+pub enum ConnectionsButton {
+    Vpn { name: String, active: bool },
+    Wwan { enabled: bool },
+}
+
+/// Spawns a panel showing the wired devices, with a toggle per VPN profile and
+/// one for mobile broadband when a modem is present.
+pub fn spawn_connections_panel(commands: &mut Commands, font: Handle<Font>, node: Node) -> Entity {
+    commands
+        .spawn((
+            Node {
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                ..node
+            },
+            BackgroundColor(Color::srgb(0.08, 0.08, 0.08)),
+            ConnectionsPanel { font },
+        ))
+        .id()
+}
+
+pub(crate) fn connections_button_system(
+    mut query: Query<
+        (&Interaction, &ConnectionsButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+    mut writer: EventWriter<NetworkActionEvent>,
+) {
+    for (interaction, button, mut color) in query.iter_mut() {
+        color.0 = match interaction {
+            Interaction::Hovered | Interaction::Pressed => HOVERED_BUTTON,
+            Interaction::None => NORMAL_BUTTON,
+        };
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let action = match button {
+            ConnectionsButton::Vpn { name, active: true } => {
+                NetworkAction::DeactivateVpn(name.clone())
+            }
+            ConnectionsButton::Vpn {
+                name,
+                active: false,
+            } => NetworkAction::ActivateVpn(name.clone()),
+            ConnectionsButton::Wwan { enabled } => NetworkAction::ToggleWwan(!enabled),
+        };
+        writer.write(NetworkActionEvent(action));
+    }
+}
+
+pub(crate) fn update_connections_panel(
+    mut commands: Commands,
+    wired: Res<WiredState>,
+    vpns: Res<VpnState>,
+    wwan: Res<WwanState>,
+    panels: Query<(Entity, &ConnectionsPanel)>,
+) {
+    if !(wired.is_changed() || vpns.is_changed() || wwan.is_changed()) {
+        return;
+    }
+    for (entity, panel) in panels.iter() {
+        let text_font = TextFont {
+            font: panel.font.clone(),
+            font_size: 18.0,
+            ..default()
+        };
+        commands
+            .entity(entity)
+            .despawn_related::<Children>()
+            .with_children(|parent| {
+                for device in wired.0.iter() {
+                    let status = if !device.carrier {
+                        "cable unplugged".to_string()
+                    } else if device.connected {
+                        format!("connected, {} Mb/s", device.speed_mbps)
+                    } else {
+                        "disconnected".to_string()
+                    };
+                    parent.spawn((
+                        Text::new(format!("{}: {}", device.interface, status)),
+                        text_font.clone(),
+                        TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                    ));
+                }
+
+                let mut buttons: Vec<(String, ConnectionsButton)> = vpns
+                    .0
+                    .iter()
+                    .map(|vpn| {
+                        let label = if vpn.active {
+                            format!("VPN {}: on", vpn.name)
+                        } else {
+                            format!("VPN {}: off", vpn.name)
+                        };
+                        let button = ConnectionsButton::Vpn {
+                            name: vpn.name.clone(),
+                            active: vpn.active,
+                        };
+                        (label, button)
+                    })
+                    .collect();
+                if wwan.present {
+                    let label = if !wwan.hardware_enabled {
+                        "Mobile broadband: disabled by switch"
+                    } else if wwan.enabled {
+                        "Mobile broadband: on"
+                    } else {
+                        "Mobile broadband: off"
+                    };
+                    buttons.push((
+                        label.to_string(),
+                        ConnectionsButton::Wwan {
+                            enabled: wwan.enabled,
+                        },
+                    ));
+                }
+                for (label, button) in buttons {
+                    parent
+                        .spawn((
+                            Button,
+                            Node {
+                                padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+                                ..default()
+                            },
+                            BackgroundColor(NORMAL_BUTTON),
+                            button,
+                        ))
+                        .with_child((
+                            Text::new(label),
+                            text_font.clone(),
+                            TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                        ));
+                }
+            });
+    }
+}
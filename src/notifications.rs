@@ -0,0 +1,489 @@
+pub mod proxies;
+pub mod server;
+pub mod ui;
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, mpsc::Sender},
+    time::Duration,
+};
+
+use bevy::{
+    prelude::*,
+    tasks::{
+        AsyncComputeTaskPool, IoTaskPool, Task, block_on,
+        futures_lite::{StreamExt, future},
+    },
+};
+use proxies::{
+    CLOSE_REASON_CLOSED, CLOSE_REASON_DISMISSED, CLOSE_REASON_EXPIRED, NotificationsProxy,
+};
+use zbus::{Connection, zvariant::Value};
+
+use crate::result_channel::ResultChannel;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Urgency {
+    Low,
+    #[default]
+    Normal,
+    /// Servers keep critical notifications until they are dismissed.
+    Critical,
+}
+
+impl From<u8> for Urgency {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Urgency::Low,
+            2 => Urgency::Critical,
+            _ => Urgency::Normal,
+        }
+    }
+}
+
+impl From<Urgency> for u8 {
+    fn from(urgency: Urgency) -> Self {
+        match urgency {
+            Urgency::Low => 0,
+            Urgency::Normal => 1,
+            Urgency::Critical => 2,
+        }
+    }
+}
+
+/// How long a notification stays on screen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Expiry {
+    /// Up to the server.
+    #[default]
+    Default,
+    Never,
+    After(Duration),
+}
+
+impl Expiry {
+    /// The `expire_timeout` argument of `Notify`.
+    pub fn to_millis(self) -> i32 {
+        match self {
+            Expiry::Default => -1,
+            Expiry::Never => 0,
+            Expiry::After(duration) => duration.as_millis().clamp(1, i32::MAX as u128) as i32,
+        }
+    }
+
+    pub fn from_millis(millis: i32) -> Self {
+        match millis {
+            0 => Expiry::Never,
+            millis if millis < 0 => Expiry::Default,
+            millis => Expiry::After(Duration::from_millis(millis as u64)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseReason {
+    Expired,
+    Dismissed,
+    /// Closed by a `CloseNotification` call.
+    Closed,
+    Undefined,
+}
+
+impl From<u32> for CloseReason {
+    fn from(value: u32) -> Self {
+        match value {
+            CLOSE_REASON_EXPIRED => CloseReason::Expired,
+            CLOSE_REASON_DISMISSED => CloseReason::Dismissed,
+            CLOSE_REASON_CLOSED => CloseReason::Closed,
+            _ => CloseReason::Undefined,
+        }
+    }
+}
+
+impl From<CloseReason> for u32 {
+    fn from(reason: CloseReason) -> Self {
+        match reason {
+            CloseReason::Expired => CLOSE_REASON_EXPIRED,
+            CloseReason::Dismissed => CLOSE_REASON_DISMISSED,
+            CloseReason::Closed => CLOSE_REASON_CLOSED,
+            CloseReason::Undefined => 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Notification {
+    pub app_name: String,
+    /// Id of a notification to update in place, 0 for a new one.
+    pub replaces_id: u32,
+    /// Icon name or `file://` URI.
+    pub icon: String,
+    pub summary: String,
+    pub body: String,
+    /// `(key, label)` pairs. The `default` key is invoked by clicking the
+    /// notification itself.
+    pub actions: Vec<(String, String)>,
+    pub urgency: Urgency,
+    pub expiry: Expiry,
+}
+
+impl Notification {
+    pub fn new(summary: impl Into<String>) -> Self {
+        Self {
+            summary: summary.into(),
+            ..default()
+        }
+    }
+
+    pub fn with_body(mut self, body: impl Into<String>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    pub fn with_action(mut self, key: impl Into<String>, label: impl Into<String>) -> Self {
+        self.actions.push((key.into(), label.into()));
+        self
+    }
+
+    pub fn with_urgency(mut self, urgency: Urgency) -> Self {
+        self.urgency = urgency;
+        self
+    }
+
+    pub fn with_expiry(mut self, expiry: Expiry) -> Self {
+        self.expiry = expiry;
+        self
+    }
+}
+
+/// Sends a notification to whatever server owns the name on the session bus.
+#[derive(Event, Debug, Clone)]
+pub struct SendNotification(pub Notification);
+
+/// Closes a notification sent earlier, by id.
+#[derive(Event, Debug, Clone)]
+pub struct CloseNotification(pub u32);
+
+/// Sent once the server accepted a notification, with the id it gave it.
+#[derive(Event, Debug, Clone)]
+pub struct NotificationSent {
+    pub id: u32,
+    pub summary: String,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct NotificationActionInvoked {
+    pub id: u32,
+    pub action_key: String,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct NotificationClosed {
+    pub id: u32,
+    pub reason: CloseReason,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct NotificationError(pub String);
+
+/// Session bus connection used to talk to the notification server.
+#[derive(Resource, Default)]
+pub struct NotificationsBus {
+    pub connection: Option<Connection>,
+}
+
+#[derive(Resource)]
+struct BusInitTask(Task<Option<Connection>>);
+
+/// Ids the server gave the notifications sent from here that haven't closed
+/// yet. The server signals actions and closing for every app's notifications,
+/// only these are reported.
+#[derive(Resource, Default)]
+struct SentIds(Arc<Mutex<HashSet<u32>>>);
+
+/// What the tasks talking to the server send back.
+enum NotificationResult {
+    Sent { id: u32, summary: String },
+    ActionInvoked { id: u32, action_key: String },
+    Closed { id: u32, reason: u32 },
+    Failed(String),
+}
+
+/// Client side of `org.freedesktop.Notifications`: runs `SendNotification`
+/// and `CloseNotification` and reports what the server signals back.
+pub struct NotificationsPlugin;
+
+impl Plugin for NotificationsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NotificationsBus>()
+            .init_resource::<ResultChannel<NotificationResult>>()
+            .init_resource::<SentIds>()
+            .add_event::<SendNotification>()
+            .add_event::<CloseNotification>()
+            .add_event::<NotificationSent>()
+            .add_event::<NotificationActionInvoked>()
+            .add_event::<NotificationClosed>()
+            .add_event::<NotificationError>()
+            .add_systems(Startup, init_bus_connection)
+            .add_systems(
+                Update,
+                (
+                    poll_bus_init,
+                    handle_notification_events.after(poll_bus_init),
+                    poll_notification_results.after(handle_notification_events),
+                ),
+            );
+    }
+}
+
+fn init_bus_connection(mut commands: Commands) {
+    let task = IoTaskPool::get().spawn(async {
+        match Connection::session().await {
+            Ok(connection) => Some(connection),
+            Err(e) => {
+                error!("Failed to connect to the session bus: {e}");
+                None
+            }
+        }
+    });
+    commands.insert_resource(BusInitTask(task));
+}
+
+fn poll_bus_init(
+    mut commands: Commands,
+    task: Option<ResMut<BusInitTask>>,
+    mut bus: ResMut<NotificationsBus>,
+    results: Res<ResultChannel<NotificationResult>>,
+    sent_ids: Res<SentIds>,
+) {
+    let Some(mut task) = task else {
+        return;
+    };
+    if let Some(connection) = block_on(future::poll_once(&mut task.0)) {
+        if let Some(connection) = &connection {
+            let connection = connection.clone();
+            let sender = results.sender();
+            let sent_ids = sent_ids.0.clone();
+            IoTaskPool::get()
+                .spawn(async move {
+                    if let Err(e) = follow_server_signals(&connection, &sender, &sent_ids).await {
+                        error!("Failed to follow notification signals: {e}");
+                    }
+                })
+                .detach();
+        }
+        bus.connection = connection;
+        commands.remove_resource::<BusInitTask>();
+    }
+}
+
+/// Forwards `ActionInvoked` and `NotificationClosed` of the notifications in
+/// `sent_ids` until the plugin is gone. Closed ones are taken out of it.
+async fn follow_server_signals(
+    connection: &Connection,
+    sender: &Sender<NotificationResult>,
+    sent_ids: &Mutex<HashSet<u32>>,
+) -> zbus::Result<()> {
+    let proxy = NotificationsProxy::new(connection).await?;
+    let mut invoked = proxy.receive_action_invoked().await?;
+    let mut closed = proxy.receive_notification_closed().await?;
+    loop {
+        let next_invoked = async {
+            let signal = invoked.next().await?;
+            Some(signal.args().map(|args| NotificationResult::ActionInvoked {
+                id: *args.id(),
+                action_key: args.action_key().clone(),
+            }))
+        };
+        let next_closed = async {
+            let signal = closed.next().await?;
+            Some(signal.args().map(|args| NotificationResult::Closed {
+                id: *args.id(),
+                reason: *args.reason(),
+            }))
+        };
+        match future::or(next_invoked, next_closed).await {
+            Some(Ok(result)) => {
+                let ours = match &result {
+                    NotificationResult::ActionInvoked { id, .. } => {
+                        sent_ids.lock().unwrap().contains(id)
+                    }
+                    NotificationResult::Closed { id, .. } => sent_ids.lock().unwrap().remove(id),
+                    _ => true,
+                };
+                if !ours {
+                    continue;
+                }
+                if sender.send(result).is_err() {
+                    return Ok(());
+                }
+            }
+            Some(Err(e)) => warn!("Invalid notification signal: {e}"),
+            None => return Ok(()),
+        }
+    }
+}
+
+fn handle_notification_events(
+    mut send_events: EventReader<SendNotification>,
+    mut close_events: EventReader<CloseNotification>,
+    bus: Res<NotificationsBus>,
+    results: Res<ResultChannel<NotificationResult>>,
+    sent_ids: Res<SentIds>,
+    mut error_writer: EventWriter<NotificationError>,
+) {
+    let pool = AsyncComputeTaskPool::get();
+    let Some(connection) = bus.connection.clone() else {
+        for _ in send_events.read() {
+            error_writer.write(NotificationError(
+                "the session bus is not available".to_string(),
+            ));
+        }
+        close_events.clear();
+        return;
+    };
+    for SendNotification(notification) in send_events.read() {
+        let connection = connection.clone();
+        let notification = notification.clone();
+        let sender = results.sender();
+        let sent_ids = sent_ids.0.clone();
+        pool.spawn(async move {
+            let result = match notify(&connection, &notification).await {
+                Ok(id) => {
+                    sent_ids.lock().unwrap().insert(id);
+                    NotificationResult::Sent {
+                        id,
+                        summary: notification.summary,
+                    }
+                }
+                Err(err) => {
+                    error!("failed to send notification: {err}");
+                    NotificationResult::Failed(err.to_string())
+                }
+            };
+            let _ = sender.send(result);
+        })
+        .detach();
+    }
+    for CloseNotification(id) in close_events.read() {
+        let connection = connection.clone();
+        let id = *id;
+        let sender = results.sender();
+        pool.spawn(async move {
+            let result = async {
+                NotificationsProxy::new(&connection)
+                    .await?
+                    .close_notification(id)
+                    .await
+            };
+            if let Err(err) = result.await {
+                error!("failed to close notification {id}: {err}");
+                let _ = sender.send(NotificationResult::Failed(err.to_string()));
+            }
+        })
+        .detach();
+    }
+}
+
+pub async fn notify(connection: &Connection, notification: &Notification) -> zbus::Result<u32> {
+    let proxy = NotificationsProxy::new(connection).await?;
+    let actions: Vec<&str> = notification
+        .actions
+        .iter()
+        .flat_map(|(key, label)| [key.as_str(), label.as_str()])
+        .collect();
+    let hints = HashMap::from([("urgency", Value::U8(notification.urgency.into()))]);
+    proxy
+        .notify(
+            &notification.app_name,
+            notification.replaces_id,
+            &notification.icon,
+            &notification.summary,
+            &notification.body,
+            &actions,
+            hints,
+            notification.expiry.to_millis(),
+        )
+        .await
+}
+
+fn poll_notification_results(
+    results: Res<ResultChannel<NotificationResult>>,
+    mut sent_writer: EventWriter<NotificationSent>,
+    mut invoked_writer: EventWriter<NotificationActionInvoked>,
+    mut closed_writer: EventWriter<NotificationClosed>,
+    mut error_writer: EventWriter<NotificationError>,
+) {
+    for result in results.drain() {
+        match result {
+            NotificationResult::Sent { id, summary } => {
+                sent_writer.write(NotificationSent { id, summary });
+            }
+            NotificationResult::ActionInvoked { id, action_key } => {
+                invoked_writer.write(NotificationActionInvoked { id, action_key });
+            }
+            NotificationResult::Closed { id, reason } => {
+                closed_writer.write(NotificationClosed {
+                    id,
+                    reason: CloseReason::from(reason),
+                });
+            }
+            NotificationResult::Failed(reason) => {
+                error_writer.write(NotificationError(reason));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expiry_round_trips_through_millis() {
+        assert_eq!(Expiry::Default.to_millis(), -1);
+        assert_eq!(Expiry::Never.to_millis(), 0);
+        assert_eq!(Expiry::After(Duration::from_millis(2500)).to_millis(), 2500);
+        assert_eq!(Expiry::from_millis(-1), Expiry::Default);
+        assert_eq!(Expiry::from_millis(-20), Expiry::Default);
+        assert_eq!(Expiry::from_millis(0), Expiry::Never);
+        assert_eq!(
+            Expiry::from_millis(2500),
+            Expiry::After(Duration::from_millis(2500))
+        );
+    }
+
+    #[test]
+    fn expiry_clamps_durations_to_millis() {
+        let too_long = Duration::from_millis(i32::MAX as u64 + 1000);
+        assert_eq!(Expiry::After(too_long).to_millis(), i32::MAX);
+        // Zero would mean never expiring
+        assert_eq!(Expiry::After(Duration::ZERO).to_millis(), 1);
+        assert_eq!(Expiry::After(Duration::from_micros(10)).to_millis(), 1);
+    }
+
+    #[test]
+    fn urgency_converts_to_and_from_u8() {
+        for urgency in [Urgency::Low, Urgency::Normal, Urgency::Critical] {
+            assert_eq!(Urgency::from(u8::from(urgency)), urgency);
+        }
+        assert_eq!(u8::from(Urgency::Critical), 2);
+        assert_eq!(Urgency::from(7), Urgency::Normal);
+    }
+
+    #[test]
+    fn close_reason_converts_to_and_from_u32() {
+        for reason in [
+            CloseReason::Expired,
+            CloseReason::Dismissed,
+            CloseReason::Closed,
+            CloseReason::Undefined,
+        ] {
+            assert_eq!(CloseReason::from(u32::from(reason)), reason);
+        }
+        assert_eq!(u32::from(CloseReason::Expired), 1);
+        assert_eq!(u32::from(CloseReason::Undefined), 4);
+        assert_eq!(CloseReason::from(0), CloseReason::Undefined);
+        assert_eq!(CloseReason::from(9), CloseReason::Undefined);
+    }
+}
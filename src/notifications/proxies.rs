@@ -0,0 +1,42 @@
+//! The `org.freedesktop.Notifications` D-Bus API, as used by the client.
+
+use std::collections::HashMap;
+
+use zbus::{Result as ZbusResult, proxy, zvariant::Value};
+
+pub const NOTIFICATIONS_SERVICE: &str = "org.freedesktop.Notifications";
+pub const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+
+pub const CLOSE_REASON_EXPIRED: u32 = 1;
+pub const CLOSE_REASON_DISMISSED: u32 = 2;
+pub const CLOSE_REASON_CLOSED: u32 = 3;
+
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+pub trait Notifications {
+    /// `actions` alternates keys and labels, `expire_timeout` is in
+    /// milliseconds with -1 for the server's default and 0 for never.
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> ZbusResult<u32>;
+
+    fn close_notification(&self, id: u32) -> ZbusResult<()>;
+
+    #[zbus(signal)]
+    fn notification_closed(&self, id: u32, reason: u32) -> ZbusResult<()>;
+
+    #[zbus(signal)]
+    fn action_invoked(&self, id: u32, action_key: String) -> ZbusResult<()>;
+}
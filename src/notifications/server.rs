@@ -0,0 +1,450 @@
+//! Optional notification server: owns `org.freedesktop.Notifications` on the
+//! session bus and shows what arrives as toasts with action buttons.
+
+use std::{collections::HashMap, time::Duration};
+
+use async_std::channel::{self, Receiver, Sender};
+use bevy::{
+    prelude::*,
+    tasks::{IoTaskPool, Task, block_on, futures_lite::future},
+};
+use zbus::{
+    Connection, connection, interface,
+    object_server::SignalContext,
+    zvariant::{OwnedValue, Value},
+};
+
+use super::{
+    CloseReason, Expiry, Urgency,
+    proxies::{NOTIFICATIONS_PATH, NOTIFICATIONS_SERVICE},
+    ui,
+};
+
+/// How long toasts of `Expiry::Default` notifications stay, critical ones stay
+/// until dismissed.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A notification received by the server.
+#[derive(Debug, Clone)]
+pub struct ReceivedNotification {
+    pub id: u32,
+    pub app_name: String,
+    pub summary: String,
+    pub body: String,
+    /// `(key, label)` pairs.
+    pub actions: Vec<(String, String)>,
+    pub urgency: Urgency,
+    pub expiry: Expiry,
+}
+
+impl ReceivedNotification {
+    /// How long the toast stays, None for until dismissed.
+    pub fn timeout(&self) -> Option<Duration> {
+        match (self.expiry, self.urgency) {
+            (Expiry::After(duration), _) => Some(duration),
+            (Expiry::Never, _) | (Expiry::Default, Urgency::Critical) => None,
+            (Expiry::Default, _) => Some(DEFAULT_TIMEOUT),
+        }
+    }
+}
+
+/// Turns the `actions` argument of `Notify`, alternating keys and labels, into
+/// `(key, label)` pairs. A key left without a label is dropped.
+fn pair_actions(actions: Vec<String>) -> Vec<(String, String)> {
+    let mut actions = actions.into_iter();
+    let mut pairs = Vec::new();
+    while let (Some(key), Some(label)) = (actions.next(), actions.next()) {
+        pairs.push((key, label));
+    }
+    pairs
+}
+
+/// Sent for every notification the server shows, including replacements.
+#[derive(Event, Debug, Clone)]
+pub struct NotificationReceived(pub ReceivedNotification);
+
+/// What the D-Bus interface hands over to Bevy.
+enum ServerMessage {
+    Show(ReceivedNotification),
+    Close(u32),
+}
+
+struct NotificationServer {
+    next_id: u32,
+    messages: Sender<ServerMessage>,
+}
+
+#[interface(name = "org.freedesktop.Notifications")]
+impl NotificationServer {
+    fn get_capabilities(&self) -> Vec<String> {
+        vec!["actions".to_string(), "body".to_string()]
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn notify(
+        &mut self,
+        app_name: String,
+        replaces_id: u32,
+        _app_icon: String,
+        summary: String,
+        body: String,
+        actions: Vec<String>,
+        hints: HashMap<String, OwnedValue>,
+        expire_timeout: i32,
+    ) -> u32 {
+        let id = if replaces_id != 0 {
+            replaces_id
+        } else {
+            self.next_id += 1;
+            self.next_id
+        };
+        let urgency = match hints.get("urgency").map(|value| &**value) {
+            Some(Value::U8(urgency)) => Urgency::from(*urgency),
+            _ => Urgency::Normal,
+        };
+        let notification = ReceivedNotification {
+            id,
+            app_name,
+            summary,
+            body,
+            actions: pair_actions(actions),
+            urgency,
+            expiry: Expiry::from_millis(expire_timeout),
+        };
+        let _ = self.messages.send(ServerMessage::Show(notification)).await;
+        id
+    }
+
+    async fn close_notification(&self, id: u32) {
+        let _ = self.messages.send(ServerMessage::Close(id)).await;
+    }
+
+    fn get_server_information(&self) -> (String, String, String, String) {
+        (
+            env!("CARGO_PKG_NAME").to_string(),
+            "mechanix".to_string(),
+            env!("CARGO_PKG_VERSION").to_string(),
+            "1.2".to_string(),
+        )
+    }
+
+    #[zbus(signal)]
+    async fn notification_closed(
+        ctxt: &SignalContext<'_>,
+        id: u32,
+        reason: u32,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn action_invoked(
+        ctxt: &SignalContext<'_>,
+        id: u32,
+        action_key: &str,
+    ) -> zbus::Result<()>;
+}
+
+/// Connection owning the server name, None until it is claimed or when
+/// another server already runs.
+#[derive(Resource, Default)]
+pub struct NotificationServerBus {
+    pub connection: Option<Connection>,
+}
+
+#[derive(Resource)]
+struct ServerInitTask(Task<Option<Connection>>);
+
+#[derive(Resource)]
+struct ServerMessages(Receiver<ServerMessage>);
+
+/// Notifications shown and not closed yet, with the timer closing them unless
+/// they stay until dismissed. Kept apart from the toasts, as there may be no
+/// toast area or several of them.
+#[derive(Resource, Default)]
+pub(crate) struct ShownNotifications(HashMap<u32, Option<Timer>>);
+
+/// Serves `org.freedesktop.Notifications` from the app, showing toasts in the
+/// areas spawned with `ui::spawn_toast_area`. Fails, with an error logged,
+/// when the desktop already runs a notification server.
+pub struct NotificationServerPlugin;
+
+impl Plugin for NotificationServerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NotificationServerBus>()
+            .init_resource::<ShownNotifications>()
+            .add_event::<NotificationReceived>()
+            .add_systems(Startup, start_server)
+            .add_systems(
+                Update,
+                (
+                    poll_server_init,
+                    receive_server_messages,
+                    ui::toast_button_system,
+                    expire_notifications,
+                ),
+            );
+    }
+}
+
+fn start_server(mut commands: Commands) {
+    let (sender, receiver) = channel::unbounded();
+    let server = NotificationServer {
+        next_id: 0,
+        messages: sender,
+    };
+    let task = IoTaskPool::get().spawn(async move {
+        let result = async {
+            connection::Builder::session()?
+                .name(NOTIFICATIONS_SERVICE)?
+                .serve_at(NOTIFICATIONS_PATH, server)?
+                .build()
+                .await
+        };
+        match result.await {
+            Ok(connection) => Some(connection),
+            Err(e) => {
+                error!("Failed to start the notification server: {e}");
+                None
+            }
+        }
+    });
+    commands.insert_resource(ServerInitTask(task));
+    commands.insert_resource(ServerMessages(receiver));
+}
+
+fn poll_server_init(
+    mut commands: Commands,
+    task: Option<ResMut<ServerInitTask>>,
+    mut bus: ResMut<NotificationServerBus>,
+) {
+    let Some(mut task) = task else {
+        return;
+    };
+    if let Some(connection) = block_on(future::poll_once(&mut task.0)) {
+        bus.connection = connection;
+        commands.remove_resource::<ServerInitTask>();
+    }
+}
+
+fn receive_server_messages(
+    mut commands: Commands,
+    messages: Res<ServerMessages>,
+    bus: Res<NotificationServerBus>,
+    mut shown: ResMut<ShownNotifications>,
+    areas: Query<(Entity, &ui::ToastArea)>,
+    toasts: Query<(Entity, &ui::NotificationToast)>,
+    mut writer: EventWriter<NotificationReceived>,
+) {
+    while let Ok(message) = messages.0.try_recv() {
+        match message {
+            ServerMessage::Show(notification) => {
+                // A replacement takes the place of the toasts it replaces,
+                // and starts its own timer
+                despawn_toasts(&mut commands, &toasts, notification.id);
+                let timer = notification
+                    .timeout()
+                    .map(|timeout| Timer::new(timeout, TimerMode::Once));
+                shown.0.insert(notification.id, timer);
+                for (area, toast_area) in areas.iter() {
+                    ui::spawn_toast(&mut commands, area, toast_area, &notification);
+                }
+                writer.write(NotificationReceived(notification));
+            }
+            ServerMessage::Close(id) => {
+                close_notification(
+                    &mut commands,
+                    &mut shown,
+                    &toasts,
+                    &bus,
+                    id,
+                    None,
+                    CloseReason::Closed,
+                );
+            }
+        }
+    }
+}
+
+fn expire_notifications(
+    mut commands: Commands,
+    time: Res<Time>,
+    bus: Res<NotificationServerBus>,
+    mut shown: ResMut<ShownNotifications>,
+    toasts: Query<(Entity, &ui::NotificationToast)>,
+) {
+    let mut expired = Vec::new();
+    for (id, timer) in shown.0.iter_mut() {
+        if let Some(timer) = timer
+            && timer.tick(time.delta()).finished()
+        {
+            expired.push(*id);
+        }
+    }
+    for id in expired {
+        close_notification(
+            &mut commands,
+            &mut shown,
+            &toasts,
+            &bus,
+            id,
+            None,
+            CloseReason::Expired,
+        );
+    }
+}
+
+fn despawn_toasts(
+    commands: &mut Commands,
+    toasts: &Query<(Entity, &ui::NotificationToast)>,
+    id: u32,
+) {
+    for (entity, toast) in toasts.iter() {
+        if toast.id == id {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Closes notification `id` if it is still shown: removes its toasts from
+/// every area and signals it once.
+pub(crate) fn close_notification(
+    commands: &mut Commands,
+    shown: &mut ShownNotifications,
+    toasts: &Query<(Entity, &ui::NotificationToast)>,
+    bus: &NotificationServerBus,
+    id: u32,
+    action_key: Option<String>,
+    reason: CloseReason,
+) {
+    if shown.0.remove(&id).is_none() {
+        return;
+    }
+    despawn_toasts(commands, toasts, id);
+    emit_closed(bus, id, action_key, reason);
+}
+
+/// Signals that notification `id` closed for `reason`, preceded by
+/// `ActionInvoked` when it closed because of an action.
+fn emit_closed(
+    bus: &NotificationServerBus,
+    id: u32,
+    action_key: Option<String>,
+    reason: CloseReason,
+) {
+    let Some(connection) = bus.connection.clone() else {
+        return;
+    };
+    IoTaskPool::get()
+        .spawn(async move {
+            let result = async {
+                let ctxt = SignalContext::new(&connection, NOTIFICATIONS_PATH)?;
+                if let Some(action_key) = &action_key {
+                    NotificationServer::action_invoked(&ctxt, id, action_key).await?;
+                }
+                NotificationServer::notification_closed(&ctxt, id, reason.into()).await
+            };
+            if let Err(e) = result.await {
+                error!("Failed to signal that notification {id} closed: {e}");
+            }
+        })
+        .detach();
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+
+    fn received(urgency: Urgency, expiry: Expiry) -> ReceivedNotification {
+        ReceivedNotification {
+            id: 1,
+            app_name: "test".into(),
+            summary: "Summary".into(),
+            body: String::new(),
+            actions: vec![],
+            urgency,
+            expiry,
+        }
+    }
+
+    #[test]
+    fn timeout_follows_expiry_and_urgency() {
+        let after = Expiry::After(Duration::from_secs(2));
+        assert_eq!(
+            received(Urgency::Normal, after).timeout(),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            received(Urgency::Critical, after).timeout(),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(received(Urgency::Low, Expiry::Never).timeout(), None);
+        assert_eq!(
+            received(Urgency::Normal, Expiry::Default).timeout(),
+            Some(DEFAULT_TIMEOUT)
+        );
+        assert_eq!(received(Urgency::Critical, Expiry::Default).timeout(), None);
+    }
+
+    #[test]
+    fn actions_pair_keys_with_labels() {
+        let actions = ["default", "Open", "reply", "Reply", "dangling"]
+            .map(String::from)
+            .to_vec();
+        assert_eq!(
+            pair_actions(actions),
+            vec![
+                ("default".to_string(), "Open".to_string()),
+                ("reply".to_string(), "Reply".to_string()),
+            ]
+        );
+        assert!(pair_actions(vec![]).is_empty());
+    }
+
+    #[test]
+    fn notifications_expire_without_toast_areas() {
+        let (sender, receiver) = channel::unbounded();
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                600,
+            )))
+            .init_resource::<NotificationServerBus>()
+            .init_resource::<ShownNotifications>()
+            .insert_resource(ServerMessages(receiver))
+            .add_event::<NotificationReceived>()
+            .add_systems(
+                Update,
+                (receive_server_messages, expire_notifications).chain(),
+            );
+
+        let mut expiring = received(Urgency::Normal, Expiry::After(Duration::from_secs(1)));
+        expiring.id = 1;
+        let mut critical = received(Urgency::Critical, Expiry::Default);
+        critical.id = 2;
+        sender.try_send(ServerMessage::Show(expiring)).unwrap();
+        sender.try_send(ServerMessage::Show(critical)).unwrap();
+        app.update();
+        let shown = |app: &App| {
+            let mut ids: Vec<u32> = app
+                .world()
+                .resource::<ShownNotifications>()
+                .0
+                .keys()
+                .copied()
+                .collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(shown(&app), vec![1, 2]);
+
+        for _ in 0..10 {
+            app.update();
+        }
+        assert_eq!(shown(&app), vec![2]);
+
+        sender.try_send(ServerMessage::Close(2)).unwrap();
+        app.update();
+        assert!(shown(&app).is_empty());
+    }
+}
@@ -0,0 +1,178 @@
+//! Toasts for the notifications received by the server.
+
+use bevy::prelude::*;
+
+use super::{
+    CloseReason, Urgency,
+    server::{NotificationServerBus, ReceivedNotification, ShownNotifications, close_notification},
+};
+
+const TOAST_BACKGROUND: Color = Color::srgb(0.12, 0.12, 0.14);
+const CRITICAL_BORDER: Color = Color::srgb(0.85, 0.25, 0.2);
+const NORMAL_BUTTON: Color = Color::srgb(0.2, 0.2, 0.22);
+const HOVERED_BUTTON: Color = Color::srgb(0.3, 0.3, 0.32);
+
+/// Column toasts are stacked into, newest last. Holds the font used for them.
+#[derive(Component)]
+pub struct ToastArea {
+    font: Handle<Font>,
+}
+
+/// A shown notification. Every toast area has one per notification.
+#[derive(Component)]
+pub struct NotificationToast {
+    pub id: u32,
+}
+
+/// Invokes `action_key` on the toast's notification, or only dismisses it.
+#[derive(Component)]
+pub(crate) struct NotificationToastButton {
+    id: u32,
+    action_key: Option<String>,
+}
+
+pub fn spawn_toast_area(commands: &mut Commands, font: Handle<Font>, node: Node) -> Entity {
+    commands
+        .spawn((
+            Node {
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(8.0),
+                ..node
+            },
+            GlobalZIndex(30),
+            ToastArea { font },
+        ))
+        .id()
+}
+
+pub(crate) fn spawn_toast(
+    commands: &mut Commands,
+    area: Entity,
+    toast_area: &ToastArea,
+    notification: &ReceivedNotification,
+) {
+    let font = |font_size| TextFont {
+        font: toast_area.font.clone(),
+        font_size,
+        ..default()
+    };
+    let border = if notification.urgency == Urgency::Critical {
+        CRITICAL_BORDER
+    } else {
+        TOAST_BACKGROUND
+    };
+    let id = notification.id;
+    commands.entity(area).with_children(|parent| {
+        parent
+            .spawn((
+                Node {
+                    width: Val::Px(320.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    border: UiRect::all(Val::Px(2.0)),
+                    ..default()
+                },
+                BackgroundColor(TOAST_BACKGROUND),
+                BorderColor(border),
+                BorderRadius::all(Val::Px(6.0)),
+                NotificationToast { id },
+            ))
+            .with_children(|toast| {
+                toast
+                    .spawn(Node {
+                        justify_content: JustifyContent::SpaceBetween,
+                        column_gap: Val::Px(8.0),
+                        ..default()
+                    })
+                    .with_children(|header| {
+                        header.spawn((
+                            Text::new(&notification.summary),
+                            font(18.0),
+                            TextColor(Color::srgb(0.95, 0.95, 0.95)),
+                        ));
+                        spawn_toast_button(header, font(16.0), id, "x", None);
+                    });
+                if !notification.body.is_empty() {
+                    toast.spawn((
+                        Text::new(&notification.body),
+                        font(16.0),
+                        TextColor(Color::srgb(0.75, 0.75, 0.75)),
+                    ));
+                }
+                if !notification.actions.is_empty() {
+                    toast
+                        .spawn(Node {
+                            column_gap: Val::Px(6.0),
+                            ..default()
+                        })
+                        .with_children(|actions| {
+                            for (key, label) in notification.actions.iter() {
+                                spawn_toast_button(
+                                    actions,
+                                    font(16.0),
+                                    id,
+                                    label,
+                                    Some(key.clone()),
+                                );
+                            }
+                        });
+                }
+            });
+    });
+}
+
+fn spawn_toast_button(
+    parent: &mut ChildSpawnerCommands,
+    font: TextFont,
+    id: u32,
+    label: &str,
+    action_key: Option<String>,
+) {
+    parent
+        .spawn((
+            Button,
+            Node {
+                padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+                ..default()
+            },
+            BorderRadius::all(Val::Px(4.0)),
+            BackgroundColor(NORMAL_BUTTON),
+            NotificationToastButton { id, action_key },
+        ))
+        .with_child((
+            Text::new(label),
+            font,
+            TextColor(Color::srgb(0.9, 0.9, 0.9)),
+        ));
+}
+
+pub(crate) fn toast_button_system(
+    mut commands: Commands,
+    mut buttons: Query<
+        (&Interaction, &NotificationToastButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+    toasts: Query<(Entity, &NotificationToast)>,
+    mut shown: ResMut<ShownNotifications>,
+    bus: Res<NotificationServerBus>,
+) {
+    for (interaction, button, mut color) in buttons.iter_mut() {
+        color.0 = match interaction {
+            Interaction::Hovered | Interaction::Pressed => HOVERED_BUTTON,
+            Interaction::None => NORMAL_BUTTON,
+        };
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        close_notification(
+            &mut commands,
+            &mut shown,
+            &toasts,
+            &bus,
+            button.id,
+            button.action_key.clone(),
+            CloseReason::Dismissed,
+        );
+    }
+}
@@ -0,0 +1,290 @@
+pub mod proxies;
+pub mod ui;
+
+use std::{fmt, time::Duration};
+
+use bevy::{
+    prelude::*,
+    tasks::{
+        IoTaskPool,
+        futures_lite::{StreamExt, future},
+    },
+};
+use proxies::{
+    DISPLAY_DEVICE_PATH, UP_DEVICE_STATE_CHARGING, UP_DEVICE_STATE_DISCHARGING,
+    UP_DEVICE_STATE_EMPTY, UP_DEVICE_STATE_FULLY_CHARGED, UP_DEVICE_STATE_PENDING_CHARGE,
+    UP_DEVICE_STATE_PENDING_DISCHARGE, UPOWER_PATH, UPOWER_SERVICE, UPowerDeviceProxy, UPowerProxy,
+};
+use tokio::sync::watch;
+use zbus::{Connection, fdo::PropertiesProxy};
+
+use crate::bus::{SystemBus, SystemBusPlugin};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BatteryState {
+    #[default]
+    Unknown,
+    Charging,
+    Discharging,
+    Empty,
+    FullyCharged,
+    /// Plugged in, but not charging yet.
+    PendingCharge,
+    PendingDischarge,
+}
+
+impl From<u32> for BatteryState {
+    fn from(value: u32) -> Self {
+        match value {
+            UP_DEVICE_STATE_CHARGING => BatteryState::Charging,
+            UP_DEVICE_STATE_DISCHARGING => BatteryState::Discharging,
+            UP_DEVICE_STATE_EMPTY => BatteryState::Empty,
+            UP_DEVICE_STATE_FULLY_CHARGED => BatteryState::FullyCharged,
+            UP_DEVICE_STATE_PENDING_CHARGE => BatteryState::PendingCharge,
+            UP_DEVICE_STATE_PENDING_DISCHARGE => BatteryState::PendingDischarge,
+            _ => BatteryState::Unknown,
+        }
+    }
+}
+
+/// What UPower currently reports for the display device, updated by the plugin
+/// as it changes.
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct PowerState {
+    /// False on machines without a battery.
+    pub present: bool,
+    pub percentage: f64,
+    pub state: BatteryState,
+    pub time_to_empty: Option<Duration>,
+    pub time_to_full: Option<Duration>,
+    pub on_battery: bool,
+}
+
+impl fmt::Display for PowerState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.present {
+            return write!(f, "No battery");
+        }
+        write!(f, "{:.0}%", self.percentage)?;
+        let remaining = match self.state {
+            BatteryState::Charging => self.time_to_full.map(|time| (time, "until full")),
+            BatteryState::Discharging => self.time_to_empty.map(|time| (time, "left")),
+            BatteryState::FullyCharged => return write!(f, " full"),
+            _ => None,
+        };
+        if let Some((time, what)) = remaining {
+            let minutes = time.as_secs() / 60;
+            write!(f, " {}:{:02} {}", minutes / 60, minutes % 60, what)?;
+        } else if self.state == BatteryState::Charging {
+            write!(f, " charging")?;
+        }
+        Ok(())
+    }
+}
+
+/// Sent whenever `PowerState` changes.
+#[derive(Event, Debug, Clone)]
+pub struct PowerStateChanged(pub PowerState);
+
+pub async fn read_power_state(connection: &Connection) -> zbus::Result<PowerState> {
+    let upower = UPowerProxy::new(connection).await?;
+    let device = UPowerDeviceProxy::new(connection).await?;
+    let seconds = |seconds: i64| (seconds > 0).then(|| Duration::from_secs(seconds as u64));
+    Ok(PowerState {
+        present: device.is_present().await?,
+        percentage: device.percentage().await?,
+        state: BatteryState::from(device.state().await?),
+        time_to_empty: seconds(device.time_to_empty().await?),
+        time_to_full: seconds(device.time_to_full().await?),
+        on_battery: upower.on_battery().await?,
+    })
+}
+
+/// Sends the current state, then a new one whenever UPower or its display
+/// device report changed properties. Returns once `tx` has no receivers.
+pub async fn follow_power_state(
+    connection: &Connection,
+    tx: &watch::Sender<PowerState>,
+) -> zbus::Result<()> {
+    let mut upower_changes = PropertiesProxy::builder(connection)
+        .destination(UPOWER_SERVICE)?
+        .path(UPOWER_PATH)?
+        .build()
+        .await?
+        .receive_properties_changed()
+        .await?;
+    let mut device_changes = PropertiesProxy::builder(connection)
+        .destination(UPOWER_SERVICE)?
+        .path(DISPLAY_DEVICE_PATH)?
+        .build()
+        .await?
+        .receive_properties_changed()
+        .await?;
+
+    loop {
+        match read_power_state(connection).await {
+            Ok(state) => {
+                tx.send_if_modified(|current| {
+                    let modified = *current != state;
+                    *current = state;
+                    modified
+                });
+            }
+            Err(e) => error!("Error reading the power state: {e}"),
+        }
+        if tx.is_closed() {
+            return Ok(());
+        }
+        let upower_changed = async { upower_changes.next().await.is_some() };
+        let device_changed = async { device_changes.next().await.is_some() };
+        if !future::or(upower_changed, device_changed).await {
+            return Ok(());
+        }
+    }
+}
+
+#[derive(Resource)]
+struct PowerStateReceiver(watch::Receiver<PowerState>);
+
+/// Follows UPower's display device into `PowerState`.
+pub struct PowerPlugin;
+
+impl Plugin for PowerPlugin {
+    fn build(&self, app: &mut App) {
+        SystemBusPlugin::add_once(app);
+        app.init_resource::<PowerState>()
+            .add_event::<PowerStateChanged>()
+            .add_systems(
+                Update,
+                (
+                    spawn_power_follower,
+                    sync_power_state.after(spawn_power_follower),
+                    ui::update_battery_indicator.after(sync_power_state),
+                ),
+            );
+    }
+}
+
+/// Starts following UPower once the shared system bus is connected.
+fn spawn_power_follower(
+    mut commands: Commands,
+    bus: Res<SystemBus>,
+    receiver: Option<Res<PowerStateReceiver>>,
+) {
+    if receiver.is_some() {
+        return;
+    }
+    let Some(connection) = bus.connection.clone() else {
+        return;
+    };
+    let (tx, rx) = watch::channel(PowerState::default());
+    IoTaskPool::get()
+        .spawn(async move {
+            if let Err(e) = follow_power_state(&connection, &tx).await {
+                error!("Failed to follow the power state: {e}");
+            }
+        })
+        .detach();
+    commands.insert_resource(PowerStateReceiver(rx));
+}
+
+fn sync_power_state(
+    receiver: Option<ResMut<PowerStateReceiver>>,
+    mut state: ResMut<PowerState>,
+    mut writer: EventWriter<PowerStateChanged>,
+) {
+    let Some(mut receiver) = receiver else {
+        return;
+    };
+    if receiver.0.has_changed().unwrap_or(false) {
+        *state = receiver.0.borrow_and_update().clone();
+        writer.write(PowerStateChanged(state.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn battery(percentage: f64, state: BatteryState) -> PowerState {
+        PowerState {
+            present: true,
+            percentage,
+            state,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn battery_state_from_upower_values() {
+        assert_eq!(BatteryState::from(0), BatteryState::Unknown);
+        assert_eq!(
+            BatteryState::from(UP_DEVICE_STATE_CHARGING),
+            BatteryState::Charging
+        );
+        assert_eq!(
+            BatteryState::from(UP_DEVICE_STATE_DISCHARGING),
+            BatteryState::Discharging
+        );
+        assert_eq!(
+            BatteryState::from(UP_DEVICE_STATE_EMPTY),
+            BatteryState::Empty
+        );
+        assert_eq!(
+            BatteryState::from(UP_DEVICE_STATE_FULLY_CHARGED),
+            BatteryState::FullyCharged
+        );
+        assert_eq!(
+            BatteryState::from(UP_DEVICE_STATE_PENDING_CHARGE),
+            BatteryState::PendingCharge
+        );
+        assert_eq!(
+            BatteryState::from(UP_DEVICE_STATE_PENDING_DISCHARGE),
+            BatteryState::PendingDischarge
+        );
+        assert_eq!(BatteryState::from(42), BatteryState::Unknown);
+    }
+
+    #[test]
+    fn display_without_battery() {
+        assert_eq!(PowerState::default().to_string(), "No battery");
+    }
+
+    #[test]
+    fn display_remaining_time_in_whole_minutes() {
+        let discharging = PowerState {
+            time_to_empty: Some(Duration::from_secs(2 * 3600 + 5 * 60 + 59)),
+            ..battery(54.6, BatteryState::Discharging)
+        };
+        assert_eq!(discharging.to_string(), "55% 2:05 left");
+        let almost_empty = PowerState {
+            time_to_empty: Some(Duration::from_secs(59)),
+            ..battery(1.0, BatteryState::Discharging)
+        };
+        assert_eq!(almost_empty.to_string(), "1% 0:00 left");
+        let charging = PowerState {
+            time_to_full: Some(Duration::from_secs(45 * 60)),
+            ..battery(80.0, BatteryState::Charging)
+        };
+        assert_eq!(charging.to_string(), "80% 0:45 until full");
+    }
+
+    #[test]
+    fn display_full_and_charging_without_estimate() {
+        let full = PowerState {
+            // Ignored once full
+            time_to_full: Some(Duration::from_secs(60)),
+            ..battery(100.0, BatteryState::FullyCharged)
+        };
+        assert_eq!(full.to_string(), "100% full");
+        assert_eq!(
+            battery(30.0, BatteryState::Charging).to_string(),
+            "30% charging"
+        );
+        assert_eq!(battery(30.0, BatteryState::Discharging).to_string(), "30%");
+        assert_eq!(
+            battery(30.0, BatteryState::PendingCharge).to_string(),
+            "30%"
+        );
+    }
+}
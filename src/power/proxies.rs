@@ -0,0 +1,49 @@
+//! Subset of the `org.freedesktop.UPower` D-Bus API used by the plugin.
+
+use zbus::{Result as ZbusResult, proxy};
+
+pub const UPOWER_SERVICE: &str = "org.freedesktop.UPower";
+pub const UPOWER_PATH: &str = "/org/freedesktop/UPower";
+/// Composite device summing up every battery, as shown by desktop panels.
+pub const DISPLAY_DEVICE_PATH: &str = "/org/freedesktop/UPower/devices/DisplayDevice";
+
+pub const UP_DEVICE_STATE_CHARGING: u32 = 1;
+pub const UP_DEVICE_STATE_DISCHARGING: u32 = 2;
+pub const UP_DEVICE_STATE_EMPTY: u32 = 3;
+pub const UP_DEVICE_STATE_FULLY_CHARGED: u32 = 4;
+pub const UP_DEVICE_STATE_PENDING_CHARGE: u32 = 5;
+pub const UP_DEVICE_STATE_PENDING_DISCHARGE: u32 = 6;
+
+#[proxy(
+    interface = "org.freedesktop.UPower",
+    default_service = "org.freedesktop.UPower",
+    default_path = "/org/freedesktop/UPower"
+)]
+pub trait UPower {
+    #[zbus(property)]
+    fn on_battery(&self) -> ZbusResult<bool>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.UPower.Device",
+    default_service = "org.freedesktop.UPower",
+    default_path = "/org/freedesktop/UPower/devices/DisplayDevice"
+)]
+pub trait UPowerDevice {
+    #[zbus(property)]
+    fn is_present(&self) -> ZbusResult<bool>;
+
+    #[zbus(property)]
+    fn percentage(&self) -> ZbusResult<f64>;
+
+    #[zbus(property)]
+    fn state(&self) -> ZbusResult<u32>;
+
+    /// Seconds, 0 when unknown.
+    #[zbus(property)]
+    fn time_to_empty(&self) -> ZbusResult<i64>;
+
+    /// Seconds, 0 when unknown.
+    #[zbus(property)]
+    fn time_to_full(&self) -> ZbusResult<i64>;
+}
@@ -0,0 +1,109 @@
+//! Ready-made battery indicator for the power plugin.
+
+use bevy::prelude::*;
+
+use super::{BatteryState, PowerState};
+
+const BATTERY_OUTLINE: Color = Color::srgb(0.8, 0.8, 0.8);
+const BATTERY_LOW: Color = Color::srgb(0.9, 0.25, 0.2);
+const BATTERY_MEDIUM: Color = Color::srgb(0.95, 0.8, 0.3);
+const BATTERY_OK: Color = Color::srgb(0.3, 0.85, 0.4);
+const BATTERY_CHARGING: Color = Color::srgb(0.3, 0.6, 0.95);
+
+/// Battery indicator driven by [`PowerState`].
+#[derive(Component)]
+pub struct BatteryIndicator;
+
+#[derive(Component)]
+pub(crate) struct BatteryFill;
+
+#[derive(Component)]
+pub(crate) struct BatteryText;
+
+/// Spawns a battery outline filled up to the charge, colored by level or blue
+/// while charging, followed by the [`PowerState`] text.
+pub fn spawn_battery_indicator(commands: &mut Commands, font: Handle<Font>, node: Node) -> Entity {
+    commands
+        .spawn((
+            Node {
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(8.0),
+                ..node
+            },
+            BatteryIndicator,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(Node {
+                    align_items: AlignItems::Center,
+                    ..default()
+                })
+                .with_children(|battery| {
+                    battery
+                        .spawn((
+                            Node {
+                                width: Val::Px(40.0),
+                                height: Val::Px(20.0),
+                                border: UiRect::all(Val::Px(2.0)),
+                                padding: UiRect::all(Val::Px(2.0)),
+                                ..default()
+                            },
+                            BorderColor(BATTERY_OUTLINE),
+                            BorderRadius::all(Val::Px(3.0)),
+                        ))
+                        .with_child((
+                            Node {
+                                width: Val::Percent(0.0),
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                            BackgroundColor(BATTERY_OK),
+                            BatteryFill,
+                        ));
+                    // Terminal
+                    battery.spawn((
+                        Node {
+                            width: Val::Px(3.0),
+                            height: Val::Px(8.0),
+                            ..default()
+                        },
+                        BackgroundColor(BATTERY_OUTLINE),
+                    ));
+                });
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font,
+                    font_size: 20.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                BatteryText,
+            ));
+        })
+        .id()
+}
+
+pub(crate) fn update_battery_indicator(
+    state: Res<PowerState>,
+    mut fills: Query<(&mut Node, &mut BackgroundColor), With<BatteryFill>>,
+    mut texts: Query<&mut Text, With<BatteryText>>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    let percentage = if state.present { state.percentage } else { 0.0 };
+    let color = match state.state {
+        BatteryState::Charging | BatteryState::PendingCharge => BATTERY_CHARGING,
+        _ if percentage < 20.0 => BATTERY_LOW,
+        _ if percentage < 50.0 => BATTERY_MEDIUM,
+        _ => BATTERY_OK,
+    };
+    for (mut node, mut background) in fills.iter_mut() {
+        node.width = Val::Percent(percentage.clamp(0.0, 100.0) as f32);
+        background.0 = color;
+    }
+    for mut text in texts.iter_mut() {
+        text.0 = state.to_string();
+    }
+}
@@ -0,0 +1,38 @@
+//! The channel through which a plugin's action tasks hand their outcomes
+//! back to the Bevy world.
+
+use std::sync::{
+    Mutex,
+    mpsc::{self, Receiver, Sender},
+};
+
+use bevy::prelude::*;
+
+/// Every action task gets a clone of the sender, and a system of the plugin
+/// turns what arrives into events on the next frame.
+#[derive(Resource)]
+pub(crate) struct ResultChannel<T: Send + 'static> {
+    sender: Sender<T>,
+    receiver: Mutex<Receiver<T>>,
+}
+
+impl<T: Send + 'static> ResultChannel<T> {
+    pub(crate) fn sender(&self) -> Sender<T> {
+        self.sender.clone()
+    }
+
+    /// What arrived since the last call.
+    pub(crate) fn drain(&self) -> Vec<T> {
+        self.receiver.lock().unwrap().try_iter().collect()
+    }
+}
+
+impl<T: Send + 'static> Default for ResultChannel<T> {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            sender,
+            receiver: Mutex::new(receiver),
+        }
+    }
+}
@@ -0,0 +1,17 @@
+//! Helpers shared by the tests of the plugins.
+
+use std::time::Duration;
+
+use bevy::prelude::{App, World};
+
+/// Runs frames until `done` holds, for up to ten seconds.
+pub(crate) fn update_until(app: &mut App, done: impl Fn(&World) -> bool) -> bool {
+    for _ in 0..1000 {
+        app.update();
+        if done(app.world()) {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    false
+}
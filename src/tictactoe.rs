@@ -0,0 +1,223 @@
+//! Tic-tac-toe rules shared by the `tictactoe` binary, independent of Bevy.
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Player {
+    X,
+    O,
+}
+
+impl Player {
+    pub fn other(self) -> Self {
+        match self {
+            Player::X => Player::O,
+            Player::O => Player::X,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Player::X => "X",
+            Player::O => "O",
+        }
+    }
+}
+
+impl fmt::Display for Player {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    InProgress,
+    Won(Player),
+    Drawn,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveError {
+    OutOfBounds,
+    Occupied,
+    GameOver,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::OutOfBounds => f.write_str("cell is outside the board"),
+            MoveError::Occupied => f.write_str("cell is already taken"),
+            MoveError::GameOver => f.write_str("game is already over"),
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+const LINES: [[(usize, usize); 3]; 8] = [
+    [(0, 0), (0, 1), (0, 2)],
+    [(1, 0), (1, 1), (1, 2)],
+    [(2, 0), (2, 1), (2, 2)],
+    [(0, 0), (1, 0), (2, 0)],
+    [(0, 1), (1, 1), (2, 1)],
+    [(0, 2), (1, 2), (2, 2)],
+    [(0, 0), (1, 1), (2, 2)],
+    [(0, 2), (1, 1), (2, 0)],
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Board {
+    cells: [[Option<Player>; 3]; 3],
+    turn: Player,
+    outcome: Outcome,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self {
+            cells: [[None; 3]; 3],
+            turn: Player::X,
+            outcome: Outcome::InProgress,
+        }
+    }
+}
+
+impl Board {
+    pub fn cell(&self, row: usize, col: usize) -> Option<Player> {
+        self.cells.get(row)?.get(col).copied().flatten()
+    }
+
+    /// The player whose move is next.
+    pub fn turn(&self) -> Player {
+        self.turn
+    }
+
+    pub fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+
+    /// Places the current player's mark and passes the turn on. Returns the
+    /// outcome after the move.
+    pub fn play(&mut self, row: usize, col: usize) -> Result<Outcome, MoveError> {
+        if self.outcome != Outcome::InProgress {
+            return Err(MoveError::GameOver);
+        }
+        let cell = self
+            .cells
+            .get_mut(row)
+            .and_then(|r| r.get_mut(col))
+            .ok_or(MoveError::OutOfBounds)?;
+        if cell.is_some() {
+            return Err(MoveError::Occupied);
+        }
+        *cell = Some(self.turn);
+        self.turn = self.turn.other();
+
+        self.outcome = if let Some(winner) = self.winner() {
+            Outcome::Won(winner)
+        } else if self.is_full() {
+            Outcome::Drawn
+        } else {
+            Outcome::InProgress
+        };
+        Ok(self.outcome)
+    }
+
+    pub fn winner(&self) -> Option<Player> {
+        LINES.iter().find_map(|line| {
+            let [a, b, c] = line.map(|(row, col)| self.cells[row][col]);
+            if a.is_some() && a == b && b == c { a } else { None }
+        })
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.cells.iter().flatten().all(Option::is_some)
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play_all(board: &mut Board, moves: &[(usize, usize)]) -> Outcome {
+        let mut outcome = Outcome::InProgress;
+        for &(row, col) in moves {
+            outcome = board.play(row, col).unwrap();
+        }
+        outcome
+    }
+
+    #[test]
+    fn players_alternate() {
+        let mut board = Board::default();
+        board.play(0, 0).unwrap();
+        board.play(1, 1).unwrap();
+        assert_eq!(board.cell(0, 0), Some(Player::X));
+        assert_eq!(board.cell(1, 1), Some(Player::O));
+        assert_eq!(board.turn(), Player::X);
+    }
+
+    #[test]
+    fn every_line_wins() {
+        for line in LINES {
+            let mut board = Board::default();
+            let others: Vec<_> = (0..3)
+                .flat_map(|r| (0..3).map(move |c| (r, c)))
+                .filter(|cell| !line.contains(cell))
+                .collect();
+            // X plays the line, O fills in elsewhere without completing a line
+            // of its own before X does.
+            let mut outcome = Outcome::InProgress;
+            for (i, &(row, col)) in line.iter().enumerate() {
+                outcome = board.play(row, col).unwrap();
+                if i < 2 {
+                    board.play(others[i].0, others[i].1).unwrap();
+                }
+            }
+            assert_eq!(outcome, Outcome::Won(Player::X), "line {:?}", line);
+        }
+    }
+
+    #[test]
+    fn full_board_without_line_is_drawn() {
+        let mut board = Board::default();
+        let outcome = play_all(
+            &mut board,
+            &[(0, 0), (0, 1), (0, 2), (1, 1), (1, 0), (1, 2), (2, 1), (2, 0), (2, 2)],
+        );
+        assert_eq!(outcome, Outcome::Drawn);
+    }
+
+    #[test]
+    fn rejects_invalid_moves() {
+        let mut board = Board::default();
+        assert_eq!(board.play(3, 0), Err(MoveError::OutOfBounds));
+        board.play(0, 0).unwrap();
+        assert_eq!(board.play(0, 0), Err(MoveError::Occupied));
+        play_all(&mut board, &[(1, 0), (0, 1), (1, 1), (0, 2)]);
+        assert_eq!(board.play(2, 2), Err(MoveError::GameOver));
+    }
+
+    #[test]
+    fn rejected_move_keeps_turn() {
+        let mut board = Board::default();
+        board.play(0, 0).unwrap();
+        let before = board.clone();
+        assert!(board.play(0, 0).is_err());
+        assert_eq!(board, before);
+    }
+
+    #[test]
+    fn reset_clears_board() {
+        let mut board = Board::default();
+        board.play(2, 2).unwrap();
+        board.reset();
+        assert_eq!(board, Board::default());
+    }
+}
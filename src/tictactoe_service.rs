@@ -0,0 +1,77 @@
+use tokio::sync::mpsc;
+use zbus::{Result as ZbusResult, connection, interface, object_server::SignalContext, proxy};
+
+pub const SERVICE_NAME: &str = "org.mechanix.services.TicTacToe";
+pub const OBJECT_PATH: &str = "/org/mechanix/services/TicTacToe";
+
+/// Game events published on the session bus by the `tictactoe` binary.
+#[derive(Debug, Clone)]
+pub enum GameEvent {
+    MovePlayed { row: u8, col: u8, player: String },
+    GameWon { player: String },
+    GameDrawn,
+}
+
+#[derive(Clone, Copy)]
+pub struct TicTacToeBusInterface {}
+
+#[interface(name = "org.mechanix.services.TicTacToe")]
+impl TicTacToeBusInterface {
+    #[zbus(signal)]
+    async fn move_played(
+        &self,
+        ctxt: &SignalContext<'_>,
+        row: u8,
+        col: u8,
+        player: &str,
+    ) -> Result<(), zbus::Error>;
+
+    #[zbus(signal)]
+    async fn game_won(&self, ctxt: &SignalContext<'_>, player: &str) -> Result<(), zbus::Error>;
+
+    #[zbus(signal)]
+    async fn game_drawn(&self, ctxt: &SignalContext<'_>) -> Result<(), zbus::Error>;
+}
+
+/// Client side of the interface, for observers that want to follow games.
+#[proxy(
+    interface = "org.mechanix.services.TicTacToe",
+    default_service = "org.mechanix.services.TicTacToe",
+    default_path = "/org/mechanix/services/TicTacToe"
+)]
+trait TicTacToe {
+    #[zbus(signal)]
+    async fn move_played(&self, row: u8, col: u8, player: String) -> ZbusResult<()>;
+
+    #[zbus(signal)]
+    async fn game_won(&self, player: String) -> ZbusResult<()>;
+
+    #[zbus(signal)]
+    async fn game_drawn(&self) -> ZbusResult<()>;
+}
+
+/// Claims the service name on the session bus and emits a signal for every
+/// event received on `rx`. Returns once all senders have been dropped.
+pub async fn publish_game_events(mut rx: mpsc::Receiver<GameEvent>) -> ZbusResult<()> {
+    let bus = TicTacToeBusInterface {};
+    let conn = connection::Builder::session()?
+        .name(SERVICE_NAME)?
+        .serve_at(OBJECT_PATH, bus)?
+        .build()
+        .await?;
+    let ctxt = SignalContext::new(&conn, OBJECT_PATH)?;
+
+    while let Some(event) = rx.recv().await {
+        let result = match &event {
+            GameEvent::MovePlayed { row, col, player } => {
+                bus.move_played(&ctxt, *row, *col, player).await
+            }
+            GameEvent::GameWon { player } => bus.game_won(&ctxt, player).await,
+            GameEvent::GameDrawn => bus.game_drawn(&ctxt).await,
+        };
+        if let Err(e) = result {
+            println!("Error sending {:?}: {}", event, e);
+        }
+    }
+    Ok(())
+}
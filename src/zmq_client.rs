@@ -0,0 +1,265 @@
+//! ZeroMQ client that feeds colors from a color server into a channel.
+
+use std::{thread, time::Duration};
+
+use crate::{color::parse_rgb, color_bridge::ColorSender};
+
+pub const DEFAULT_ENDPOINT: &str = "tcp://localhost:5555";
+pub const DEFAULT_TOPIC: &str = "color";
+
+#[derive(Debug, Clone)]
+pub enum ZmqMode {
+    /// Subscribe to a PUB socket. Messages are `<topic> RGB(r, g, b)`. If
+    /// nothing arrives within `idle_timeout` the socket is recreated.
+    Sub { topic: String, idle_timeout: Duration },
+    /// Poll a REP socket. A request that is not answered within `timeout`
+    /// drops the socket and reconnects.
+    Req { timeout: Duration, interval: Duration },
+}
+
+#[derive(Debug, Clone)]
+pub struct ZmqClientConfig {
+    pub endpoint: String,
+    pub mode: ZmqMode,
+    pub min_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for ZmqClientConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: DEFAULT_ENDPOINT.to_string(),
+            mode: ZmqMode::Req {
+                timeout: Duration::from_secs(2),
+                interval: Duration::from_secs(1),
+            },
+            min_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Exponential backoff between reconnect attempts.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    min: Duration,
+    max: Duration,
+    current: Duration,
+}
+
+impl Backoff {
+    pub fn new(min: Duration, max: Duration) -> Self {
+        Self {
+            min,
+            max,
+            current: min,
+        }
+    }
+
+    /// Returns the delay to wait now and doubles the next one, up to `max`.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.current;
+        self.current = (self.current * 2).min(self.max);
+        delay
+    }
+
+    pub fn reset(&mut self) {
+        self.current = self.min;
+    }
+}
+
+/// Spawns the client on its own thread. The thread exits once `tx` is closed.
+pub fn spawn_zmq_color_client(
+    config: ZmqClientConfig,
+    tx: ColorSender,
+) -> thread::JoinHandle<()> {
+    spawn_zmq_color_client_in(zmq::Context::new(), config, tx)
+}
+
+/// Same as [`spawn_zmq_color_client`] but on an existing context, which is
+/// required for `inproc://` endpoints.
+pub fn spawn_zmq_color_client_in(
+    context: zmq::Context,
+    config: ZmqClientConfig,
+    tx: ColorSender,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || run_client(&context, config, tx))
+}
+
+fn run_client(context: &zmq::Context, config: ZmqClientConfig, tx: ColorSender) {
+    let mut backoff = Backoff::new(config.min_backoff, config.max_backoff);
+    // Checked before every attempt, as a client that can't connect never
+    // finds out through a failed send
+    while !tx.is_closed() {
+        let result = match &config.mode {
+            ZmqMode::Sub {
+                topic,
+                idle_timeout,
+            } => run_sub(context, &config.endpoint, topic, *idle_timeout, &tx, &mut backoff),
+            ZmqMode::Req { timeout, interval } => {
+                run_req(context, &config.endpoint, *timeout, *interval, &tx, &mut backoff)
+            }
+        };
+        match result {
+            // Receiver is gone, nobody is interested anymore
+            Ok(()) => return,
+            Err(_) if tx.is_closed() => return,
+            Err(e) => {
+                let delay = backoff.next_delay();
+                println!(
+                    "ZeroMQ client error on {}: {}, retrying in {:?}",
+                    config.endpoint, e, delay
+                );
+                thread::sleep(delay);
+            }
+        }
+    }
+}
+
+fn run_req(
+    context: &zmq::Context,
+    endpoint: &str,
+    timeout: Duration,
+    interval: Duration,
+    tx: &ColorSender,
+    backoff: &mut Backoff,
+) -> zmq::Result<()> {
+    let requester = context.socket(zmq::REQ)?;
+    requester.set_linger(0)?;
+    requester.set_rcvtimeo(timeout.as_millis() as i32)?;
+    requester.set_sndtimeo(timeout.as_millis() as i32)?;
+    requester.connect(endpoint)?;
+    println!("Connected to {}", endpoint);
+
+    loop {
+        requester.send("Hello", 0)?;
+        // A REQ socket can't be reused after a missed reply, so a timeout
+        // (EAGAIN) bubbles up and the socket is recreated.
+        let msg = requester.recv_msg(0)?;
+        backoff.reset();
+        if !forward_color(msg.as_str(), None, tx) {
+            return Ok(());
+        }
+        thread::sleep(interval);
+    }
+}
+
+fn run_sub(
+    context: &zmq::Context,
+    endpoint: &str,
+    topic: &str,
+    idle_timeout: Duration,
+    tx: &ColorSender,
+    backoff: &mut Backoff,
+) -> zmq::Result<()> {
+    let subscriber = context.socket(zmq::SUB)?;
+    subscriber.set_linger(0)?;
+    subscriber.set_rcvtimeo(idle_timeout.as_millis() as i32)?;
+    subscriber.connect(endpoint)?;
+    subscriber.set_subscribe(topic.as_bytes())?;
+    println!("Subscribed to {:?} on {}", topic, endpoint);
+
+    loop {
+        let msg = subscriber.recv_msg(0)?;
+        backoff.reset();
+        if !forward_color(msg.as_str(), Some(topic), tx) {
+            return Ok(());
+        }
+    }
+}
+
+/// Parses and forwards one message. Returns `false` once the receiver is gone.
+fn forward_color(msg: Option<&str>, topic: Option<&str>, tx: &ColorSender) -> bool {
+    let Some(msg) = msg else {
+        println!("Ignoring non UTF-8 message");
+        return true;
+    };
+    let payload = match topic {
+        Some(topic) => msg.strip_prefix(topic).unwrap_or(msg),
+        None => msg,
+    };
+    match parse_rgb(payload) {
+        Ok(color) => tx.send(color).is_ok(),
+        Err(e) => {
+            println!("Ignoring {:?}: {}", msg, e);
+            true
+        }
+    }
+}
+
+/// Runs `serve` and a client in `mode` on one context, so they can talk over
+/// the `inproc://` `endpoint`, and waits for `count` colors. Returns how many
+/// arrived once both have stopped.
+#[cfg(test)]
+pub(crate) fn inproc_round_trip(
+    endpoint: &str,
+    mode: ZmqMode,
+    count: usize,
+    serve: impl FnOnce(&zmq::Context) -> zmq::Result<()> + Send + 'static,
+) -> usize {
+    use crate::color_bridge::{DeliveryPolicy, color_bridge};
+
+    let context = zmq::Context::new();
+    let server_context = context.clone();
+    let server = thread::spawn(move || serve(&server_context));
+
+    let (tx, mut rx) = color_bridge(DeliveryPolicy::DrainAll, 64);
+    let client = spawn_zmq_color_client_in(
+        context,
+        ZmqClientConfig {
+            endpoint: endpoint.to_string(),
+            mode,
+            min_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(50),
+        },
+        tx,
+    );
+
+    let mut received = 0;
+    for _ in 0..200 {
+        received += rx.receive().len();
+        if received >= count {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    server.join().unwrap().unwrap();
+    // The server may be gone already, dropping the receiver must still stop
+    // the client's retries
+    drop(rx);
+    client.join().unwrap();
+    received
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_max_and_resets() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_millis(350));
+        let delays: Vec<u128> = (0..4).map(|_| backoff.next_delay().as_millis()).collect();
+        assert_eq!(delays, vec![100, 200, 350, 350]);
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn req_round_trip_over_inproc() {
+        let endpoint = "inproc://zmq-client-req";
+        let mode = ZmqMode::Req {
+            timeout: Duration::from_millis(200),
+            interval: Duration::from_millis(10),
+        };
+        let received = inproc_round_trip(endpoint, mode, 3, move |context| {
+            let replier = context.socket(zmq::REP)?;
+            replier.bind(endpoint)?;
+            for _ in 0..3 {
+                replier.recv_msg(0)?;
+                replier.send("RGB(0.1, 0.2, 0.3)", 0)?;
+            }
+            Ok(())
+        });
+        assert_eq!(received, 3);
+    }
+}
@@ -0,0 +1,119 @@
+//! ZeroMQ color server, the counterpart of [`crate::zmq_client`].
+
+use std::{thread, time::Duration};
+
+use crate::{color::BarColor, zmq_client::DEFAULT_TOPIC};
+
+pub const DEFAULT_BIND_ENDPOINT: &str = "tcp://*:5555";
+
+#[derive(Debug, Clone)]
+pub enum ServerMode {
+    /// Answer every request with a fresh random color.
+    Rep,
+    /// Publish `<topic> RGB(r, g, b)` every interval.
+    Pub { topic: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct ZmqServerConfig {
+    pub endpoint: String,
+    pub mode: ServerMode,
+    /// Delay between published colors. Unused in REP mode, where the client
+    /// sets the pace.
+    pub interval: Duration,
+    /// Stop after this many colors were sent, mostly useful for scripted runs.
+    pub max_messages: Option<u64>,
+}
+
+impl Default for ZmqServerConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: DEFAULT_BIND_ENDPOINT.to_string(),
+            mode: ServerMode::Rep,
+            interval: Duration::from_secs(1),
+            max_messages: None,
+        }
+    }
+}
+
+impl ZmqServerConfig {
+    pub fn publisher(endpoint: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            mode: ServerMode::Pub {
+                topic: DEFAULT_TOPIC.to_string(),
+            },
+            ..Default::default()
+        }
+    }
+}
+
+/// Binds and serves colors on the calling thread until `max_messages` is
+/// reached or a socket error occurs. Pass the same context as the client to
+/// use `inproc://` endpoints.
+pub fn run_zmq_color_server(context: &zmq::Context, config: &ZmqServerConfig) -> zmq::Result<()> {
+    match &config.mode {
+        ServerMode::Rep => serve_rep(context, config),
+        ServerMode::Pub { topic } => serve_pub(context, config, topic),
+    }
+}
+
+fn serve_rep(context: &zmq::Context, config: &ZmqServerConfig) -> zmq::Result<()> {
+    let responder = context.socket(zmq::REP)?;
+    responder.set_linger(0)?;
+    responder.bind(&config.endpoint)?;
+    println!("Serving colors on {}", config.endpoint);
+
+    let mut sent = 0;
+    while config.max_messages.is_none_or(|max| sent < max) {
+        let _request = responder.recv_msg(0)?;
+        let color_str = BarColor::random().to_string();
+        responder.send(color_str.as_str(), 0)?;
+        println!("Sent {}", color_str);
+        sent += 1;
+    }
+    Ok(())
+}
+
+fn serve_pub(context: &zmq::Context, config: &ZmqServerConfig, topic: &str) -> zmq::Result<()> {
+    let publisher = context.socket(zmq::PUB)?;
+    publisher.set_linger(0)?;
+    publisher.bind(&config.endpoint)?;
+    println!("Publishing colors on {} with topic {:?}", config.endpoint, topic);
+
+    let mut sent = 0;
+    while config.max_messages.is_none_or(|max| sent < max) {
+        thread::sleep(config.interval);
+        let color_str = format!("{} {}", topic, BarColor::random());
+        publisher.send(color_str.as_str(), 0)?;
+        println!("Published {}", color_str);
+        sent += 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zmq_client::{ZmqMode, inproc_round_trip};
+
+    #[test]
+    fn pub_round_trip_over_inproc() {
+        let endpoint = "inproc://zmq-server-pub";
+        let mode = ZmqMode::Sub {
+            topic: DEFAULT_TOPIC.to_string(),
+            idle_timeout: Duration::from_millis(200),
+        };
+        // Messages published before the subscription was set up are lost,
+        // later ones must arrive
+        let received = inproc_round_trip(endpoint, mode, 5, move |context| {
+            let config = ZmqServerConfig {
+                interval: Duration::from_millis(20),
+                max_messages: Some(50),
+                ..ZmqServerConfig::publisher(endpoint)
+            };
+            run_zmq_color_server(context, &config)
+        });
+        assert!(received >= 5, "received {received} colors");
+    }
+}